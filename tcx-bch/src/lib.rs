@@ -27,8 +27,8 @@ mod tests {
 
     use serde_json::Value;
 
-    use tcx_chain::KeystoreGuard;
     use tcx_chain::{HdKeystore, Keystore, Metadata};
+    use tcx_chain::{KeystoreGuard, DEFAULT_MAX_UNLOCK_FAILURES};
     use tcx_constants::CurveType;
     use tcx_constants::{CoinInfo, TEST_MNEMONIC, TEST_PASSWORD};
 
@@ -48,7 +48,12 @@ mod tests {
             network: "MAINNET".to_string(),
             seg_wit: "NONE".to_string(),
         };
-        let mut guard = KeystoreGuard::unlock_by_password(&mut keystore, TEST_PASSWORD).unwrap();
+        let mut guard = KeystoreGuard::unlock_by_password(
+            &mut keystore,
+            TEST_PASSWORD,
+            DEFAULT_MAX_UNLOCK_FAILURES,
+        )
+        .unwrap();
 
         let _ = guard
             .keystore_mut()
@@ -85,7 +90,12 @@ mod tests {
             seg_wit: "NONE".to_string(),
         };
 
-        let mut guard = KeystoreGuard::unlock_by_password(&mut keystore, TEST_PASSWORD).unwrap();
+        let mut guard = KeystoreGuard::unlock_by_password(
+            &mut keystore,
+            TEST_PASSWORD,
+            DEFAULT_MAX_UNLOCK_FAILURES,
+        )
+        .unwrap();
 
         let _ = guard
             .keystore_mut()
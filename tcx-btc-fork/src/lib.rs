@@ -5,6 +5,10 @@ pub mod transaction;
 
 use core::result;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tcx_chain::{Account, Address};
+use tcx_constants::coin_info::coin_info_from_param;
+use tcx_primitive::{Derive, DerivePath, DeterministicType, TypedDeterministicPublicKey};
 
 #[macro_use]
 extern crate failure;
@@ -19,7 +23,7 @@ extern crate tcx_chain;
 pub type Result<T> = result::Result<T, failure::Error>;
 
 pub use signer::{BitcoinForkSinger, BtcForkSegWitTransaction, BtcForkTransaction};
-pub use transaction::{BtcForkSignedTxOutput, BtcForkTxInput, Utxo};
+pub use transaction::{BtcForkSignedTxOutput, BtcForkTxInput, BtcForkUnsignedTxOutput, Utxo};
 
 pub use address::{BtcForkAddress, PubKeyScript, WifDisplay};
 pub use signer::ScriptPubKeyComponent;
@@ -42,3 +46,34 @@ pub struct ExternalAddress {
     pub addr_type: String,
     pub derived_path: String,
 }
+
+impl ExternalAddress {
+    /// The receive address at `0/{external_idx}` derived from the `ext_pub_key` of the account,
+    /// so no password is needed
+    pub fn from_account<A: Address>(
+        account: &Account,
+        external_idx: u32,
+    ) -> Result<ExternalAddress> {
+        tcx_ensure!(
+            !account.ext_pub_key.is_empty(),
+            tcx_chain::Error::CannotDeriveKey
+        );
+        let coin_info = coin_info_from_param(&account.coin, &account.network, &account.seg_wit)?;
+
+        let derived_path = format!("0/{}", external_idx);
+        let xpub = TypedDeterministicPublicKey::from_hex(
+            DeterministicType::BIP32,
+            account.curve,
+            &account.ext_pub_key,
+        )?;
+        let public_key = xpub
+            .derive(DerivePath::from_str(&derived_path)?.into_iter())?
+            .public_key();
+
+        Ok(ExternalAddress {
+            address: A::from_public_key(&public_key, &coin_info)?,
+            addr_type: "EXTERNAL".to_string(),
+            derived_path,
+        })
+    }
+}
@@ -15,7 +15,7 @@ use tcx_primitive::{
     PublicKey, TypedDeterministicPublicKey,
 };
 
-use crate::transaction::{BtcForkSignedTxOutput, BtcForkTxInput, Utxo};
+use crate::transaction::{BtcForkSignedTxOutput, BtcForkTxInput, BtcForkUnsignedTxOutput, Utxo};
 use bitcoin::util::bip143::SighashComponents;
 use bitcoin_hashes::hash160;
 use bitcoin_hashes::hex::FromHex as HashFromHex;
@@ -49,12 +49,7 @@ impl<S: ScriptPubKeyComponent + Address, T: BitcoinTransactionSignComponent>
         address: &str,
         tx: &BitcoinForkSinger<S, T>,
     ) -> Result<BtcForkSignedTxOutput> {
-        let change_address = if self.determinable() {
-            let dpk = self.find_deterministic_public_key(symbol, address)?;
-            tx.change_address(&dpk)?
-        } else {
-            S::address_script_pub_key(&address)?
-        };
+        let change_address = tx.change_script_pubkey(self, symbol, address)?;
 
         let mut sks = vec![];
 
@@ -90,6 +85,25 @@ impl<S: ScriptPubKeyComponent + Address, T: BitcoinTransactionSignComponent>
         }
     }
 
+    /// the change goes back to the account, a watch only keystore of an xpub works like a hd
+    /// one without private keys
+    pub fn change_script_pubkey(
+        &self,
+        keystore: &mut Keystore,
+        symbol: &str,
+        address: &str,
+    ) -> Result<Script> {
+        if keystore.determinable() {
+            let dpk = keystore.find_deterministic_public_key(symbol, address)?;
+            return self.change_address(&dpk);
+        }
+
+        match keystore.find_deterministic_public_key(symbol, address) {
+            Ok(dpk) => self.change_address(&dpk),
+            Err(_) => S::address_script_pub_key(&address),
+        }
+    }
+
     fn receive_script_pubkey(&self) -> Result<Script> {
         S::address_script_pub_key(&self.tx_input.to)
     }
@@ -168,19 +182,35 @@ impl<S: ScriptPubKeyComponent + Address, T: BitcoinTransactionSignComponent>
         tx_inputs
     }
 
-    pub fn sign_transaction(
-        &self,
-        keys: &[impl PrivateKey],
-        change_addr_pubkey: Script,
-    ) -> Result<BtcForkSignedTxOutput> {
+    fn transaction(&self, change_addr_pubkey: Script) -> Result<Transaction> {
         let tx_outs = self.tx_outs(change_addr_pubkey)?;
         let tx_inputs = self.tx_inputs();
-        let tx = Transaction {
+        Ok(Transaction {
             version: T::tx_version(),
             lock_time: 0,
             input: tx_inputs,
             output: tx_outs,
-        };
+        })
+    }
+
+    /// the transaction with empty script sigs and witnesses, for the keystores can't sign
+    pub fn unsigned_transaction(
+        &self,
+        change_addr_pubkey: Script,
+    ) -> Result<BtcForkUnsignedTxOutput> {
+        let tx = self.transaction(change_addr_pubkey)?;
+
+        Ok(BtcForkUnsignedTxOutput {
+            raw_tx: serialize(&tx).to_hex(),
+        })
+    }
+
+    pub fn sign_transaction(
+        &self,
+        keys: &[impl PrivateKey],
+        change_addr_pubkey: Script,
+    ) -> Result<BtcForkSignedTxOutput> {
+        let tx = self.transaction(change_addr_pubkey)?;
 
         let signed_tx = T::sign_inputs(&tx, &self.tx_input.unspents, &keys)?;
         let tx_bytes = serialize(&signed_tx);
@@ -366,7 +396,7 @@ mod tests {
     use super::*;
 
     use super::BitcoinForkSinger;
-    use tcx_chain::{Keystore, TransactionSigner};
+    use tcx_chain::{Keystore, TransactionSigner, DEFAULT_MAX_UNLOCK_FAILURES};
     use tcx_constants::coin_info::coin_info_from_param;
     use tcx_primitive::Secp256k1PrivateKey;
 
@@ -408,6 +438,11 @@ mod tests {
             .sign_transaction(&vec![prv_key], change_addr.script_pubkey())
             .unwrap();
         assert_eq!(expected.signature, "01000000015884e5db9de218238671572340b207ee85b628074e7e467096c267266baf77a4000000006a473044022029063983b2537e4aa15ee838874269a6ba6f5280297f92deb5cd56d2b2db7e8202207e1581f73024a48fce1100ed36a1a48f6783026736de39a4dd40a1ccc75f651101210223078d2942df62c45621d209fab84ea9a7a23346201b7727b9b45a29c4e76f5effffffff0220a10700000000001976a9147821c0a3768aa9d1a37e16cf76002aef5373f1a888ac801a0600000000001976a914073b7eae2823efa349e3b9155b8a735526463a0f88ac00000000");
+
+        let unsigned = tran
+            .unsigned_transaction(change_addr.script_pubkey())
+            .unwrap();
+        assert_eq!(unsigned.raw_tx, "01000000015884e5db9de218238671572340b207ee85b628074e7e467096c267266baf77a40000000000ffffffff0220a10700000000001976a9147821c0a3768aa9d1a37e16cf76002aef5373f1a888ac801a0600000000001976a914073b7eae2823efa349e3b9155b8a735526463a0f88ac00000000");
     }
 
     #[test]
@@ -444,7 +479,7 @@ mod tests {
             };
 
         let mut keystore = Keystore::from_json(keystore_json).unwrap();
-        let _ = keystore.unlock_by_password("imtoken1");
+        let _ = keystore.unlock_by_password("imtoken1", DEFAULT_MAX_UNLOCK_FAILURES);
         let expected = keystore
             .sign_transaction("LITECOIN", "mkeNU5nVnozJiaACDELLCsVUc8Wxoh1rQN", &tran)
             .unwrap();
@@ -502,7 +537,7 @@ mod tests {
             };
 
         let mut keystore = Keystore::from_json(keystore_json).unwrap();
-        let _ = keystore.unlock_by_password("imtoken1");
+        let _ = keystore.unlock_by_password("imtoken1", DEFAULT_MAX_UNLOCK_FAILURES);
         let expected = keystore
             .sign_transaction("LITECOIN", "mkeNU5nVnozJiaACDELLCsVUc8Wxoh1rQN", &tran)
             .unwrap();
@@ -547,7 +582,7 @@ mod tests {
             };
 
         let mut keystore = Keystore::from_json(keystore_json).unwrap();
-        let _ = keystore.unlock_by_password("imtoken1");
+        let _ = keystore.unlock_by_password("imtoken1", DEFAULT_MAX_UNLOCK_FAILURES);
         let expected = keystore
             .sign_transaction("LITECOIN", "mkeNU5nVnozJiaACDELLCsVUc8Wxoh1rQN", &tran)
             .unwrap();
@@ -592,7 +627,7 @@ mod tests {
             };
 
         let mut keystore = Keystore::from_json(keystore_json).unwrap();
-        let _ = keystore.unlock_by_password("imtoken1");
+        let _ = keystore.unlock_by_password("imtoken1", DEFAULT_MAX_UNLOCK_FAILURES);
         let ret =
             keystore.sign_transaction("LITECOIN", "mkeNU5nVnozJiaACDELLCsVUc8Wxoh1rQN", &tran);
         assert!(ret.is_err());
@@ -636,7 +671,7 @@ mod tests {
             };
 
         let mut keystore = Keystore::from_json(keystore_json).unwrap();
-        let _ = keystore.unlock_by_password("imtoken1");
+        let _ = keystore.unlock_by_password("imtoken1", DEFAULT_MAX_UNLOCK_FAILURES);
         let ret =
             keystore.sign_transaction("LITECOIN", "mkeNU5nVnozJiaACDELLCsVUc8Wxoh1rQN", &tran);
         assert!(ret.is_err());
@@ -4,46 +4,84 @@
 // - https://www.bitcoincash.org/spec/transaction.html
 
 /// FUNCTION: sign_tx(SignParam{input: BtcForkTxInput}): BtcForkSignedTxOutput
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Utxo {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub tx_hash: std::string::String,
     #[prost(int32, tag = "2")]
+    #[serde(default)]
     pub vout: i32,
     #[prost(int64, tag = "3")]
+    #[serde(default)]
+    #[serde(with = "tcx_proto::json::int64")]
     pub amount: i64,
     #[prost(string, tag = "4")]
+    #[serde(default)]
     pub address: std::string::String,
     #[prost(string, tag = "5")]
+    #[serde(default)]
     pub script_pub_key: std::string::String,
     #[prost(string, tag = "6")]
+    #[serde(default)]
     pub derived_path: std::string::String,
     #[prost(int64, tag = "7")]
+    #[serde(default)]
+    #[serde(with = "tcx_proto::json::int64")]
     pub sequence: i64,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct BtcForkTxInput {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub to: std::string::String,
     #[prost(int64, tag = "2")]
+    #[serde(default)]
+    #[serde(with = "tcx_proto::json::int64")]
     pub amount: i64,
     #[prost(message, repeated, tag = "3")]
+    #[serde(default)]
     pub unspents: ::std::vec::Vec<Utxo>,
     #[prost(int64, tag = "4")]
+    #[serde(default)]
+    #[serde(with = "tcx_proto::json::int64")]
     pub fee: i64,
     #[prost(uint32, tag = "5")]
+    #[serde(default)]
     pub change_address_index: u32,
     #[prost(string, tag = "6")]
+    #[serde(default)]
     pub change_address: std::string::String,
     #[prost(string, tag = "7")]
+    #[serde(default)]
     pub network: std::string::String,
     #[prost(string, tag = "8")]
+    #[serde(default)]
     pub seg_wit: std::string::String,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct BtcForkSignedTxOutput {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub signature: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub tx_hash: std::string::String,
 }
+/// FUNCTION: btc_fork_prepare_tx(SignParam{input: BtcForkTxInput}): BtcForkUnsignedTxOutput
+///
+/// the unsigned transaction of a watch only wallet, it is signed elsewhere
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcForkUnsignedTxOutput {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub raw_tx: std::string::String,
+}
@@ -12,8 +12,27 @@ impl<'a> Drop for KeystoreGuard<'a> {
 }
 
 impl<'a> KeystoreGuard<'a> {
-    pub fn unlock_by_password(ks: &'a mut Keystore, password: &str) -> Result<KeystoreGuard<'a>> {
-        ks.unlock_by_password(password)?;
+    /// unlock under the throttling of `Keystore::unlock_by_password`
+    pub fn unlock_by_password(
+        ks: &'a mut Keystore,
+        password: &str,
+        max_failures: u32,
+    ) -> Result<KeystoreGuard<'a>> {
+        ks.unlock_by_password(password, max_failures)?;
+
+        Ok(KeystoreGuard { keystore: ks })
+    }
+
+    /// guard a keystore unlocked by the caller, it's locked again when the guard drops
+    pub fn from_unlocked(ks: &'a mut Keystore) -> KeystoreGuard<'a> {
+        KeystoreGuard { keystore: ks }
+    }
+
+    pub fn unlock_by_derived_key(
+        ks: &'a mut Keystore,
+        derived_key: &str,
+    ) -> Result<KeystoreGuard<'a>> {
+        ks.unlock_by_derived_key(derived_key)?;
 
         Ok(KeystoreGuard { keystore: ks })
     }
@@ -1,11 +1,11 @@
-use bip39::{Language, Mnemonic, Seed};
+use bip39::{Mnemonic, Seed};
 
 use uuid::Uuid;
 
 use super::Account;
 use super::Address;
 use super::Result;
-use super::{Error, Metadata};
+use super::{Error, Metadata, MnemonicLanguage};
 
 use crate::keystore::{transform_mnemonic_error, Store};
 
@@ -14,15 +14,17 @@ use std::collections::HashMap;
 use std::str::FromStr;
 use tcx_constants::CoinInfo;
 use tcx_crypto::hash::dsha256;
-use tcx_crypto::{Crypto, Pbkdf2Params};
+use tcx_crypto::{Cipher, Crypto, Kdf, Secret, Zeroizing};
 use tcx_primitive::{
-    generate_mnemonic, get_account_path, Derive, DerivePath, DeterministicType, ToHex,
-    TypedDeterministicPrivateKey, TypedDeterministicPublicKey, TypedPrivateKey,
+    generate_mnemonic_with_language, get_account_path, Derive, DerivePath, DeterministicType,
+    ToHex, TypedDeterministicPrivateKey, TypedDeterministicPublicKey, TypedPrivateKey,
 };
 
+/// the secrets of an unlocked keystore, they are wiped when it's locked
 struct Cache {
-    mnemonic: String,
-    seed: Vec<u8>,
+    mnemonic: Secret<String>,
+    passphrase: Secret<String>,
+    seed: Secret<Vec<u8>>,
     keys: HashMap<String, TypedDeterministicPrivateKey>,
 }
 
@@ -32,9 +34,16 @@ pub struct HdKeystore {
 }
 
 pub fn key_hash_from_mnemonic(mnemonic: &str) -> Result<String> {
-    let mn =
-        Mnemonic::from_phrase(mnemonic, Language::English).map_err(transform_mnemonic_error)?;
-    let seed = Seed::new(&mn, "");
+    key_hash_from_mnemonic_with_passphrase(mnemonic, "")
+}
+
+/// the hash of the seed, so the same mnemonic with different passphrases are different wallets
+pub fn key_hash_from_mnemonic_with_passphrase(mnemonic: &str, passphrase: &str) -> Result<String> {
+    let mnemonic = MnemonicLanguage::normalize(mnemonic);
+    let language = MnemonicLanguage::detect(&mnemonic)?;
+    let mn = Mnemonic::from_phrase(&mnemonic, language.bip39_language())
+        .map_err(transform_mnemonic_error)?;
+    let seed = Seed::new(&mn, passphrase);
 
     let bytes = dsha256(seed.as_bytes())[..20].to_vec();
     Ok(hex::encode(bytes))
@@ -55,16 +64,44 @@ impl HdKeystore {
         HdKeystore { store, cache: None }
     }
 
+    /// the kdf runs once for the mnemonic and the passphrase
     pub(crate) fn unlock_by_password(&mut self, password: &str) -> Result<()> {
-        let mnemonic_bytes = self.store.crypto.decrypt(password)?;
-        let mnemonic_str = String::from_utf8(mnemonic_bytes)?;
+        let derived_key = Zeroizing::new(self.store.crypto.generate_derived_key(password)?);
+        tcx_ensure!(
+            self.store.crypto.verify_derived_key(&derived_key),
+            tcx_crypto::Error::PasswordIncorrect
+        );
+        self.unlock_by_derived_key(&derived_key)
+    }
+
+    pub(crate) fn unlock_by_derived_key(&mut self, derived_key: &[u8]) -> Result<()> {
+        let mnemonic_bytes = Zeroizing::new(self.store.crypto.decrypt_by_derived_key(derived_key)?);
+        let passphrase_bytes = match &self.store.enc_passphrase {
+            Some(enc_pair) => self
+                .store
+                .crypto
+                .decrypt_enc_pair_by_derived_key(derived_key, enc_pair)?,
+            None => vec![],
+        };
+        self.unlock(mnemonic_bytes, Zeroizing::new(passphrase_bytes))
+    }
 
-        let mnemonic = Mnemonic::from_phrase(&mnemonic_str, Language::English)
+    fn unlock(
+        &mut self,
+        mnemonic_bytes: Zeroizing<Vec<u8>>,
+        passphrase_bytes: Zeroizing<Vec<u8>>,
+    ) -> Result<()> {
+        let mnemonic_str = Secret::new(std::str::from_utf8(&mnemonic_bytes)?.to_owned());
+        let passphrase = Secret::new(std::str::from_utf8(&passphrase_bytes)?.to_owned());
+
+        let language = self.store.meta.mnemonic_language.unwrap_or_default();
+        let mnemonic = Mnemonic::from_phrase(&mnemonic_str, language.bip39_language())
             .map_err(transform_mnemonic_error)?;
 
         self.cache = Some(Cache {
             mnemonic: mnemonic_str,
-            seed: bip39::Seed::new(&mnemonic, &"").as_bytes().to_vec(),
+            seed: Secret::new(bip39::Seed::new(&mnemonic, &passphrase).as_bytes().to_vec()),
+            passphrase,
             keys: HashMap::new(),
         });
 
@@ -85,6 +122,12 @@ impl HdKeystore {
         Ok(cache.mnemonic.to_string())
     }
 
+    pub(crate) fn passphrase(&self) -> Result<String> {
+        let cache = self.cache.as_ref().ok_or(Error::KeystoreLocked)?;
+
+        Ok(cache.passphrase.to_string())
+    }
+
     pub(crate) fn find_private_key(&self, symbol: &str, address: &str) -> Result<TypedPrivateKey> {
         let cache = self.cache.as_ref().ok_or(Error::KeystoreLocked)?;
 
@@ -157,25 +200,73 @@ impl HdKeystore {
     }
 
     pub fn new(password: &str, meta: Metadata) -> HdKeystore {
-        let mnemonic = generate_mnemonic();
+        Self::new_with_kdf(password, meta, Kdf::default(), Cipher::default(), "")
+    }
 
-        Self::from_mnemonic(&mnemonic, password, meta).unwrap()
+    pub fn new_with_kdf(
+        password: &str,
+        meta: Metadata,
+        kdf: Kdf,
+        cipher: Cipher,
+        passphrase: &str,
+    ) -> HdKeystore {
+        let language = meta.mnemonic_language.unwrap_or_default();
+        let mnemonic = generate_mnemonic_with_language(language.bip39_language());
+
+        Self::from_mnemonic_with_kdf(&mnemonic, password, meta, kdf, cipher, passphrase).unwrap()
     }
 
     pub fn from_mnemonic(mnemonic: &str, password: &str, meta: Metadata) -> Result<HdKeystore> {
-        let mnemonic: &str = &mnemonic.split_whitespace().collect::<Vec<&str>>().join(" ");
+        Self::from_mnemonic_with_kdf(
+            mnemonic,
+            password,
+            meta,
+            Kdf::default(),
+            Cipher::default(),
+            "",
+        )
+    }
+
+    /// the wordlist is `meta.mnemonic_language` if it's set, otherwise it's detected
+    pub fn from_mnemonic_with_kdf(
+        mnemonic: &str,
+        password: &str,
+        mut meta: Metadata,
+        kdf: Kdf,
+        cipher: Cipher,
+        passphrase: &str,
+    ) -> Result<HdKeystore> {
+        let mnemonic: &str = &MnemonicLanguage::normalize(mnemonic);
+        let language = match meta.mnemonic_language {
+            Some(language) => {
+                language.validate(mnemonic)?;
+                language
+            }
+            None => MnemonicLanguage::detect(mnemonic)?,
+        };
+        meta.mnemonic_language = Some(language);
 
-        let key_hash = key_hash_from_mnemonic(mnemonic)?;
+        let key_hash = key_hash_from_mnemonic_with_passphrase(mnemonic, passphrase)?;
 
-        let crypto: Crypto<Pbkdf2Params> = Crypto::new(password, mnemonic.as_bytes());
+        let crypto: Crypto<Kdf> =
+            Crypto::new_with_cipher(password, mnemonic.as_bytes(), kdf, cipher);
+        // the empty passphrase isn't stored, so the keystore is the same as before passphrases
+        let enc_passphrase = if passphrase.is_empty() {
+            None
+        } else {
+            Some(crypto.derive_enc_pair(password, passphrase.as_bytes())?)
+        };
         Ok(HdKeystore {
             store: Store {
                 key_hash,
                 crypto,
+                enc_passphrase,
                 id: Uuid::new_v4().to_hyphenated().to_string(),
                 version: Self::VERSION,
                 active_accounts: vec![],
                 meta,
+                failed_unlocks: 0,
+                last_failed_unlock: 0,
             },
 
             cache: None,
@@ -213,6 +304,9 @@ impl HdKeystore {
             network: coin_info.network.to_string(),
             ext_pub_key,
             seg_wit: coin_info.seg_wit.to_string(),
+            label: "".to_string(),
+            tags: vec![],
+            hidden: false,
         };
 
         if let Some(_) = self
@@ -267,6 +361,7 @@ mod tests {
             password_hint: String::new(),
             timestamp: metadata_default_time(),
             source: Source::Mnemonic,
+            mnemonic_language: None,
         };
 
         assert_eq!(meta.name, expected.name);
@@ -333,6 +428,9 @@ mod tests {
             seg_wit: "NONE".to_string(),
             curve: CurveType::SECP256k1,
             coin: "BITCOIN".to_string(),
+            label: "".to_string(),
+            tags: vec![],
+            hidden: false,
         };
 
         assert_eq!(acc, expected);
@@ -362,6 +460,92 @@ mod tests {
         assert_eq!(format!("{}", wrong_password_err), "password_incorrect");
     }
 
+    #[test]
+    pub fn from_mnemonic_with_passphrase() {
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let mut keystore = HdKeystore::from_mnemonic_with_kdf(
+            mnemonic,
+            TEST_PASSWORD,
+            Metadata::default(),
+            Kdf::default(),
+            Cipher::default(),
+            "TREZOR",
+        )
+        .unwrap();
+        assert_eq!(
+            keystore.store.key_hash,
+            key_hash_from_mnemonic_with_passphrase(mnemonic, "TREZOR").unwrap()
+        );
+        assert_ne!(
+            keystore.store.key_hash,
+            key_hash_from_mnemonic(mnemonic).unwrap()
+        );
+        assert!(keystore.store.enc_passphrase.is_some());
+
+        let seed = "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04";
+        keystore.unlock_by_password(TEST_PASSWORD).unwrap();
+        assert_eq!(
+            hex::encode(keystore.cache.as_ref().unwrap().seed.as_slice()),
+            seed
+        );
+        assert_eq!(keystore.passphrase().unwrap(), "TREZOR");
+        assert_eq!(keystore.mnemonic().unwrap(), mnemonic);
+        keystore.lock();
+
+        let derived_key = keystore
+            .store
+            .crypto
+            .generate_derived_key(TEST_PASSWORD)
+            .unwrap();
+        keystore.unlock_by_derived_key(&derived_key).unwrap();
+        assert_eq!(
+            hex::encode(keystore.cache.as_ref().unwrap().seed.as_slice()),
+            seed
+        );
+
+        let keystore =
+            HdKeystore::from_mnemonic(mnemonic, TEST_PASSWORD, Metadata::default()).unwrap();
+        assert!(keystore.store.enc_passphrase.is_none());
+    }
+
+    #[test]
+    pub fn mnemonic_language() {
+        let french = "abaisser\u{3000}abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser\u{3000}abeille";
+        let mut keystore =
+            HdKeystore::from_mnemonic(french, TEST_PASSWORD, Metadata::default()).unwrap();
+        assert_eq!(
+            keystore.store.meta.mnemonic_language,
+            Some(MnemonicLanguage::French)
+        );
+        assert_eq!(
+            keystore.store.key_hash,
+            key_hash_from_mnemonic(french).unwrap()
+        );
+        keystore.unlock_by_password(TEST_PASSWORD).unwrap();
+        assert_eq!(
+            keystore.mnemonic().unwrap(),
+            MnemonicLanguage::normalize(french)
+        );
+
+        let meta = Metadata {
+            mnemonic_language: Some(MnemonicLanguage::English),
+            ..Metadata::default()
+        };
+        let ret = HdKeystore::from_mnemonic(french, TEST_PASSWORD, meta);
+        assert_eq!(format!("{}", ret.err().unwrap()), "mnemonic_word_invalid");
+
+        let meta = Metadata {
+            mnemonic_language: Some(MnemonicLanguage::Korean),
+            ..Metadata::default()
+        };
+        let mut keystore = HdKeystore::new(TEST_PASSWORD, meta);
+        keystore.unlock_by_password(TEST_PASSWORD).unwrap();
+        assert_eq!(
+            MnemonicLanguage::detect(&keystore.mnemonic().unwrap()).unwrap(),
+            MnemonicLanguage::Korean
+        );
+    }
+
     #[test]
     pub fn derive_key_at_paths() {
         let mut keystore =
@@ -385,6 +569,9 @@ mod tests {
             seg_wit: "NONE".to_string(),
             curve: CurveType::SECP256k1,
             coin: "BITCOIN".to_string(),
+            label: "".to_string(),
+            tags: vec![],
+            hidden: false,
         };
 
         assert_eq!(acc, expected);
@@ -0,0 +1,229 @@
+use bip39::{Language, Mnemonic};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use super::{transform_mnemonic_error, Error};
+use crate::Result;
+
+/// The bip39 wordlist of a mnemonic
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MnemonicLanguage {
+    English,
+    ChineseSimplified,
+    ChineseTraditional,
+    French,
+    Italian,
+    Japanese,
+    Korean,
+    Spanish,
+}
+
+/// The order `detect` tries the wordlists in, a mnemonic that is valid in several wordlists
+/// (e.g. every word is in both chinese wordlists) gets the first one
+const DETECTION_ORDER: [MnemonicLanguage; 8] = [
+    MnemonicLanguage::English,
+    MnemonicLanguage::ChineseSimplified,
+    MnemonicLanguage::ChineseTraditional,
+    MnemonicLanguage::Japanese,
+    MnemonicLanguage::Korean,
+    MnemonicLanguage::Spanish,
+    MnemonicLanguage::French,
+    MnemonicLanguage::Italian,
+];
+
+impl Default for MnemonicLanguage {
+    fn default() -> Self {
+        MnemonicLanguage::English
+    }
+}
+
+impl FromStr for MnemonicLanguage {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ENGLISH" => Ok(MnemonicLanguage::English),
+            "CHINESE_SIMPLIFIED" => Ok(MnemonicLanguage::ChineseSimplified),
+            "CHINESE_TRADITIONAL" => Ok(MnemonicLanguage::ChineseTraditional),
+            "FRENCH" => Ok(MnemonicLanguage::French),
+            "ITALIAN" => Ok(MnemonicLanguage::Italian),
+            "JAPANESE" => Ok(MnemonicLanguage::Japanese),
+            "KOREAN" => Ok(MnemonicLanguage::Korean),
+            "SPANISH" => Ok(MnemonicLanguage::Spanish),
+            _ => Err(Error::MnemonicLanguageUnsupported.into()),
+        }
+    }
+}
+
+impl MnemonicLanguage {
+    /// the name `from_str` parses
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MnemonicLanguage::English => "ENGLISH",
+            MnemonicLanguage::ChineseSimplified => "CHINESE_SIMPLIFIED",
+            MnemonicLanguage::ChineseTraditional => "CHINESE_TRADITIONAL",
+            MnemonicLanguage::French => "FRENCH",
+            MnemonicLanguage::Italian => "ITALIAN",
+            MnemonicLanguage::Japanese => "JAPANESE",
+            MnemonicLanguage::Korean => "KOREAN",
+            MnemonicLanguage::Spanish => "SPANISH",
+        }
+    }
+
+    pub fn bip39_language(self) -> Language {
+        match self {
+            MnemonicLanguage::English => Language::English,
+            MnemonicLanguage::ChineseSimplified => Language::ChineseSimplified,
+            MnemonicLanguage::ChineseTraditional => Language::ChineseTraditional,
+            MnemonicLanguage::French => Language::French,
+            MnemonicLanguage::Italian => Language::Italian,
+            MnemonicLanguage::Japanese => Language::Japanese,
+            MnemonicLanguage::Korean => Language::Korean,
+            MnemonicLanguage::Spanish => Language::Spanish,
+        }
+    }
+
+    /// the words separated by a single half-width space, the seed is derived from the
+    /// normalized mnemonic no matter which whitespaces (e.g. the japanese full-width space) the
+    /// user typed
+    pub fn normalize(mnemonic: &str) -> String {
+        mnemonic.split_whitespace().collect::<Vec<&str>>().join(" ")
+    }
+
+    /// the first wordlist in `DETECTION_ORDER` that the mnemonic is valid in
+    ///
+    /// When no wordlist matches, the checksum error is preferred over the word error, because it
+    /// means all words were found in some wordlist.
+    pub fn detect(mnemonic: &str) -> Result<MnemonicLanguage> {
+        let mnemonic = Self::normalize(mnemonic);
+        let mut error: Option<Error> = None;
+        for language in DETECTION_ORDER.iter() {
+            match Mnemonic::from_phrase(&mnemonic, language.bip39_language()) {
+                Ok(_) => return Ok(*language),
+                Err(err) => {
+                    let err = transform_mnemonic_error(err);
+                    if error.is_none() || err == Error::MnemonicChecksumInvalid {
+                        error = Some(err);
+                    }
+                }
+            }
+        }
+        Err(error.unwrap_or(Error::MnemonicInvalid).into())
+    }
+
+    pub fn validate(self, mnemonic: &str) -> Result<()> {
+        Mnemonic::from_phrase(&Self::normalize(mnemonic), self.bip39_language())
+            .map_err(transform_mnemonic_error)?;
+        Ok(())
+    }
+
+    /// the bip39 entropy of the mnemonic, it is the master secret of the slip39 shares
+    ///
+    /// The shares are not interoperable with other slip39 wallets: they derive the bip32 seed
+    /// from the master secret directly, while the master secret here is turned back into the
+    /// bip39 mnemonic and the seed is derived from it. So the shares recover the same wallet only
+    /// by `hd_store_import_slip39`.
+    pub fn entropy(self, mnemonic: &str) -> Result<Vec<u8>> {
+        let mnemonic = Mnemonic::from_phrase(&Self::normalize(mnemonic), self.bip39_language())
+            .map_err(transform_mnemonic_error)?;
+        Ok(mnemonic.entropy().to_vec())
+    }
+
+    /// the mnemonic of a 16, 20, 24, 28 or 32 bytes entropy
+    pub fn mnemonic_from_entropy(self, entropy: &[u8]) -> Result<String> {
+        let mnemonic = Mnemonic::from_entropy(entropy, self.bip39_language())
+            .map_err(transform_mnemonic_error)?;
+        Ok(mnemonic.into_phrase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tcx_constants::TEST_MNEMONIC;
+
+    #[test]
+    pub fn detect_language() {
+        assert_eq!(
+            MnemonicLanguage::detect(TEST_MNEMONIC).unwrap(),
+            MnemonicLanguage::English
+        );
+
+        let french = "abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abeille";
+        assert_eq!(
+            MnemonicLanguage::detect(french).unwrap(),
+            MnemonicLanguage::French
+        );
+        MnemonicLanguage::French.validate(french).unwrap();
+        assert!(MnemonicLanguage::English.validate(french).is_err());
+
+        let italian = "abaco abaco abaco abaco abaco abaco abaco abaco abaco abaco abaco abete";
+        assert_eq!(
+            MnemonicLanguage::detect(italian).unwrap(),
+            MnemonicLanguage::Italian
+        );
+
+        // every word is in both chinese wordlists
+        let chinese = "的 的 的 的 的 的 的 的 的 的 的 在";
+        assert_eq!(
+            MnemonicLanguage::detect(chinese).unwrap(),
+            MnemonicLanguage::ChineseSimplified
+        );
+        MnemonicLanguage::ChineseTraditional
+            .validate(chinese)
+            .unwrap();
+    }
+
+    #[test]
+    pub fn detect_invalid_mnemonic() {
+        let invalid_mnemonics =
+            vec![
+            (
+                "inject kidney empty canal shadow pact comfort wife crush horse wife inject",
+                "mnemonic_checksum_invalid",
+            ),
+            (
+                "invalid_word kidney empty canal shadow pact comfort wife crush horse wife sketch",
+                "mnemonic_word_invalid",
+            ),
+            (
+                "inject kidney empty canal shadow pact comfort wife crush horse wife",
+                "mnemonic_length_invalid",
+            ),
+            ("的 的 的 的 的 的 的 的 的 的 的 的", "mnemonic_checksum_invalid"),
+        ];
+        for (mnemonic, err) in invalid_mnemonics {
+            let ret = MnemonicLanguage::detect(mnemonic);
+            assert_eq!(format!("{}", ret.err().unwrap()), err);
+        }
+    }
+
+    #[test]
+    pub fn mnemonic_entropy() {
+        let entropy = MnemonicLanguage::English.entropy(TEST_MNEMONIC).unwrap();
+        assert_eq!(entropy.len(), 16);
+        assert_eq!(
+            MnemonicLanguage::English
+                .mnemonic_from_entropy(&entropy)
+                .unwrap(),
+            TEST_MNEMONIC
+        );
+        assert!(MnemonicLanguage::English
+            .mnemonic_from_entropy(&entropy[..15])
+            .is_err());
+    }
+
+    #[test]
+    pub fn language_from_str() {
+        assert_eq!(
+            MnemonicLanguage::from_str("JAPANESE").unwrap(),
+            MnemonicLanguage::Japanese
+        );
+        let ret = MnemonicLanguage::from_str("KLINGON");
+        assert_eq!(
+            format!("{}", ret.err().unwrap()),
+            "mnemonic_language_unsupported"
+        );
+    }
+}
@@ -3,19 +3,25 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 mod guard;
 mod hd;
+mod mnemonic;
 mod private;
+mod throttle;
+mod watch_only;
 
 use serde::{Deserialize, Serialize};
 
 use tcx_constants::{CoinInfo, CurveType};
 
 pub use self::{
-    guard::KeystoreGuard, hd::key_hash_from_mnemonic, hd::HdKeystore,
-    private::key_hash_from_private_key, private::PrivateKeystore,
+    guard::KeystoreGuard, hd::key_hash_from_mnemonic, hd::key_hash_from_mnemonic_with_passphrase,
+    hd::HdKeystore, mnemonic::MnemonicLanguage, private::key_hash_from_private_key,
+    private::PrivateKeystore, throttle::DEFAULT_MAX_UNLOCK_FAILURES,
+    watch_only::key_hash_from_watched_account, watch_only::WatchOnlyKeystore,
 };
 
 use crate::signer::ChainSigner;
-use tcx_crypto::{Crypto, Pbkdf2Params};
+use tcx_crypto::crypto::KdfParams;
+use tcx_crypto::{Cipher, Crypto, EncPair, Kdf, Zeroizing};
 use tcx_primitive::{TypedDeterministicPublicKey, TypedPrivateKey, TypedPublicKey};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,11 +30,25 @@ pub(crate) struct Store {
     pub id: String,
     pub version: i64,
     pub key_hash: String,
-    pub crypto: Crypto<Pbkdf2Params>,
+    pub crypto: Crypto<Kdf>,
+    /// the bip39 passphrase of a hd keystore, encrypted by the derived key of `crypto`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enc_passphrase: Option<EncPair>,
     pub active_accounts: Vec<Account>,
 
     #[serde(rename = "imTokenMeta")]
     pub meta: Metadata,
+
+    /// the failed unlocks by password since the last successful one
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub failed_unlocks: u32,
+    /// the unix time of the last failed unlock
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub last_failed_unlock: i64,
+}
+
+fn is_zero<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
 }
 
 #[derive(Fail, Debug, PartialEq)]
@@ -49,6 +69,14 @@ pub enum Error {
     KeystoreLocked,
     #[fail(display = "invalid_version")]
     InvalidVersion,
+    #[fail(display = "mnemonic_language_unsupported")]
+    MnemonicLanguageUnsupported,
+    #[fail(display = "watch_only_cannot_sign")]
+    WatchOnlyCannotSign,
+    #[fail(display = "unlock_throttled")]
+    UnlockThrottled,
+    #[fail(display = "keystore_locked_out")]
+    KeystoreLockedOut,
 }
 
 fn transform_mnemonic_error(err: failure::Error) -> Error {
@@ -72,6 +100,18 @@ pub struct Account {
     pub network: String,
     pub seg_wit: String,
     pub ext_pub_key: String,
+    /// a name the user gives to the account, it's not secret
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// a hidden account is kept but not listed by default
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub hidden: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 /// Chain address interface, for encapsulate derivation
@@ -94,6 +134,9 @@ pub enum Source {
     Mnemonic,
     NewIdentity,
     RecoveredIdentity,
+    Xpub,
+    PublicKey,
+    Address,
 }
 
 /// Metadata of keystore, for presenting wallet data
@@ -106,6 +149,9 @@ pub struct Metadata {
     pub timestamp: i64,
     #[serde(default = "metadata_default_source")]
     pub source: Source,
+    /// the wordlist of the mnemonic of a hd keystore, keystores without it are english
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mnemonic_language: Option<MnemonicLanguage>,
 }
 
 fn metadata_default_time() -> i64 {
@@ -125,6 +171,7 @@ impl Default for Metadata {
             password_hint: String::new(),
             timestamp: metadata_default_time(),
             source: Source::Mnemonic,
+            mnemonic_language: None,
         }
     }
 }
@@ -132,6 +179,7 @@ impl Default for Metadata {
 pub enum Keystore {
     PrivateKey(PrivateKeystore),
     Hd(HdKeystore),
+    WatchOnly(WatchOnlyKeystore),
 }
 
 impl Keystore {
@@ -161,6 +209,7 @@ impl Keystore {
         match self {
             Keystore::PrivateKey(ks) => ks.store(),
             Keystore::Hd(ks) => ks.store(),
+            Keystore::WatchOnly(ks) => ks.store(),
         }
     }
 
@@ -168,6 +217,7 @@ impl Keystore {
         match self {
             Keystore::PrivateKey(ks) => ks.store_mut(),
             Keystore::Hd(ks) => ks.store_mut(),
+            Keystore::WatchOnly(ks) => ks.store_mut(),
         }
     }
 
@@ -175,21 +225,293 @@ impl Keystore {
         self.store().meta.clone()
     }
 
+    /// the metadata isn't encrypted, so renaming needs no password
+    pub fn set_name(&mut self, name: &str) {
+        self.store_mut().meta.name = name.to_owned();
+    }
+
+    pub fn set_password_hint(&mut self, password_hint: &str) {
+        self.store_mut().meta.password_hint = password_hint.to_owned();
+    }
+
+    /// replace the label and the tags of an account
+    pub fn update_account_label(
+        &mut self,
+        symbol: &str,
+        address: &str,
+        label: &str,
+        tags: &[String],
+    ) -> Result<()> {
+        let account = self
+            .store_mut()
+            .active_accounts
+            .iter_mut()
+            .find(|acc| acc.coin == symbol && acc.address == address)
+            .ok_or(Error::AccountNotFound)?;
+        account.label = label.to_owned();
+        account.tags = tags.to_vec();
+        Ok(())
+    }
+
+    fn account_position(&self, symbol: &str, address: &str, path: &str) -> Result<usize> {
+        let position = self
+            .store()
+            .active_accounts
+            .iter()
+            .position(|acc| {
+                acc.coin == symbol && acc.address == address && acc.derivation_path == path
+            })
+            .ok_or(Error::AccountNotFound)?;
+        Ok(position)
+    }
+
+    /// remove a mistaken derivation, it can be derived again later
+    pub fn remove_account(&mut self, symbol: &str, address: &str, path: &str) -> Result<Account> {
+        let position = self.account_position(symbol, address, path)?;
+        Ok(self.store_mut().active_accounts.remove(position))
+    }
+
+    pub fn set_account_hidden(
+        &mut self,
+        symbol: &str,
+        address: &str,
+        path: &str,
+        hidden: bool,
+    ) -> Result<()> {
+        let position = self.account_position(symbol, address, path)?;
+        self.store_mut().active_accounts[position].hidden = hidden;
+        Ok(())
+    }
+
     pub fn key_hash(&self) -> String {
         self.store().key_hash.to_string()
     }
 
-    pub fn unlock_by_password(&mut self, password: &str) -> Result<()> {
+    /// unlock by the password, it's refused while waiting after failed unlocks and once
+    /// `max_failures` unlocks failed in a row
+    pub fn unlock_by_password(&mut self, password: &str, max_failures: u32) -> Result<()> {
+        self.check_password(max_failures, |keystore| {
+            keystore.unlock_by_password_unthrottled(password)
+        })
+    }
+
+    pub(crate) fn unlock_by_password_unthrottled(&mut self, password: &str) -> Result<()> {
         match self {
             Keystore::PrivateKey(ks) => ks.unlock_by_password(password),
             Keystore::Hd(ks) => ks.unlock_by_password(password),
+            Keystore::WatchOnly(ks) => ks.unlock_by_password(password),
+        }
+    }
+
+    /// verify the password like `verify_password`, but under the same throttling as
+    /// `unlock_by_password`
+    pub fn try_password(&mut self, password: &str, max_failures: u32) -> Result<()> {
+        self.check_password(max_failures, |keystore| {
+            tcx_ensure!(
+                keystore.verify_password(password),
+                tcx_crypto::Error::PasswordIncorrect
+            );
+            Ok(())
+        })
+    }
+
+    /// run a check of the password, a wrong password is counted and makes the next check wait
+    /// longer, a right one clears the count
+    fn check_password<T>(
+        &mut self,
+        max_failures: u32,
+        check: impl FnOnce(&mut Keystore) -> Result<T>,
+    ) -> Result<T> {
+        let now = metadata_default_time();
+        if self.unlock_wait(now, max_failures) > 0 {
+            if self.is_locked_out(max_failures) {
+                return Err(Error::KeystoreLockedOut.into());
+            }
+            return Err(Error::UnlockThrottled.into());
+        }
+
+        let ret = check(self);
+        let store = self.store_mut();
+        match &ret {
+            Ok(_) => {
+                store.failed_unlocks = 0;
+                store.last_failed_unlock = 0;
+            }
+            Err(err)
+                if err.downcast_ref::<tcx_crypto::Error>()
+                    == Some(&tcx_crypto::Error::PasswordIncorrect) =>
+            {
+                store.failed_unlocks = store.failed_unlocks.saturating_add(1);
+                store.last_failed_unlock = now;
+            }
+            Err(_) => {}
+        }
+        ret
+    }
+
+    pub fn failed_unlocks(&self) -> u32 {
+        self.store().failed_unlocks
+    }
+
+    /// too many failed unlocks, the password can only be tried once a day
+    pub fn is_locked_out(&self, max_failures: u32) -> bool {
+        throttle::is_locked_out(self.failed_unlocks(), max_failures)
+    }
+
+    /// seconds to wait at `now` before the next unlock by password, 0 if it's allowed
+    pub fn unlock_wait(&self, now: i64, max_failures: u32) -> i64 {
+        let store = self.store();
+        let wait = throttle::unlock_wait_secs(store.failed_unlocks, max_failures);
+        std::cmp::max(store.last_failed_unlock + wait - now, 0)
+    }
+
+    /// unlock without the kdf, `derived_key` is the hex output of `get_derived_key`
+    pub fn unlock_by_derived_key(&mut self, derived_key: &str) -> Result<()> {
+        let derived_key = Zeroizing::new(
+            hex::decode(derived_key).map_err(|_| tcx_crypto::Error::DerivedKeyIncorrect)?,
+        );
+        match self {
+            Keystore::PrivateKey(ks) => ks.unlock_by_derived_key(&derived_key),
+            Keystore::Hd(ks) => ks.unlock_by_derived_key(&derived_key),
+            Keystore::WatchOnly(ks) => ks.unlock_by_derived_key(&derived_key),
+        }
+    }
+
+    /// the hex output of the kdf, which the host can keep in a secure storage. It's verified
+    /// against the mac, so it's also the check of the password
+    pub fn get_derived_key(&self, password: &str) -> Result<String> {
+        let store = self.store();
+        let derived_key = Zeroizing::new(store.crypto.generate_derived_key(password)?);
+        tcx_ensure!(
+            store.crypto.verify_derived_key(&derived_key),
+            tcx_crypto::Error::PasswordIncorrect
+        );
+        Ok(hex::encode(derived_key.as_slice()))
+    }
+
+    /// `get_derived_key` under the same throttling as `unlock_by_password`
+    pub fn get_derived_key_throttled(
+        &mut self,
+        password: &str,
+        max_failures: u32,
+    ) -> Result<String> {
+        self.check_password(max_failures, |keystore| keystore.get_derived_key(password))
+    }
+
+    pub fn verify_derived_key(&self, derived_key: &str) -> bool {
+        match hex::decode(derived_key) {
+            Ok(derived_key) => self
+                .store()
+                .crypto
+                .verify_derived_key(&Zeroizing::new(derived_key)),
+            Err(_) => false,
+        }
+    }
+
+    /// cache a verified derived key in memory, after that `key` works like the password
+    pub fn cache_derived_key(&mut self, key: &str, derived_key: &str) -> Result<()> {
+        tcx_ensure!(
+            self.verify_derived_key(derived_key),
+            tcx_crypto::Error::DerivedKeyIncorrect
+        );
+        let derived_key = Zeroizing::new(hex::decode(derived_key)?);
+        self.store_mut().crypto.cache_derived_key(key, &derived_key);
+        Ok(())
+    }
+
+    pub fn clear_derived_key(&mut self) {
+        self.store_mut().crypto.clear_cache_derived_key();
+    }
+
+    pub fn cipher(&self) -> Cipher {
+        self.store().crypto.cipher()
+    }
+
+    /// re-encrypt the secret and the passphrase by `cipher` if it's encrypted by another one,
+    /// returns whether the keystore changed and needs to be flushed. `derived_key` is the hex
+    /// output of `get_derived_key`, so the password is checked under the caller's throttling
+    pub fn upgrade_cipher(&mut self, derived_key: &str, cipher: Cipher) -> Result<bool> {
+        if self.cipher() == cipher {
+            return Ok(false);
+        }
+        tcx_ensure!(
+            self.verify_derived_key(derived_key),
+            tcx_crypto::Error::DerivedKeyIncorrect
+        );
+        let derived_key = Zeroizing::new(
+            hex::decode(derived_key).map_err(|_| tcx_crypto::Error::DerivedKeyIncorrect)?,
+        );
+        let store = self.store_mut();
+        let passphrase = match &store.enc_passphrase {
+            Some(enc_pair) => Some(Zeroizing::new(
+                store
+                    .crypto
+                    .decrypt_enc_pair_by_derived_key(&derived_key, enc_pair)?,
+            )),
+            None => None,
+        };
+        store.crypto.upgrade_cipher(&derived_key, cipher)?;
+        if let Some(passphrase) = passphrase {
+            store.enc_passphrase = Some(
+                store
+                    .crypto
+                    .derive_enc_pair_by_derived_key(&derived_key, &passphrase)?,
+            );
+        }
+        Ok(true)
+    }
+
+    /// re-encrypt the secret by `new_password` with a fresh salt and iv, the id, accounts,
+    /// cipher and metadata except the password hint are kept. The kdf and its cost are kept too
+    /// unless another `kdf` is given
+    ///
+    /// `old_derived_key` is the hex output of `get_derived_key` of the old password, so each kdf
+    /// runs once
+    pub fn change_password(
+        &mut self,
+        old_derived_key: &str,
+        new_password: &str,
+        password_hint: &str,
+        kdf: Option<Kdf>,
+    ) -> Result<()> {
+        tcx_ensure!(
+            self.verify_derived_key(old_derived_key),
+            tcx_crypto::Error::DerivedKeyIncorrect
+        );
+        let derived_key = Zeroizing::new(
+            hex::decode(old_derived_key).map_err(|_| tcx_crypto::Error::DerivedKeyIncorrect)?,
+        );
+        let store = self.store_mut();
+        let kdf = kdf.unwrap_or_else(|| store.crypto.kdfparams().clone());
+        let secret = Zeroizing::new(store.crypto.decrypt_by_derived_key(&derived_key)?);
+        let passphrase = match &store.enc_passphrase {
+            Some(enc_pair) => Some(Zeroizing::new(
+                store
+                    .crypto
+                    .decrypt_enc_pair_by_derived_key(&derived_key, enc_pair)?,
+            )),
+            None => None,
+        };
+        let cipher = store.crypto.cipher();
+        let (crypto, new_derived_key) =
+            Crypto::new_with_derived_key(new_password, &secret, kdf, cipher);
+        store.crypto = crypto;
+        if let Some(passphrase) = passphrase {
+            store.enc_passphrase = Some(
+                store
+                    .crypto
+                    .derive_enc_pair_by_derived_key(&new_derived_key, &passphrase)?,
+            );
         }
+        store.meta.password_hint = password_hint.to_owned();
+        Ok(())
     }
 
     pub fn is_locked(&self) -> bool {
         match self {
             Keystore::PrivateKey(ks) => ks.is_locked(),
             Keystore::Hd(ks) => ks.is_locked(),
+            Keystore::WatchOnly(ks) => ks.is_locked(),
         }
     }
 
@@ -197,6 +519,15 @@ impl Keystore {
         match self {
             Keystore::PrivateKey(_) => false,
             Keystore::Hd(_) => true,
+            Keystore::WatchOnly(_) => false,
+        }
+    }
+
+    /// a watch only keystore has no secret, it can't sign or export any key
+    pub fn is_watch_only(&self) -> bool {
+        match self {
+            Keystore::WatchOnly(_) => true,
+            _ => false,
         }
     }
 
@@ -204,31 +535,41 @@ impl Keystore {
         match self {
             Keystore::PrivateKey(pk_store) => pk_store.private_key(),
             Keystore::Hd(hd_store) => hd_store.mnemonic(),
+            Keystore::WatchOnly(_) => Err(Error::WatchOnlyCannotSign.into()),
         }
     }
 
+    /// the bip39 passphrase of an unlocked hd keystore, private key keystores have none
+    pub fn export_passphrase(&self) -> Result<String> {
+        match self {
+            Keystore::PrivateKey(_) => Ok("".to_owned()),
+            Keystore::Hd(hd_store) => hd_store.passphrase(),
+            Keystore::WatchOnly(_) => Ok("".to_owned()),
+        }
+    }
+
+    /// the private key of an account, the caller formats it without an intermediate hex copy
     pub fn export_private_key(
         &mut self,
         coin: &str,
         main_address: &str,
         path: Option<&str>,
-    ) -> Result<String> {
+    ) -> Result<TypedPrivateKey> {
         match self {
             Keystore::PrivateKey(pk_store) => {
                 let _ = pk_store
                     .account(coin, main_address)
                     .ok_or(Error::AccountNotFound)?;
-                pk_store.private_key()
+                pk_store.find_private_key(main_address)
             }
             Keystore::Hd(hd_store) => {
-                let typed_pk = if let Some(path) = path {
-                    hd_store.find_private_key_by_path(coin, main_address, path)?
+                if let Some(path) = path {
+                    hd_store.find_private_key_by_path(coin, main_address, path)
                 } else {
-                    hd_store.find_private_key(coin, main_address)?
-                };
-
-                Ok(hex::encode(typed_pk.to_bytes()))
+                    hd_store.find_private_key(coin, main_address)
+                }
             }
+            Keystore::WatchOnly(_) => Err(Error::WatchOnlyCannotSign.into()),
         }
     }
 
@@ -236,6 +577,7 @@ impl Keystore {
         match self {
             Keystore::PrivateKey(ks) => ks.lock(),
             Keystore::Hd(ks) => ks.lock(),
+            Keystore::WatchOnly(ks) => ks.lock(),
         }
     }
 
@@ -243,6 +585,7 @@ impl Keystore {
         match self {
             Keystore::PrivateKey(ks) => ks.derive_coin::<A>(coin_info),
             Keystore::Hd(ks) => ks.derive_coin::<A>(coin_info),
+            Keystore::WatchOnly(_) => Err(Error::CannotDeriveKey.into()),
         }
     }
 
@@ -250,6 +593,7 @@ impl Keystore {
         match self {
             Keystore::PrivateKey(ks) => ks.find_private_key(address),
             Keystore::Hd(ks) => ks.find_private_key(symbol, address),
+            Keystore::WatchOnly(_) => Err(Error::WatchOnlyCannotSign.into()),
         }
     }
 
@@ -262,6 +606,7 @@ impl Keystore {
         match self {
             Keystore::Hd(ks) => ks.find_private_key_by_path(symbol, address, path),
             Keystore::PrivateKey(ks) => ks.find_private_key(address),
+            Keystore::WatchOnly(_) => Err(Error::WatchOnlyCannotSign.into()),
         }
     }
 
@@ -281,6 +626,7 @@ impl Keystore {
     ) -> Result<TypedDeterministicPublicKey> {
         match self {
             Keystore::Hd(ks) => ks.find_deterministic_public_key(symbol, address),
+            Keystore::WatchOnly(ks) => ks.find_deterministic_public_key(symbol, address),
             _ => Err(Error::CannotDeriveKey.into()),
         }
     }
@@ -289,6 +635,7 @@ impl Keystore {
         match self {
             Keystore::PrivateKey(ks) => ks.account(symbol, address),
             Keystore::Hd(ks) => ks.account(symbol, address),
+            Keystore::WatchOnly(ks) => ks.account(symbol, address),
         }
     }
 
@@ -296,6 +643,7 @@ impl Keystore {
         match self {
             Keystore::PrivateKey(ks) => ks.store().active_accounts.as_slice(),
             Keystore::Hd(ks) => ks.store().active_accounts.as_slice(),
+            Keystore::WatchOnly(ks) => ks.store().active_accounts.as_slice(),
         }
     }
 
@@ -303,26 +651,41 @@ impl Keystore {
         match self {
             Keystore::PrivateKey(ks) => ks.verify_password(password),
             Keystore::Hd(ks) => ks.verify_password(password),
+            Keystore::WatchOnly(ks) => ks.verify_password(password),
         }
     }
 
     pub fn from_json(json: &str) -> Result<Keystore> {
         let store: Store = serde_json::from_str(json)?;
 
-        match store.version {
-            HdKeystore::VERSION => Ok(Keystore::Hd(HdKeystore::from_store(store))),
-            PrivateKeystore::VERSION => {
-                Ok(Keystore::PrivateKey(PrivateKeystore::from_store(store)))
-            }
+        let keystore = match store.version {
+            HdKeystore::VERSION => Keystore::Hd(HdKeystore::from_store(store)),
+            PrivateKeystore::VERSION => Keystore::PrivateKey(PrivateKeystore::from_store(store)),
+            WatchOnlyKeystore::VERSION => Keystore::WatchOnly(WatchOnlyKeystore::from_store(store)),
 
-            _ => Err(Error::InvalidVersion.into()),
-        }
+            _ => return Err(Error::InvalidVersion.into()),
+        };
+        // the kdf of a keystore from elsewhere runs on every unlock, refuse an unbounded cost
+        keystore.store().crypto.kdfparams().validate()?;
+        Ok(keystore)
     }
 
     pub fn to_json(&self) -> String {
         match self {
             Keystore::PrivateKey(ks) => serde_json::to_string(ks.store()).unwrap(),
             Keystore::Hd(ks) => serde_json::to_string(ks.store()).unwrap(),
+            Keystore::WatchOnly(ks) => serde_json::to_string(ks.store()).unwrap(),
+        }
+    }
+
+    /// a copy to apply a change to before it's persisted, the unlocked secret isn't copied but
+    /// the cached derived key is
+    pub fn locked_copy(&self) -> Keystore {
+        let store = self.store().clone();
+        match self {
+            Keystore::PrivateKey(_) => Keystore::PrivateKey(PrivateKeystore::from_store(store)),
+            Keystore::Hd(_) => Keystore::Hd(HdKeystore::from_store(store)),
+            Keystore::WatchOnly(_) => Keystore::WatchOnly(WatchOnlyKeystore::from_store(store)),
         }
     }
 }
@@ -364,12 +727,16 @@ impl ChainSigner for Keystore {
 #[cfg(test)]
 mod tests {
     use crate::keystore::Keystore::{Hd, PrivateKey};
-    use crate::{ChainSigner, HdKeystore, Keystore, Metadata, PrivateKeystore, Source};
+    use crate::{
+        key_hash_from_mnemonic, ChainSigner, HdKeystore, Keystore, Metadata, PrivateKeystore,
+        Source, WatchOnlyKeystore, DEFAULT_MAX_UNLOCK_FAILURES,
+    };
     use serde_json::Value;
     use std::str::FromStr;
 
-    use crate::keystore::metadata_default_source;
+    use crate::keystore::{metadata_default_source, metadata_default_time};
     use tcx_constants::{TEST_MNEMONIC, TEST_PASSWORD};
+    use tcx_crypto::{Argon2Params, Cipher, Pbkdf2Params};
     use tcx_primitive::{Ss58Codec, ToHex};
 
     static HD_KEYSTORE_JSON: &'static str = r#"
@@ -488,6 +855,11 @@ mod tests {
 
         let ret = Keystore::from_json(OLD_KEYSTORE_JSON);
         assert!(ret.is_err());
+
+        let costly = PK_KEYSTORE_JSON.replace(r#""c":1024,"#, r#""c":100000000,"#);
+        assert_ne!(costly, PK_KEYSTORE_JSON);
+        let ret = Keystore::from_json(&costly);
+        assert_eq!(format!("{}", ret.err().unwrap()), "kdf_params_invalid");
     }
 
     #[test]
@@ -504,7 +876,7 @@ mod tests {
         );
         assert!(ret.is_err());
         assert_eq!(format!("{}", ret.err().unwrap()), "keystore_locked");
-        let _ = keystore.unlock_by_password(TEST_PASSWORD);
+        let _ = keystore.unlock_by_password(TEST_PASSWORD, DEFAULT_MAX_UNLOCK_FAILURES);
         let ret = keystore
             .sign_hash(
                 &msg,
@@ -519,7 +891,7 @@ mod tests {
         let ret = keystore.sign_hash(&msg, "TRON", "TXo4VDm8Qc5YBSjPhu8pMaxzTApSvLshWG", None);
         assert!(ret.is_err());
         assert_eq!(format!("{}", ret.err().unwrap()), "keystore_locked");
-        let _ = keystore.unlock_by_password("imtoken1");
+        let _ = keystore.unlock_by_password("imtoken1", DEFAULT_MAX_UNLOCK_FAILURES);
         let msg = hex::decode("645c0b7b58158babbfa6c6cd5a48aa7340a8749176b120e8516216787a13dc76")
             .unwrap();
         let ret = keystore
@@ -549,7 +921,8 @@ mod tests {
         let export_ret = keystore.export();
         assert!(export_ret.is_err());
         assert_eq!(format!("{}", export_ret.err().unwrap()), "keystore_locked");
-        let unlocked_ret = keystore.unlock_by_password("WRONG PASSWORD");
+        let unlocked_ret =
+            keystore.unlock_by_password("WRONG PASSWORD", DEFAULT_MAX_UNLOCK_FAILURES);
         assert!(unlocked_ret.is_err());
         assert_eq!(
             format!("{}", unlocked_ret.err().unwrap()),
@@ -558,7 +931,9 @@ mod tests {
 
         assert!(keystore.verify_password(TEST_PASSWORD));
         assert!(!keystore.verify_password("WRONG PASSWORD"));
-        keystore.unlock_by_password(TEST_PASSWORD).unwrap();
+        keystore
+            .unlock_by_password(TEST_PASSWORD, DEFAULT_MAX_UNLOCK_FAILURES)
+            .unwrap();
         assert_eq!(
             "inject kidney empty canal shadow pact comfort wife crush horse wife sketch",
             keystore.export().unwrap()
@@ -570,10 +945,210 @@ mod tests {
         assert_eq!(format!("{}", export_ret.err().unwrap()), "keystore_locked");
     }
 
+    #[test]
+    fn test_keystore_unlock_by_derived_key() {
+        let mut keystore = Keystore::from_json(HD_KEYSTORE_JSON).unwrap();
+        let derived_key = keystore.get_derived_key(TEST_PASSWORD).unwrap();
+        assert!(keystore.verify_derived_key(&derived_key));
+        assert!(!keystore.verify_derived_key("0102"));
+
+        let ret = keystore.get_derived_key_throttled("WRONG PASSWORD", 10);
+        assert_eq!(format!("{}", ret.err().unwrap()), "password_incorrect");
+        assert_eq!(keystore.failed_unlocks(), 1);
+        assert_eq!(
+            keystore
+                .get_derived_key_throttled(TEST_PASSWORD, 10)
+                .unwrap(),
+            derived_key
+        );
+        assert_eq!(keystore.failed_unlocks(), 0);
+        assert!(!keystore.verify_derived_key("not hex"));
+
+        let unlocked_ret = keystore.unlock_by_derived_key(&"00".repeat(64));
+        assert_eq!(
+            format!("{}", unlocked_ret.err().unwrap()),
+            "derived_key_incorrect"
+        );
+        keystore.unlock_by_derived_key(&derived_key).unwrap();
+        assert_eq!(
+            "inject kidney empty canal shadow pact comfort wife crush horse wife sketch",
+            keystore.export().unwrap()
+        );
+        keystore.lock();
+
+        let cache_ret = keystore.cache_derived_key("TEMP_PASSWORD", "0102");
+        assert!(cache_ret.is_err());
+        keystore
+            .cache_derived_key("TEMP_PASSWORD", &derived_key)
+            .unwrap();
+        keystore
+            .unlock_by_password("TEMP_PASSWORD", DEFAULT_MAX_UNLOCK_FAILURES)
+            .unwrap();
+        assert!(!keystore.is_locked());
+        assert_eq!(
+            derived_key,
+            keystore.get_derived_key("TEMP_PASSWORD").unwrap()
+        );
+
+        keystore.clear_derived_key();
+        assert!(!keystore.verify_password("TEMP_PASSWORD"));
+    }
+
+    #[test]
+    fn test_keystore_argon2id() {
+        let kdf = Argon2Params::with_params(1024, 1, 1);
+        let keystore = Keystore::PrivateKey(PrivateKeystore::from_private_key_with_kdf(
+            "a392604efc2fad9c0b3da43b5f698a2e3f270f170d859912be0d54742275c5f6",
+            TEST_PASSWORD,
+            Source::Private,
+            kdf.into(),
+            Cipher::default(),
+        ));
+        let json: Value = Value::from_str(&keystore.to_json()).unwrap();
+        assert_eq!(json["crypto"]["kdf"], "argon2id");
+        assert_eq!(json["crypto"]["kdfparams"]["memory"], 1024);
+
+        let mut keystore = Keystore::from_json(&keystore.to_json()).unwrap();
+        assert!(!keystore.verify_password("WRONG PASSWORD"));
+        keystore
+            .unlock_by_password(TEST_PASSWORD, DEFAULT_MAX_UNLOCK_FAILURES)
+            .unwrap();
+        assert_eq!(
+            keystore.export().unwrap(),
+            "a392604efc2fad9c0b3da43b5f698a2e3f270f170d859912be0d54742275c5f6"
+        );
+    }
+
+    #[test]
+    fn test_keystore_upgrade_cipher() {
+        let mut keystore = Keystore::from_json(HD_KEYSTORE_JSON).unwrap();
+        assert_eq!(keystore.cipher(), Cipher::Aes128Ctr);
+        let derived_key = keystore.get_derived_key(TEST_PASSWORD).unwrap();
+        assert!(!keystore
+            .upgrade_cipher(&derived_key, Cipher::Aes128Ctr)
+            .unwrap());
+        let ret = keystore.upgrade_cipher(&"00".repeat(32), Cipher::Aes256Gcm);
+        assert_eq!(format!("{}", ret.err().unwrap()), "derived_key_incorrect");
+        assert_eq!(keystore.cipher(), Cipher::Aes128Ctr);
+
+        assert!(keystore
+            .upgrade_cipher(&derived_key, Cipher::Aes256Gcm)
+            .unwrap());
+        let json: Value = Value::from_str(&keystore.to_json()).unwrap();
+        assert_eq!(json["crypto"]["cipher"], "aes-256-gcm");
+
+        let mut keystore = Keystore::from_json(&keystore.to_json()).unwrap();
+        assert_eq!(keystore.cipher(), Cipher::Aes256Gcm);
+        keystore
+            .unlock_by_password(TEST_PASSWORD, DEFAULT_MAX_UNLOCK_FAILURES)
+            .unwrap();
+        assert_eq!(keystore.export().unwrap(), TEST_MNEMONIC);
+    }
+
+    #[test]
+    fn test_keystore_change_password() {
+        for (json, password) in &[
+            (HD_KEYSTORE_JSON, TEST_PASSWORD),
+            (PK_KEYSTORE_JSON, "imtoken1"),
+        ] {
+            let mut keystore = Keystore::from_json(json).unwrap();
+            keystore
+                .unlock_by_password(password, DEFAULT_MAX_UNLOCK_FAILURES)
+                .unwrap();
+            let secret = keystore.export().unwrap();
+            keystore.lock();
+            let accounts = keystore.accounts().to_vec();
+
+            let ret = keystore.change_password(
+                &"00".repeat(64),
+                "NEW_PASSWORD",
+                "new hint",
+                Some(Pbkdf2Params::with_rounds(1024).into()),
+            );
+            assert_eq!(format!("{}", ret.err().unwrap()), "derived_key_incorrect");
+
+            let derived_key = keystore.get_derived_key(password).unwrap();
+            keystore
+                .change_password(
+                    &derived_key,
+                    "NEW_PASSWORD",
+                    "new hint",
+                    Some(Pbkdf2Params::with_rounds(1024).into()),
+                )
+                .unwrap();
+            assert!(!keystore.verify_password(password));
+            assert!(keystore.verify_password("NEW_PASSWORD"));
+            assert_eq!(keystore.meta().password_hint, "new hint");
+            assert_eq!(keystore.accounts(), accounts.as_slice());
+
+            let reloaded = Keystore::from_json(&keystore.to_json()).unwrap();
+            assert_eq!(reloaded.id(), keystore.id());
+            keystore
+                .unlock_by_password("NEW_PASSWORD", DEFAULT_MAX_UNLOCK_FAILURES)
+                .unwrap();
+            assert_eq!(keystore.export().unwrap(), secret);
+        }
+    }
+
+    #[test]
+    fn test_keystore_passphrase() {
+        let mut keystore = Keystore::Hd(
+            HdKeystore::from_mnemonic_with_kdf(
+                TEST_MNEMONIC,
+                TEST_PASSWORD,
+                Metadata::default(),
+                Pbkdf2Params::with_rounds(1024).into(),
+                Cipher::default(),
+                "TREZOR",
+            )
+            .unwrap(),
+        );
+        let json: Value = Value::from_str(&keystore.to_json()).unwrap();
+        assert!(json["encPassphrase"]["encStr"].is_string());
+        assert_eq!(json["encPassphrase"]["cipher"], "aes-128-ctr");
+        assert!(json["encPassphrase"]["mac"].is_string());
+        assert_ne!(
+            keystore.key_hash(),
+            key_hash_from_mnemonic(TEST_MNEMONIC).unwrap()
+        );
+
+        let derived_key = keystore.get_derived_key(TEST_PASSWORD).unwrap();
+        keystore
+            .change_password(&derived_key, "NEW_PASSWORD", "", None)
+            .unwrap();
+        let mut keystore = Keystore::from_json(&keystore.to_json()).unwrap();
+        keystore
+            .unlock_by_password("NEW_PASSWORD", DEFAULT_MAX_UNLOCK_FAILURES)
+            .unwrap();
+        assert_eq!(keystore.export().unwrap(), TEST_MNEMONIC);
+        assert_eq!(keystore.export_passphrase().unwrap(), "TREZOR");
+
+        // the passphrase follows the upgraded cipher
+        let derived_key = keystore.get_derived_key("NEW_PASSWORD").unwrap();
+        keystore
+            .upgrade_cipher(&derived_key, Cipher::Aes256Gcm)
+            .unwrap();
+        let mut keystore = Keystore::from_json(&keystore.to_json()).unwrap();
+        let json: Value = Value::from_str(&keystore.to_json()).unwrap();
+        assert_eq!(json["encPassphrase"]["cipher"], "aes-256-gcm");
+        keystore
+            .unlock_by_password("NEW_PASSWORD", DEFAULT_MAX_UNLOCK_FAILURES)
+            .unwrap();
+        assert_eq!(keystore.export_passphrase().unwrap(), "TREZOR");
+
+        let mut keystore = Keystore::from_json(PK_KEYSTORE_JSON).unwrap();
+        keystore
+            .unlock_by_password("imtoken1", DEFAULT_MAX_UNLOCK_FAILURES)
+            .unwrap();
+        assert_eq!(keystore.export_passphrase().unwrap(), "");
+    }
+
     #[test]
     fn test_hd_find_key() {
         let mut keystore = Keystore::from_json(HD_KEYSTORE_JSON).unwrap();
-        keystore.unlock_by_password(TEST_PASSWORD).unwrap();
+        keystore
+            .unlock_by_password(TEST_PASSWORD, DEFAULT_MAX_UNLOCK_FAILURES)
+            .unwrap();
         let pk =
             keystore.find_private_key("BITCOINCASH", "qzld7dav7d2sfjdl6x9snkvf6raj8lfxjcj5fa8y21");
         assert!(pk.is_err());
@@ -619,7 +1194,9 @@ mod tests {
     #[test]
     fn test_pk_find_key() {
         let mut keystore = Keystore::from_json(PK_KEYSTORE_JSON).unwrap();
-        keystore.unlock_by_password("imtoken1").unwrap();
+        keystore
+            .unlock_by_password("imtoken1", DEFAULT_MAX_UNLOCK_FAILURES)
+            .unwrap();
         let pk =
             keystore.find_private_key("BITCOINCASH", "qzld7dav7d2sfjdl6x9snkvf6raj8lfxjcj5fa8y21");
         assert!(pk.is_err());
@@ -651,6 +1228,144 @@ mod tests {
         assert!(ret.is_err())
     }
 
+    #[test]
+    fn test_update_meta_and_label() {
+        let mut keystore = Keystore::from_json(HD_KEYSTORE_JSON).unwrap();
+        keystore.set_name("renamed");
+        keystore.set_password_hint("new hint");
+        let ret = keystore.update_account_label("BITCOINCASH", "unknown", "label", &[]);
+        assert_eq!(format!("{}", ret.err().unwrap()), "account_not_found");
+        keystore
+            .update_account_label(
+                "BITCOINCASH",
+                "qzld7dav7d2sfjdl6x9snkvf6raj8lfxjcj5fa8y2r",
+                "savings",
+                &["cold".to_owned()],
+            )
+            .unwrap();
+
+        let keystore = Keystore::from_json(&keystore.to_json()).unwrap();
+        assert_eq!(keystore.meta().name, "renamed");
+        assert_eq!(keystore.meta().password_hint, "new hint");
+        let account = &keystore.accounts()[0];
+        assert_eq!(account.label, "savings");
+        assert_eq!(account.tags, vec!["cold".to_owned()]);
+        assert!(keystore.verify_password(TEST_PASSWORD));
+    }
+
+    #[test]
+    fn test_remove_and_hide_account() {
+        let mut keystore = Keystore::from_json(HD_KEYSTORE_JSON).unwrap();
+        let address = "qzld7dav7d2sfjdl6x9snkvf6raj8lfxjcj5fa8y2r";
+        let ret = keystore.set_account_hidden("BITCOINCASH", address, "m/44'/145'/0'/0/1", true);
+        assert_eq!(format!("{}", ret.err().unwrap()), "account_not_found");
+
+        keystore
+            .set_account_hidden("BITCOINCASH", address, "m/44'/145'/0'/0/0", true)
+            .unwrap();
+        let mut keystore = Keystore::from_json(&keystore.to_json()).unwrap();
+        assert!(keystore.accounts()[0].hidden);
+        keystore
+            .set_account_hidden("BITCOINCASH", address, "m/44'/145'/0'/0/0", false)
+            .unwrap();
+        assert_eq!(
+            Value::from_str(&keystore.to_json()).unwrap(),
+            Value::from_str(HD_KEYSTORE_JSON).unwrap()
+        );
+
+        let ret = keystore.remove_account("TRON", address, "m/44'/145'/0'/0/0");
+        assert_eq!(format!("{}", ret.err().unwrap()), "account_not_found");
+        let account = keystore
+            .remove_account("BITCOINCASH", address, "m/44'/145'/0'/0/0")
+            .unwrap();
+        assert_eq!(account.address, address);
+        let keystore = Keystore::from_json(&keystore.to_json()).unwrap();
+        assert_eq!(keystore.accounts().len(), 0);
+    }
+
+    #[test]
+    fn test_unlock_throttling() {
+        let max = DEFAULT_MAX_UNLOCK_FAILURES;
+        let mut keystore = Keystore::from_json(HD_KEYSTORE_JSON).unwrap();
+        for _ in 0..3 {
+            let ret = keystore.unlock_by_password("WRONG PASSWORD", max);
+            assert_eq!(format!("{}", ret.err().unwrap()), "password_incorrect");
+        }
+        let now = metadata_default_time();
+        assert!(keystore.unlock_wait(now, max) > 0 && keystore.unlock_wait(now, max) <= 2);
+
+        // the right password is refused too while waiting
+        let ret = keystore.unlock_by_password(TEST_PASSWORD, max);
+        assert_eq!(format!("{}", ret.err().unwrap()), "unlock_throttled");
+        let ret = keystore.try_password(TEST_PASSWORD, max);
+        assert_eq!(format!("{}", ret.err().unwrap()), "unlock_throttled");
+
+        let mut keystore = Keystore::from_json(&keystore.to_json()).unwrap();
+        assert_eq!(keystore.failed_unlocks(), 3);
+        keystore.store_mut().last_failed_unlock -= 2;
+        assert_eq!(keystore.unlock_wait(now, max), 0);
+        keystore.unlock_by_password(TEST_PASSWORD, max).unwrap();
+        assert_eq!(keystore.failed_unlocks(), 0);
+        assert_eq!(
+            Value::from_str(&keystore.to_json()).unwrap(),
+            Value::from_str(HD_KEYSTORE_JSON).unwrap()
+        );
+
+        keystore.store_mut().failed_unlocks = max;
+        keystore.store_mut().last_failed_unlock = now;
+        let ret = keystore.unlock_by_password(TEST_PASSWORD, max);
+        assert_eq!(format!("{}", ret.err().unwrap()), "keystore_locked_out");
+        assert!(keystore.unlock_wait(now, max) > 3600);
+
+        // the limit is the caller's, a lower one locks out earlier
+        keystore.store_mut().failed_unlocks = 4;
+        assert!(keystore.is_locked_out(4));
+        assert!(!keystore.is_locked_out(max));
+    }
+
+    #[test]
+    fn test_watch_only() {
+        let account = Keystore::from_json(HD_KEYSTORE_JSON).unwrap().accounts()[0].clone();
+        let meta = Metadata {
+            source: Source::Xpub,
+            ..Metadata::default()
+        };
+        let keystore = Keystore::WatchOnly(WatchOnlyKeystore::from_account(
+            account.clone(),
+            TEST_PASSWORD,
+            meta,
+            Pbkdf2Params::with_rounds(1024).into(),
+            Cipher::default(),
+        ));
+        let json: Value = Value::from_str(&keystore.to_json()).unwrap();
+        assert_eq!(json["version"], 11002);
+        assert_eq!(json["imTokenMeta"]["source"], "XPUB");
+
+        let mut keystore = Keystore::from_json(&keystore.to_json()).unwrap();
+        assert!(keystore.is_watch_only());
+        assert!(!keystore.determinable());
+        assert_eq!(keystore.accounts().to_vec(), vec![account.clone()]);
+        assert!(!keystore.verify_password("WRONG PASSWORD"));
+        keystore
+            .unlock_by_password(TEST_PASSWORD, DEFAULT_MAX_UNLOCK_FAILURES)
+            .unwrap();
+        assert!(!keystore.is_locked());
+
+        let ret = keystore.export();
+        assert_eq!(format!("{}", ret.err().unwrap()), "watch_only_cannot_sign");
+        let ret = keystore.export_private_key("BITCOINCASH", &account.address, None);
+        assert_eq!(format!("{}", ret.err().unwrap()), "watch_only_cannot_sign");
+        let msg = hex::decode("645c0b7b58158babbfa6c6cd5a48aa7340a8749176b120e8516216787a13dc76")
+            .unwrap();
+        let ret = keystore.sign_hash(&msg, "BITCOINCASH", &account.address, Some("0/0"));
+        assert_eq!(format!("{}", ret.err().unwrap()), "watch_only_cannot_sign");
+
+        let public_key = keystore
+            .find_deterministic_public_key("BITCOINCASH", &account.address)
+            .unwrap();
+        assert_eq!(public_key.to_hex(), account.ext_pub_key);
+    }
+
     #[test]
     fn test_create() {
         let hd_store = HdKeystore::new(TEST_PASSWORD, Metadata::default());
@@ -1,7 +1,7 @@
 use super::Account;
 use super::{Address, Metadata, Source};
 use tcx_constants::CoinInfo;
-use tcx_crypto::{Crypto, Pbkdf2Params};
+use tcx_crypto::{Cipher, Crypto, Kdf, Secret, Zeroizing};
 
 use super::Error;
 use super::Result;
@@ -18,7 +18,8 @@ pub fn key_hash_from_private_key(data: &[u8]) -> String {
 pub struct PrivateKeystore {
     store: Store,
 
-    private_key: Option<Vec<u8>>,
+    /// wiped when the keystore is locked
+    private_key: Option<Secret<Vec<u8>>>,
 }
 
 impl PrivateKeystore {
@@ -40,7 +41,15 @@ impl PrivateKeystore {
     }
 
     pub(crate) fn unlock_by_password(&mut self, password: &str) -> Result<()> {
-        self.private_key = Some(self.decrypt_private_key(password)?);
+        let private_key = Zeroizing::new(self.store.crypto.decrypt(password)?);
+        self.private_key = Some(Secret::from(private_key));
+
+        Ok(())
+    }
+
+    pub(crate) fn unlock_by_derived_key(&mut self, derived_key: &[u8]) -> Result<()> {
+        let private_key = Zeroizing::new(self.store.crypto.decrypt_by_derived_key(derived_key)?);
+        self.private_key = Some(Secret::from(private_key));
 
         Ok(())
     }
@@ -100,10 +109,26 @@ impl PrivateKeystore {
     }
 
     pub fn from_private_key(private_key: &str, password: &str, source: Source) -> PrivateKeystore {
-        let key_data: Vec<u8> = hex::decode(private_key).expect("hex can't decode");
+        Self::from_private_key_with_kdf(
+            private_key,
+            password,
+            source,
+            Kdf::default(),
+            Cipher::default(),
+        )
+    }
+
+    pub fn from_private_key_with_kdf(
+        private_key: &str,
+        password: &str,
+        source: Source,
+        kdf: Kdf,
+        cipher: Cipher,
+    ) -> PrivateKeystore {
+        let key_data = Zeroizing::new(hex::decode(private_key).expect("hex can't decode"));
         let key_hash = key_hash_from_private_key(&key_data);
         //        let pk_bytes = hex::decode(private_key).expect("valid private_key");
-        let crypto: Crypto<Pbkdf2Params> = Crypto::new(password, &key_data);
+        let crypto: Crypto<Kdf> = Crypto::new_with_cipher(password, &key_data, kdf, cipher);
 
         let meta = Metadata {
             source,
@@ -113,10 +138,13 @@ impl PrivateKeystore {
         let store = Store {
             key_hash,
             crypto,
+            enc_passphrase: None,
             meta,
             id: Uuid::new_v4().to_hyphenated().to_string(),
             version: PrivateKeystore::VERSION,
             active_accounts: vec![],
+            failed_unlocks: 0,
+            last_failed_unlock: 0,
         };
 
         PrivateKeystore {
@@ -140,6 +168,9 @@ impl PrivateKeystore {
             network: coin.network.to_string(),
             seg_wit: coin.seg_wit.to_string(),
             ext_pub_key: "".to_string(),
+            label: "".to_string(),
+            tags: vec![],
+            hidden: false,
         };
 
         Ok(acc)
@@ -147,12 +178,7 @@ impl PrivateKeystore {
 
     pub(crate) fn private_key(&self) -> Result<String> {
         tcx_ensure!(self.private_key.is_some(), Error::KeystoreLocked);
-        let vec = self.private_key.as_ref().unwrap().to_vec();
-        Ok(hex::encode(&vec))
-    }
-
-    fn decrypt_private_key(&self, password: &str) -> Result<Vec<u8>> {
-        self.store.crypto.decrypt(password)
+        Ok(hex::encode(self.private_key.as_ref().unwrap().as_slice()))
     }
 }
 
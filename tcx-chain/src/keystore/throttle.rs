@@ -0,0 +1,44 @@
+/// failed unlocks allowed without waiting, for the typos
+const FREE_UNLOCK_FAILURES: u32 = 3;
+
+/// the longest wait between two unlocks by password before the keystore is locked out
+const MAX_UNLOCK_WAIT_SECS: i64 = 3600;
+
+/// the wait of a locked out keystore, one more try is allowed after it
+const LOCKED_OUT_SECS: i64 = 24 * 3600;
+
+/// the failed unlocks locking a keystore out, unless the host configures another limit
+pub const DEFAULT_MAX_UNLOCK_FAILURES: u32 = 10;
+
+pub(crate) fn is_locked_out(failures: u32, max_failures: u32) -> bool {
+    failures >= max_failures
+}
+
+/// the wait after the last failed unlock, doubled by every failure after the free ones
+pub(crate) fn unlock_wait_secs(failures: u32, max_failures: u32) -> i64 {
+    if is_locked_out(failures, max_failures) {
+        LOCKED_OUT_SECS
+    } else if failures < FREE_UNLOCK_FAILURES {
+        0
+    } else {
+        let exponent = std::cmp::min(failures - FREE_UNLOCK_FAILURES + 1, 16);
+        std::cmp::min(1i64 << exponent, MAX_UNLOCK_WAIT_SECS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlock_wait_secs() {
+        let max = DEFAULT_MAX_UNLOCK_FAILURES;
+        assert_eq!(unlock_wait_secs(0, max), 0);
+        assert_eq!(unlock_wait_secs(2, max), 0);
+        assert_eq!(unlock_wait_secs(3, max), 2);
+        assert_eq!(unlock_wait_secs(4, max), 4);
+        assert_eq!(unlock_wait_secs(9, max), 128);
+        assert_eq!(unlock_wait_secs(max, max), LOCKED_OUT_SECS);
+        assert_eq!(unlock_wait_secs(4, 4), LOCKED_OUT_SECS);
+    }
+}
@@ -0,0 +1,126 @@
+use super::Account;
+use super::Metadata;
+use super::Result;
+use tcx_crypto::{Cipher, Crypto, Kdf};
+
+use crate::keystore::Store;
+
+use tcx_crypto::hash::dsha256;
+use tcx_primitive::{DeterministicType, TypedDeterministicPublicKey};
+use uuid::Uuid;
+
+/// the same address of the same chain is the same watch only wallet, no matter it's watched by
+/// an xpub, a public key or the address itself
+pub fn key_hash_from_watched_account(account: &Account) -> String {
+    let watched = format!("{}:{}:{}", account.coin, account.network, account.address);
+    hex::encode(dsha256(watched.as_bytes())[..20].to_vec())
+}
+
+/// A keystore without any secret, it holds one account created from an xpub, a public key or an
+/// address. The encrypted content is the watched address, so the password guards the keystore
+/// like the other ones.
+pub struct WatchOnlyKeystore {
+    store: Store,
+
+    unlocked: bool,
+}
+
+impl WatchOnlyKeystore {
+    pub const VERSION: i64 = 11002i64;
+
+    pub(crate) fn store(&self) -> &Store {
+        &self.store
+    }
+
+    pub(crate) fn store_mut(&mut self) -> &mut Store {
+        &mut self.store
+    }
+
+    pub(crate) fn from_store(store: Store) -> Self {
+        WatchOnlyKeystore {
+            store,
+            unlocked: false,
+        }
+    }
+
+    pub(crate) fn unlock_by_password(&mut self, password: &str) -> Result<()> {
+        self.store.crypto.decrypt(password)?;
+        self.unlocked = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn unlock_by_derived_key(&mut self, derived_key: &[u8]) -> Result<()> {
+        self.store.crypto.decrypt_by_derived_key(derived_key)?;
+        self.unlocked = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn lock(&mut self) {
+        self.unlocked = false;
+    }
+
+    pub(crate) fn is_locked(&self) -> bool {
+        !self.unlocked
+    }
+
+    pub(crate) fn find_deterministic_public_key(
+        &self,
+        symbol: &str,
+        address: &str,
+    ) -> Result<TypedDeterministicPublicKey> {
+        let account = self
+            .account(symbol, address)
+            .ok_or(super::Error::AccountNotFound)?;
+        tcx_ensure!(
+            !account.ext_pub_key.is_empty(),
+            super::Error::CannotDeriveKey
+        );
+
+        TypedDeterministicPublicKey::from_hex(
+            DeterministicType::BIP32,
+            account.curve,
+            &account.ext_pub_key,
+        )
+    }
+
+    pub(crate) fn account(&self, symbol: &str, address: &str) -> Option<&Account> {
+        self.store
+            .active_accounts
+            .iter()
+            .find(|acc| acc.address == address && acc.coin == symbol)
+    }
+
+    pub(crate) fn verify_password(&self, password: &str) -> bool {
+        self.store.crypto.verify_password(password)
+    }
+
+    pub fn from_account(
+        account: Account,
+        password: &str,
+        meta: Metadata,
+        kdf: Kdf,
+        cipher: Cipher,
+    ) -> WatchOnlyKeystore {
+        let key_hash = key_hash_from_watched_account(&account);
+        let crypto: Crypto<Kdf> =
+            Crypto::new_with_cipher(password, account.address.as_bytes(), kdf, cipher);
+
+        WatchOnlyKeystore {
+            store: Store {
+                key_hash,
+                crypto,
+                enc_passphrase: None,
+                id: Uuid::new_v4().to_hyphenated().to_string(),
+                version: Self::VERSION,
+                active_accounts: vec![account],
+                meta,
+                failed_unlocks: 0,
+                last_failed_unlock: 0,
+            },
+
+            unlocked: false,
+        }
+    }
+}
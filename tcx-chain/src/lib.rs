@@ -23,8 +23,10 @@ mod keystore;
 mod signer;
 
 pub use keystore::{
-    key_hash_from_mnemonic, key_hash_from_private_key, Account, Address, HdKeystore, Keystore,
-    KeystoreGuard, Metadata, PrivateKeystore, Source,
+    key_hash_from_mnemonic, key_hash_from_mnemonic_with_passphrase, key_hash_from_private_key,
+    key_hash_from_watched_account, Account, Address, Error, HdKeystore, Keystore, KeystoreGuard,
+    Metadata, MnemonicLanguage, PrivateKeystore, Source, WatchOnlyKeystore,
+    DEFAULT_MAX_UNLOCK_FAILURES,
 };
 
 pub use signer::{ChainSigner, MessageSigner, TransactionSigner};
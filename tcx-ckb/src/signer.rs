@@ -194,7 +194,7 @@ impl TransactionSigner<CkbTxInput, CkbTxOutput> for Keystore {
 mod tests {
     use crate::address::CkbAddress;
     use crate::transaction::{CachedCell, CellInput, CkbTxInput, OutPoint, Script, Witness};
-    use tcx_chain::{Keystore, Metadata, TransactionSigner};
+    use tcx_chain::{Keystore, Metadata, TransactionSigner, DEFAULT_MAX_UNLOCK_FAILURES};
     use tcx_constants::{CoinInfo, CurveType};
 
     #[test]
@@ -307,7 +307,8 @@ mod tests {
         )
         .unwrap();
 
-        ks.unlock_by_password("Password").unwrap();
+        ks.unlock_by_password("Password", DEFAULT_MAX_UNLOCK_FAILURES)
+            .unwrap();
 
         let account = ks.derive_coin::<CkbAddress>(&coin_info).unwrap().clone();
 
@@ -418,7 +419,8 @@ mod tests {
             "dcec27d0d975b0378471183a03f7071dea8532aaf968be796719ecd20af6988f",
             "Password",
         );
-        ks.unlock_by_password("Password").unwrap();
+        ks.unlock_by_password("Password", DEFAULT_MAX_UNLOCK_FAILURES)
+            .unwrap();
 
         let coin_info = CoinInfo {
             coin: "NERVOS".to_string(),
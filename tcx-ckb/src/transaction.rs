@@ -1,61 +1,96 @@
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct OutPoint {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub tx_hash: std::string::String,
     #[prost(int32, tag = "2")]
+    #[serde(default)]
     pub index: i32,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Witness {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub lock: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub input_type: std::string::String,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub output_type: std::string::String,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Script {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub args: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub code_hash: std::string::String,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub hash_type: std::string::String,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CellInput {
     #[prost(message, optional, tag = "1")]
+    #[serde(default)]
     pub previous_output: ::std::option::Option<OutPoint>,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub since: std::string::String,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CachedCell {
     #[prost(int64, tag = "1")]
+    #[serde(default)]
+    #[serde(with = "tcx_proto::json::int64")]
     pub capacity: i64,
     #[prost(message, optional, tag = "2")]
+    #[serde(default)]
     pub lock: ::std::option::Option<Script>,
     #[prost(message, optional, tag = "3")]
+    #[serde(default)]
     pub out_point: ::std::option::Option<OutPoint>,
     #[prost(string, tag = "4")]
+    #[serde(default)]
     pub derived_path: std::string::String,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CkbTxInput {
     #[prost(message, repeated, tag = "1")]
+    #[serde(default)]
     pub inputs: ::std::vec::Vec<CellInput>,
     #[prost(message, repeated, tag = "2")]
+    #[serde(default)]
     pub witnesses: ::std::vec::Vec<Witness>,
     #[prost(message, repeated, tag = "3")]
+    #[serde(default)]
     pub cached_cells: ::std::vec::Vec<CachedCell>,
     #[prost(string, tag = "4")]
+    #[serde(default)]
     pub tx_hash: std::string::String,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CkbTxOutput {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub tx_hash: std::string::String,
     #[prost(string, repeated, tag = "2")]
+    #[serde(default)]
     pub witnesses: ::std::vec::Vec<std::string::String>,
 }
@@ -19,6 +19,13 @@ pub struct HdVersion {
     prv_version: [u8; 4],
 }
 
+/// The network and the script type implied by the version of an extended public key
+pub struct Slip132Version {
+    pub_version: [u8; 4],
+    network: &'static str,
+    seg_wit: &'static str,
+}
+
 lazy_static! {
     static ref BTC_FORK_NETWORKS: RwLock<Vec<BtcForkNetwork>> = {
         let mut networks = Vec::new();
@@ -172,6 +179,67 @@ lazy_static! {
         });
         RwLock::new(versions)
     };
+
+    // https://github.com/satoshilabs/slips/blob/master/slip-0132.md, the multisig ones are
+    // not supported
+    static ref SLIP132_VERSIONS: Vec<Slip132Version> = {
+        let mut versions = Vec::new();
+        // xpub
+        versions.push(Slip132Version {
+            pub_version: [0x04, 0x88, 0xB2, 0x1E],
+            network: "MAINNET",
+            seg_wit: "NONE",
+        });
+        // ypub
+        versions.push(Slip132Version {
+            pub_version: [0x04, 0x9D, 0x7C, 0xB2],
+            network: "MAINNET",
+            seg_wit: "P2WPKH",
+        });
+        // zpub
+        versions.push(Slip132Version {
+            pub_version: [0x04, 0xB2, 0x47, 0x46],
+            network: "MAINNET",
+            seg_wit: "SEGWIT",
+        });
+        // tpub
+        versions.push(Slip132Version {
+            pub_version: [0x04, 0x35, 0x87, 0xCF],
+            network: "TESTNET",
+            seg_wit: "NONE",
+        });
+        // upub
+        versions.push(Slip132Version {
+            pub_version: [0x04, 0x4A, 0x52, 0x62],
+            network: "TESTNET",
+            seg_wit: "P2WPKH",
+        });
+        // vpub
+        versions.push(Slip132Version {
+            pub_version: [0x04, 0x5F, 0x1C, 0xF6],
+            network: "TESTNET",
+            seg_wit: "SEGWIT",
+        });
+        // Ltub
+        versions.push(Slip132Version {
+            pub_version: [0x01, 0x9D, 0xA4, 0x62],
+            network: "MAINNET",
+            seg_wit: "NONE",
+        });
+        // Mtub
+        versions.push(Slip132Version {
+            pub_version: [0x01, 0xB2, 0x6E, 0xF6],
+            network: "MAINNET",
+            seg_wit: "P2WPKH",
+        });
+        // ttub
+        versions.push(Slip132Version {
+            pub_version: [0x04, 0x36, 0xF6, 0xE1],
+            network: "TESTNET",
+            seg_wit: "NONE",
+        });
+        versions
+    };
 }
 
 // LTC address prefix: https://bitcoin.stackexchange.com/questions/62781/litecoin-constants-and-prefixes
@@ -225,3 +293,11 @@ pub fn pub_version_from_prv_version(prefix: &[u8]) -> Option<[u8; 4]> {
         version
     })
 }
+
+/// The (network, seg_wit) of a SLIP-132 extended public key version
+pub fn network_from_pub_version(prefix: &[u8]) -> Option<(String, String)> {
+    SLIP132_VERSIONS
+        .iter()
+        .find(|x| x.pub_version.eq(prefix))
+        .map(|x| (x.network.to_string(), x.seg_wit.to_string()))
+}
@@ -3,8 +3,8 @@ pub mod coin_info;
 pub mod curve;
 
 pub use btc_fork_network::{
-    coin_from_xpub_prefix, network_form_hrp, network_from_coin, pub_version_from_prv_version,
-    BtcForkNetwork,
+    coin_from_xpub_prefix, network_form_hrp, network_from_coin, network_from_pub_version,
+    pub_version_from_prv_version, BtcForkNetwork,
 };
 pub use coin_info::CoinInfo;
 pub use curve::CurveType;
@@ -1,16 +1,24 @@
 use crate::hash::hex_dsha256;
 use crate::numberic_util;
+use crate::secret::Secret;
 use crate::Error;
 use crate::Result;
+use aes_gcm::aead::generic_array::typenum::Unsigned;
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::Aes256Gcm;
 use bitcoin_hashes::hex::{FromHex, ToHex};
+use chacha20poly1305::ChaCha20Poly1305;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::str::FromStr;
+use zeroize::Zeroizing;
 
 const CREDENTIAL_LEN: usize = 64usize;
 
 pub type Credential = [u8; CREDENTIAL_LEN];
 
-fn default_kdf_rounds() -> u32 {
+pub fn default_kdf_rounds() -> u32 {
     let v = env::var("KDF_ROUNDS");
     if v.is_err() {
         *crate::KDF_ROUNDS.read() as u32
@@ -19,11 +27,19 @@ fn default_kdf_rounds() -> u32 {
     }
 }
 
+/// A secret encrypted by the derived key of a keystore besides its own secret
+///
+/// The pair keeps the cipher it's encrypted by. The authenticated ciphers carry the tag in
+/// `enc_str`, an `aes-128-ctr` pair is authenticated by `mac`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EncPair {
     pub enc_str: String,
     pub nonce: String,
+    #[serde(default)]
+    pub cipher: Cipher,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub mac: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,12 +49,27 @@ struct CipherParams {
 }
 
 pub trait KdfParams: Default {
-    fn kdf_key() -> String;
+    fn kdf_key(&self) -> String;
     fn validate(&self) -> Result<()>;
-    fn generate_derived_key(&self, password: &[u8], out: &mut [u8]);
+    fn generate_derived_key(&self, password: &[u8], out: &mut [u8]) -> Result<()>;
     fn set_salt(&mut self, salt: &str);
 }
 
+/// the highest costs accepted, so a keystore from elsewhere can't make the kdf run for hours or
+/// exhaust the memory
+const MAX_PBKDF2_ROUNDS: u32 = 10_000_000;
+/// bytes, scrypt takes 128 * r * n
+const MAX_SCRYPT_MEMORY: u64 = 1 << 30;
+const MAX_SCRYPT_P: u32 = 16;
+/// KiB
+const MAX_ARGON2_MEMORY: u32 = 1 << 20;
+const MAX_ARGON2_TIME: u32 = 16;
+const MAX_ARGON2_PARALLELISM: u32 = 16;
+
+fn salt_bytes(salt: &str) -> Result<Vec<u8>> {
+    FromHex::from_hex(salt).map_err(|_| Error::KdfParamsInvalid.into())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Pbkdf2Params {
@@ -59,22 +90,39 @@ impl Default for Pbkdf2Params {
     }
 }
 
+impl Pbkdf2Params {
+    pub fn with_rounds(c: u32) -> Pbkdf2Params {
+        Pbkdf2Params {
+            c,
+            ..Pbkdf2Params::default()
+        }
+    }
+
+    fn valid_cost(&self) -> bool {
+        self.c > 0 && self.c <= MAX_PBKDF2_ROUNDS && self.dklen > 0 && self.prf == "hmac-sha256"
+    }
+}
+
 impl KdfParams for Pbkdf2Params {
-    fn kdf_key() -> String {
+    fn kdf_key(&self) -> String {
         "pbkdf2".to_owned()
     }
 
     fn validate(&self) -> Result<()> {
-        if self.dklen == 0 || self.c == 0 || self.salt.is_empty() || self.prf.is_empty() {
+        if !self.valid_cost() || self.salt.is_empty() {
             Err(Error::KdfParamsInvalid.into())
         } else {
             Ok(())
         }
     }
 
-    fn generate_derived_key(&self, password: &[u8], out: &mut [u8]) {
-        let salt_bytes: Vec<u8> = FromHex::from_hex(&self.salt).unwrap();
+    fn generate_derived_key(&self, password: &[u8], out: &mut [u8]) -> Result<()> {
+        if !self.valid_cost() {
+            return Err(Error::KdfParamsInvalid.into());
+        }
+        let salt_bytes = salt_bytes(&self.salt)?;
         pbkdf2::pbkdf2::<hmac::Hmac<sha2::Sha256>>(password, &salt_bytes, self.c as usize, out);
+        Ok(())
     }
 
     fn set_salt(&mut self, salt: &str) {
@@ -104,26 +152,144 @@ impl Default for SCryptParams {
     }
 }
 
+impl SCryptParams {
+    /// zero keeps the default value of the param
+    pub fn with_params(n: u32, r: u32, p: u32) -> SCryptParams {
+        let default = SCryptParams::default();
+        SCryptParams {
+            n: if n == 0 { default.n } else { n },
+            r: if r == 0 { default.r } else { r },
+            p: if p == 0 { default.p } else { p },
+            ..default
+        }
+    }
+
+    fn valid_cost(&self) -> bool {
+        self.n > 1
+            && self.n.is_power_of_two()
+            && self.r > 0
+            && self.p > 0
+            && self.p <= MAX_SCRYPT_P
+            && 128 * u64::from(self.r) * u64::from(self.n) <= MAX_SCRYPT_MEMORY
+            && self.dklen > 0
+    }
+}
+
 impl KdfParams for SCryptParams {
-    fn kdf_key() -> String {
+    fn kdf_key(&self) -> String {
         "scrypt".to_owned()
     }
 
     fn validate(&self) -> Result<()> {
-        if self.dklen == 0 || self.n == 0 || self.salt.is_empty() || self.p == 0 || self.r == 0 {
+        if !self.valid_cost() || self.salt.is_empty() {
             Err(Error::KdfParamsInvalid.into())
         } else {
             Ok(())
         }
     }
 
-    fn generate_derived_key(&self, password: &[u8], out: &mut [u8]) {
-        let salt_bytes: Vec<u8> = FromHex::from_hex(&self.salt).unwrap();
-        let log_n = (self.n as f64).log2().round();
-        let inner_params =
-            scrypt::ScryptParams::new(log_n as u8, self.r, self.p).expect("init scrypt params");
+    fn generate_derived_key(&self, password: &[u8], out: &mut [u8]) -> Result<()> {
+        if !self.valid_cost() {
+            return Err(Error::KdfParamsInvalid.into());
+        }
+        let salt_bytes = salt_bytes(&self.salt)?;
+        let log_n = self.n.trailing_zeros() as u8;
+        let inner_params = scrypt::ScryptParams::new(log_n, self.r, self.p)
+            .map_err(|_| Error::KdfParamsInvalid)?;
+
+        scrypt::scrypt(password, &salt_bytes, &inner_params, out)
+            .map_err(|_| Error::KdfParamsInvalid)?;
+        Ok(())
+    }
 
-        scrypt::scrypt(password, &salt_bytes, &inner_params, out).expect("can not execute scrypt");
+    fn set_salt(&mut self, salt: &str) {
+        self.salt = salt.to_owned();
+    }
+}
+
+/// Params of argon2id, `memory` is in KiB
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Argon2Params {
+    memory: u32,
+    time: u32,
+    parallelism: u32,
+    dklen: u32,
+    salt: String,
+}
+
+impl Default for Argon2Params {
+    fn default() -> Self {
+        Argon2Params {
+            memory: 65536,
+            time: 3,
+            parallelism: 4,
+            dklen: 32,
+            salt: "".to_string(),
+        }
+    }
+}
+
+impl Argon2Params {
+    /// zero keeps the default value of the param
+    pub fn with_params(memory: u32, time: u32, parallelism: u32) -> Argon2Params {
+        let default = Argon2Params::default();
+        Argon2Params {
+            memory: if memory == 0 { default.memory } else { memory },
+            time: if time == 0 { default.time } else { time },
+            parallelism: if parallelism == 0 {
+                default.parallelism
+            } else {
+                parallelism
+            },
+            ..default
+        }
+    }
+
+    fn valid_cost(&self) -> bool {
+        self.time > 0
+            && self.time <= MAX_ARGON2_TIME
+            && self.parallelism > 0
+            && self.parallelism <= MAX_ARGON2_PARALLELISM
+            && self.memory >= 8 * self.parallelism
+            && self.memory <= MAX_ARGON2_MEMORY
+            && self.dklen > 0
+    }
+}
+
+impl KdfParams for Argon2Params {
+    fn kdf_key(&self) -> String {
+        "argon2id".to_owned()
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !self.valid_cost() || self.salt.is_empty() {
+            Err(Error::KdfParamsInvalid.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn generate_derived_key(&self, password: &[u8], out: &mut [u8]) -> Result<()> {
+        if !self.valid_cost() {
+            return Err(Error::KdfParamsInvalid.into());
+        }
+        let salt_bytes = salt_bytes(&self.salt)?;
+        let config = argon2::Config {
+            variant: argon2::Variant::Argon2id,
+            version: argon2::Version::Version13,
+            mem_cost: self.memory,
+            time_cost: self.time,
+            lanes: self.parallelism,
+            hash_length: out.len() as u32,
+            ..argon2::Config::default()
+        };
+        let derived_key = Zeroizing::new(
+            argon2::hash_raw(password, &salt_bytes, &config)
+                .map_err(|_| Error::KdfParamsInvalid)?,
+        );
+        out.copy_from_slice(&derived_key);
+        Ok(())
     }
 
     fn set_salt(&mut self, salt: &str) {
@@ -131,17 +297,102 @@ impl KdfParams for SCryptParams {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Params of any supported kdf, so keystores are loaded whatever kdf they use
+///
+/// The variant is picked by the fields of `kdfparams`, `kdf_key` returns the matching `kdf`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum Kdf {
+    Pbkdf2(Pbkdf2Params),
+    Scrypt(SCryptParams),
+    Argon2id(Argon2Params),
+}
+
+impl Default for Kdf {
+    fn default() -> Self {
+        Kdf::Pbkdf2(Pbkdf2Params::default())
+    }
+}
+
+impl From<Pbkdf2Params> for Kdf {
+    fn from(params: Pbkdf2Params) -> Self {
+        Kdf::Pbkdf2(params)
+    }
+}
+
+impl From<SCryptParams> for Kdf {
+    fn from(params: SCryptParams) -> Self {
+        Kdf::Scrypt(params)
+    }
+}
+
+impl From<Argon2Params> for Kdf {
+    fn from(params: Argon2Params) -> Self {
+        Kdf::Argon2id(params)
+    }
+}
+
+impl Kdf {
+    /// check the cost params of a new keystore, the salt is set when encrypting
+    pub fn validate_cost(&self) -> Result<()> {
+        let valid = match self {
+            Kdf::Pbkdf2(params) => params.valid_cost(),
+            Kdf::Scrypt(params) => params.valid_cost(),
+            Kdf::Argon2id(params) => params.valid_cost(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::KdfParamsInvalid.into())
+        }
+    }
+}
+
+impl KdfParams for Kdf {
+    fn kdf_key(&self) -> String {
+        match self {
+            Kdf::Pbkdf2(params) => params.kdf_key(),
+            Kdf::Scrypt(params) => params.kdf_key(),
+            Kdf::Argon2id(params) => params.kdf_key(),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self {
+            Kdf::Pbkdf2(params) => params.validate(),
+            Kdf::Scrypt(params) => params.validate(),
+            Kdf::Argon2id(params) => params.validate(),
+        }
+    }
+
+    fn generate_derived_key(&self, password: &[u8], out: &mut [u8]) -> Result<()> {
+        match self {
+            Kdf::Pbkdf2(params) => params.generate_derived_key(password, out),
+            Kdf::Scrypt(params) => params.generate_derived_key(password, out),
+            Kdf::Argon2id(params) => params.generate_derived_key(password, out),
+        }
+    }
+
+    fn set_salt(&mut self, salt: &str) {
+        match self {
+            Kdf::Pbkdf2(params) => params.set_salt(salt),
+            Kdf::Scrypt(params) => params.set_salt(salt),
+            Kdf::Argon2id(params) => params.set_salt(salt),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 struct CacheDerivedKey {
     hashed_key: String,
-    derived_key: Vec<u8>,
+    derived_key: Secret<Vec<u8>>,
 }
 
 impl CacheDerivedKey {
     pub fn new(key: &str, derived_key: &[u8]) -> CacheDerivedKey {
         CacheDerivedKey {
             hashed_key: Self::hash(key),
-            derived_key: derived_key.to_vec(),
+            derived_key: Secret::new(derived_key.to_vec()),
         }
     }
 
@@ -151,17 +402,117 @@ impl CacheDerivedKey {
 
     pub fn get_derived_key(&self, key: &str) -> Result<Vec<u8>> {
         if self.hashed_key == Self::hash(key) {
-            Ok(self.derived_key.clone())
+            Ok(self.derived_key.to_vec())
         } else {
             Err(Error::PasswordIncorrect.into())
         }
     }
 }
 
+/// The cipher of the keystore secret
+///
+/// `aes-128-ctr` is the cipher of the v3 keystore and only uses the first 16 bytes of the derived
+/// key, the authenticated ciphers use the 32 bytes after the mac key.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Cipher {
+    #[serde(rename = "aes-128-ctr")]
+    Aes128Ctr,
+    #[serde(rename = "aes-256-gcm")]
+    Aes256Gcm,
+    #[serde(rename = "chacha20-poly1305")]
+    ChaCha20Poly1305,
+}
+
+impl Default for Cipher {
+    fn default() -> Self {
+        Cipher::Aes128Ctr
+    }
+}
+
+impl FromStr for Cipher {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "aes-128-ctr" => Ok(Cipher::Aes128Ctr),
+            "aes-256-gcm" => Ok(Cipher::Aes256Gcm),
+            "chacha20-poly1305" => Ok(Cipher::ChaCha20Poly1305),
+            _ => Err(Error::UnsupportedCipher.into()),
+        }
+    }
+}
+
+impl Cipher {
+    fn iv_len(self) -> usize {
+        match self {
+            Cipher::Aes128Ctr => 16,
+            Cipher::Aes256Gcm | Cipher::ChaCha20Poly1305 => 12,
+        }
+    }
+
+    fn encrypt(self, derived_key: &[u8], iv: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Cipher::Aes128Ctr => {
+                super::aes::ctr::encrypt_nopadding(data, Self::ctr_key(derived_key)?, iv)
+            }
+            Cipher::Aes256Gcm => aead_encrypt::<Aes256Gcm>(Self::aead_key(derived_key)?, iv, data),
+            Cipher::ChaCha20Poly1305 => {
+                aead_encrypt::<ChaCha20Poly1305>(Self::aead_key(derived_key)?, iv, data)
+            }
+        }
+    }
+
+    fn decrypt(self, derived_key: &[u8], iv: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Cipher::Aes128Ctr => {
+                super::aes::ctr::decrypt_nopadding(data, Self::ctr_key(derived_key)?, iv)
+            }
+            Cipher::Aes256Gcm => aead_decrypt::<Aes256Gcm>(Self::aead_key(derived_key)?, iv, data),
+            Cipher::ChaCha20Poly1305 => {
+                aead_decrypt::<ChaCha20Poly1305>(Self::aead_key(derived_key)?, iv, data)
+            }
+        }
+    }
+
+    fn ctr_key(derived_key: &[u8]) -> Result<&[u8]> {
+        if derived_key.len() < 16 {
+            return Err(Error::InvalidKeyIvLength.into());
+        }
+        Ok(&derived_key[0..16])
+    }
+
+    fn aead_key(derived_key: &[u8]) -> Result<&[u8]> {
+        if derived_key.len() < CREDENTIAL_LEN {
+            return Err(Error::InvalidKeyIvLength.into());
+        }
+        Ok(&derived_key[32..64])
+    }
+}
+
+fn aead_encrypt<C: NewAead + Aead>(key: &[u8], iv: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    if key.len() != C::KeySize::to_usize() || iv.len() != C::NonceSize::to_usize() {
+        return Err(Error::InvalidKeyIvLength.into());
+    }
+    let cipher = C::new(GenericArray::clone_from_slice(key));
+    cipher
+        .encrypt(GenericArray::from_slice(iv), data)
+        .map_err(|_| Error::InvalidCiphertext.into())
+}
+
+fn aead_decrypt<C: NewAead + Aead>(key: &[u8], iv: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    if key.len() != C::KeySize::to_usize() || iv.len() != C::NonceSize::to_usize() {
+        return Err(Error::InvalidKeyIvLength.into());
+    }
+    let cipher = C::new(GenericArray::clone_from_slice(key));
+    cipher
+        .decrypt(GenericArray::from_slice(iv), data)
+        .map_err(|_| Error::CipherAuthFailed.into())
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Crypto<T: KdfParams> {
-    cipher: String,
+    cipher: Cipher,
     cipherparams: CipherParams,
     ciphertext: String,
     kdf: String,
@@ -176,105 +527,227 @@ where
     T: KdfParams,
 {
     pub fn new(password: &str, origin: &[u8]) -> Crypto<T> {
-        let mut param = T::default();
+        Self::new_with_params(password, origin, T::default())
+    }
+
+    pub fn new_with_params(password: &str, origin: &[u8], kdfparams: T) -> Crypto<T> {
+        Self::new_with_cipher(password, origin, kdfparams, Cipher::Aes128Ctr)
+    }
+
+    pub fn new_with_cipher(
+        password: &str,
+        origin: &[u8],
+        kdfparams: T,
+        cipher: Cipher,
+    ) -> Crypto<T> {
+        Self::new_with_derived_key(password, origin, kdfparams, cipher).0
+    }
+
+    /// `new_with_cipher` which also returns the output of its kdf, so more can be encrypted by
+    /// `derive_enc_pair_by_derived_key` without running the kdf again
+    pub fn new_with_derived_key(
+        password: &str,
+        origin: &[u8],
+        kdfparams: T,
+        cipher: Cipher,
+    ) -> (Crypto<T>, Zeroizing<Vec<u8>>) {
+        let mut param = kdfparams;
         param.set_salt(&numberic_util::random_iv(32).to_hex());
-        let iv = numberic_util::random_iv(16);
 
         let mut crypto = Crypto {
-            cipher: "aes-128-ctr".to_owned(),
-            cipherparams: CipherParams { iv: iv.to_hex() },
+            cipher,
+            cipherparams: CipherParams { iv: "".to_owned() },
             ciphertext: String::from(""),
-            kdf: T::kdf_key(),
+            kdf: param.kdf_key(),
             kdfparams: param,
             mac: String::from(""),
             cached_derived_key: None,
         };
 
-        let derived_key = crypto
-            .generate_derived_key(password)
-            .expect("new crypto generate_derived_key");
-
-        let ciphertext = crypto.encrypt(password, origin);
-        crypto.ciphertext = ciphertext.to_hex();
-        let mac = Self::generate_mac(&derived_key, &ciphertext);
-        crypto.mac = mac.to_hex();
+        let derived_key = Zeroizing::new(
+            crypto
+                .generate_derived_key(password)
+                .expect("new crypto generate_derived_key"),
+        );
         crypto
+            .encrypt(&derived_key, origin)
+            .expect("new crypto encrypt");
+        (crypto, derived_key)
+    }
+
+    /// the kdf and its cost, the salt is replaced whenever the secret is encrypted again
+    pub fn kdfparams(&self) -> &T {
+        &self.kdfparams
+    }
+
+    pub fn cipher(&self) -> Cipher {
+        self.cipher
+    }
+
+    /// re-encrypt the secret by `cipher` with a fresh iv, the kdf and its salt are kept
+    pub fn upgrade_cipher(&mut self, derived_key: &[u8], cipher: Cipher) -> Result<()> {
+        let origin = Zeroizing::new(self.decrypt_by_derived_key(derived_key)?);
+        let previous = self.cipher;
+        self.cipher = cipher;
+        let ret = self.encrypt(derived_key, &origin);
+        if ret.is_err() {
+            self.cipher = previous;
+        }
+        ret
     }
 
+    /// The cached derived key is used when `key` is the cached one, otherwise the kdf runs
     pub fn generate_derived_key(&self, key: &str) -> Result<Vec<u8>> {
         if let Some(ckd) = &self.cached_derived_key {
-            ckd.get_derived_key(key)
-        } else {
-            let mut derived_key: Credential = [0u8; CREDENTIAL_LEN];
-            self.kdfparams
-                .generate_derived_key(key.as_bytes(), &mut derived_key);
-            if &self.mac != "" && !self.verify_derived_key(&derived_key) {
-                return Err(Error::PasswordIncorrect.into());
+            if let Ok(derived_key) = ckd.get_derived_key(key) {
+                return Ok(derived_key);
             }
-            Ok(derived_key.to_vec())
         }
+
+        let mut derived_key: Zeroizing<Credential> = Zeroizing::new([0u8; CREDENTIAL_LEN]);
+        self.kdfparams
+            .generate_derived_key(key.as_bytes(), &mut *derived_key)?;
+        if &self.mac != "" && !self.verify_derived_key(&derived_key[..]) {
+            return Err(Error::PasswordIncorrect.into());
+        }
+        Ok(derived_key.to_vec())
     }
 
     pub fn decrypt(&self, password: &str) -> Result<Vec<u8>> {
+        let derived_key = Zeroizing::new(self.generate_derived_key(password)?);
+
+        if !self.verify_derived_key(&derived_key) {
+            return Err(Error::PasswordIncorrect.into());
+        }
+
+        self.decrypt_ciphertext(&derived_key)
+    }
+
+    /// decrypt by the output of the kdf, skips the kdf entirely
+    pub fn decrypt_by_derived_key(&self, derived_key: &[u8]) -> Result<Vec<u8>> {
+        if !self.verify_derived_key(derived_key) {
+            return Err(Error::DerivedKeyIncorrect.into());
+        }
+
+        self.decrypt_ciphertext(derived_key)
+    }
+
+    fn decrypt_ciphertext(&self, derived_key: &[u8]) -> Result<Vec<u8>> {
         let encrypted: Vec<u8> = FromHex::from_hex(&self.ciphertext).expect("ciphertext");
         let iv: Vec<u8> = FromHex::from_hex(&self.cipherparams.iv).expect("iv");
-        self.decrypt_data(password, &encrypted, &iv)
+        self.cipher.decrypt(derived_key, &iv, &encrypted)
     }
 
-    fn encrypt(&self, password: &str, origin: &[u8]) -> Vec<u8> {
-        let derived_key = self
-            .generate_derived_key(password)
-            .expect("crypto::encrypt must no error");
-        let key = &derived_key[0..16];
-        let iv: Vec<u8> = FromHex::from_hex(&self.cipherparams.iv).unwrap();
-        super::aes::ctr::encrypt_nopadding(origin, key, &iv)
-            .expect("encrypt_nopadding key or iv's length must be 16")
+    /// encrypt with a fresh iv and update the mac
+    fn encrypt(&mut self, derived_key: &[u8], origin: &[u8]) -> Result<()> {
+        let iv = numberic_util::random_iv(self.cipher.iv_len());
+        let ciphertext = self.cipher.encrypt(derived_key, &iv, origin)?;
+        self.cipherparams.iv = iv.to_hex();
+        self.ciphertext = ciphertext.to_hex();
+        self.mac = Self::generate_mac(derived_key, &ciphertext).to_hex();
+        Ok(())
     }
 
+    /// encrypt `origin` by the cipher of the keystore with a fresh nonce
     pub fn derive_enc_pair(&self, password: &str, origin: &[u8]) -> Result<EncPair> {
-        let iv = numberic_util::random_iv(16);
-        let encrypted_data = self.encrypt_data(password, origin, &iv)?;
-        Ok(EncPair {
-            enc_str: encrypted_data.to_hex(),
-            nonce: iv.to_hex(),
-        })
-    }
+        let derived_key = Zeroizing::new(self.generate_derived_key(password)?);
+        if !self.verify_derived_key(&derived_key) {
+            return Err(Error::PasswordIncorrect.into());
+        }
 
-    pub fn decrypt_enc_pair(&self, password: &str, enc_pair: &EncPair) -> Result<Vec<u8>> {
-        let encrypted: Vec<u8> = FromHex::from_hex(&enc_pair.enc_str).unwrap();
-        let iv: Vec<u8> = FromHex::from_hex(&enc_pair.nonce).unwrap();
-        self.decrypt_data(password, &encrypted, &iv)
+        self.encrypt_enc_pair(&derived_key, origin)
     }
 
-    pub fn verify_password(&self, password: &str) -> bool {
-        let derived_key_ret = self.generate_derived_key(password);
+    /// encrypt an enc pair by the output of the kdf, skips the kdf entirely
+    pub fn derive_enc_pair_by_derived_key(
+        &self,
+        derived_key: &[u8],
+        origin: &[u8],
+    ) -> Result<EncPair> {
+        if !self.verify_derived_key(derived_key) {
+            return Err(Error::DerivedKeyIncorrect.into());
+        }
 
-        derived_key_ret.is_ok() && self.verify_derived_key(&derived_key_ret.expect(""))
+        self.encrypt_enc_pair(derived_key, origin)
     }
 
-    fn encrypt_data(&self, password: &str, origin: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
-        let derived_key = self.generate_derived_key(password)?;
-
+    pub fn decrypt_enc_pair(&self, password: &str, enc_pair: &EncPair) -> Result<Vec<u8>> {
+        let derived_key = Zeroizing::new(self.generate_derived_key(password)?);
         if !self.verify_derived_key(&derived_key) {
             return Err(Error::PasswordIncorrect.into());
         }
 
-        let key = &derived_key[0..16];
-        super::aes::ctr::encrypt_nopadding(origin, key, &iv)
+        Self::decrypt_enc_pair_data(&derived_key, enc_pair)
     }
 
-    fn decrypt_data(&self, password: &str, encrypted: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
-        let derived_key = self.generate_derived_key(password)?;
+    /// decrypt an enc pair by the output of the kdf, skips the kdf entirely
+    pub fn decrypt_enc_pair_by_derived_key(
+        &self,
+        derived_key: &[u8],
+        enc_pair: &EncPair,
+    ) -> Result<Vec<u8>> {
+        if !self.verify_derived_key(derived_key) {
+            return Err(Error::DerivedKeyIncorrect.into());
+        }
 
-        if !self.verify_derived_key(&derived_key) {
-            return Err(Error::PasswordIncorrect.into());
+        Self::decrypt_enc_pair_data(derived_key, enc_pair)
+    }
+
+    fn encrypt_enc_pair(&self, derived_key: &[u8], origin: &[u8]) -> Result<EncPair> {
+        let nonce = numberic_util::random_iv(self.cipher.iv_len());
+        let encrypted = self.cipher.encrypt(derived_key, &nonce, origin)?;
+        let mac = if self.cipher == Cipher::Aes128Ctr {
+            Self::generate_enc_pair_mac(derived_key, &nonce, &encrypted)?.to_hex()
+        } else {
+            "".to_owned()
+        };
+        Ok(EncPair {
+            enc_str: encrypted.to_hex(),
+            nonce: nonce.to_hex(),
+            cipher: self.cipher,
+            mac,
+        })
+    }
+
+    fn decrypt_enc_pair_data(derived_key: &[u8], enc_pair: &EncPair) -> Result<Vec<u8>> {
+        let encrypted: Vec<u8> =
+            FromHex::from_hex(&enc_pair.enc_str).map_err(|_| Error::InvalidCiphertext)?;
+        let nonce: Vec<u8> =
+            FromHex::from_hex(&enc_pair.nonce).map_err(|_| Error::InvalidKeyIvLength)?;
+        if enc_pair.cipher == Cipher::Aes128Ctr
+            && enc_pair.mac
+                != Self::generate_enc_pair_mac(derived_key, &nonce, &encrypted)?.to_hex()
+        {
+            return Err(Error::CipherAuthFailed.into());
         }
+        enc_pair.cipher.decrypt(derived_key, &nonce, &encrypted)
+    }
 
-        let key = &derived_key[0..16];
-        super::aes::ctr::decrypt_nopadding(encrypted, key, &iv)
+    /// the mac key is the part of the derived key which `aes-128-ctr` doesn't use, so a pair mac
+    /// never matches the mac of the keystore secret
+    fn generate_enc_pair_mac(
+        derived_key: &[u8],
+        nonce: &[u8],
+        encrypted: &[u8],
+    ) -> Result<Vec<u8>> {
+        if derived_key.len() < CREDENTIAL_LEN {
+            return Err(Error::InvalidKeyIvLength.into());
+        }
+        let data = [&derived_key[32..64], nonce, encrypted].concat();
+        Ok(tiny_keccak::keccak256(&Zeroizing::new(data)).to_vec())
+    }
+
+    pub fn verify_password(&self, password: &str) -> bool {
+        match self.generate_derived_key(password) {
+            Ok(derived_key) => self.verify_derived_key(&Zeroizing::new(derived_key)),
+            Err(_) => false,
+        }
     }
 
     pub fn verify_derived_key(&self, dk: &[u8]) -> bool {
+        if dk.len() < 32 {
+            return false;
+        }
         let cipher_bytes = Vec::from_hex(&self.ciphertext).expect("vec::from_hex");
         let mac = Self::generate_mac(&dk, &cipher_bytes);
         self.mac == mac.to_hex()
@@ -312,13 +785,17 @@ mod tests {
             salt: "".to_owned(),
         };
         assert_eq!(default, param);
+
+        let param = Pbkdf2Params::with_rounds(1024);
+        assert_eq!(1024, param.c);
+        assert_eq!("hmac-sha256", param.prf);
     }
 
     #[test]
     pub fn new_crypto() {
         let crypto: Crypto<Pbkdf2Params> = Crypto::new(TEST_PASSWORD, "TokenCoreX".as_bytes());
         assert_ne!("", crypto.ciphertext);
-        assert_ne!("", crypto.cipher);
+        assert_eq!(Cipher::Aes128Ctr, crypto.cipher);
         assert_ne!("", crypto.mac);
         assert_ne!("", crypto.cipherparams.iv);
         assert_ne!("", crypto.kdfparams.salt);
@@ -372,6 +849,76 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn enc_pair_auth_test() {
+        for cipher in &[
+            Cipher::Aes128Ctr,
+            Cipher::Aes256Gcm,
+            Cipher::ChaCha20Poly1305,
+        ] {
+            let crypto: Crypto<Pbkdf2Params> = Crypto::new_with_cipher(
+                TEST_PASSWORD,
+                "TokenCoreX".as_bytes(),
+                Pbkdf2Params::with_rounds(1024),
+                *cipher,
+            );
+            let enc_pair = crypto
+                .derive_enc_pair(TEST_PASSWORD, "passphrase".as_bytes())
+                .unwrap();
+            assert_eq!(enc_pair.cipher, *cipher);
+            assert_eq!(enc_pair.mac.is_empty(), *cipher != Cipher::Aes128Ctr);
+
+            let mut tampered = enc_pair.clone();
+            let mut encrypted = Vec::from_hex(&tampered.enc_str).unwrap();
+            encrypted[0] ^= 1;
+            tampered.enc_str = encrypted.to_hex();
+            let ret = crypto.decrypt_enc_pair(TEST_PASSWORD, &tampered);
+            assert_eq!(
+                Error::CipherAuthFailed,
+                ret.err().unwrap().downcast::<crate::Error>().unwrap()
+            );
+        }
+
+        // a ctr pair without a mac is refused
+        let crypto: Crypto<Pbkdf2Params> = Crypto::new_with_params(
+            TEST_PASSWORD,
+            "TokenCoreX".as_bytes(),
+            Pbkdf2Params::with_rounds(1024),
+        );
+        let mut enc_pair = crypto
+            .derive_enc_pair(TEST_PASSWORD, "passphrase".as_bytes())
+            .unwrap();
+        enc_pair.mac = "".to_owned();
+        let ret = crypto.decrypt_enc_pair(TEST_PASSWORD, &enc_pair);
+        assert_eq!(
+            Error::CipherAuthFailed,
+            ret.err().unwrap().downcast::<crate::Error>().unwrap()
+        );
+    }
+
+    #[test]
+    pub fn decrypt_enc_pair_by_derived_key_test() {
+        let crypto: Crypto<Pbkdf2Params> = Crypto::new_with_params(
+            TEST_PASSWORD,
+            "TokenCoreX".as_bytes(),
+            Pbkdf2Params::with_rounds(1024),
+        );
+        let enc_pair = crypto
+            .derive_enc_pair(TEST_PASSWORD, "passphrase".as_bytes())
+            .unwrap();
+        let derived_key = crypto.generate_derived_key(TEST_PASSWORD).unwrap();
+        let decrypted = crypto
+            .decrypt_enc_pair_by_derived_key(&derived_key, &enc_pair)
+            .unwrap();
+        assert_eq!("passphrase", String::from_utf8(decrypted).unwrap());
+
+        let ret = crypto.decrypt_enc_pair_by_derived_key(&[0u8; 64], &enc_pair);
+        assert_eq!(
+            Error::DerivedKeyIncorrect,
+            ret.err().unwrap().downcast::<crate::Error>().unwrap()
+        );
+    }
+
     #[test]
     pub fn kdfparams_trait_validate_test() {
         let err = Pbkdf2Params::default().validate().err().unwrap();
@@ -391,6 +938,44 @@ mod tests {
             err.downcast::<crate::Error>().unwrap()
         );
 
+        // the cost is bounded as well
+        let mut params = Pbkdf2Params::with_rounds(100_000_000);
+        params.set_salt("0x1234");
+        assert!(params.validate().is_err());
+        let kdf: Kdf = SCryptParams::with_params(1 << 20, 8, 1).into();
+        assert!(kdf.validate_cost().is_ok());
+        let kdf: Kdf = SCryptParams::with_params(1 << 24, 8, 1).into();
+        assert!(kdf.validate_cost().is_err());
+        let kdf: Kdf = SCryptParams::with_params(1 << 10, 8, 64).into();
+        assert!(kdf.validate_cost().is_err());
+        let kdf: Kdf = Argon2Params::with_params(1 << 21, 1, 1).into();
+        assert!(kdf.validate_cost().is_err());
+        let kdf: Kdf = Argon2Params::with_params(1024, 100, 1).into();
+        assert!(kdf.validate_cost().is_err());
+
+        // an invalid kdf fails instead of panicking
+        let mut params = SCryptParams::with_params(1 << 24, 8, 1);
+        params.set_salt("01020304");
+        let mut derived_key = [0; CREDENTIAL_LEN];
+        let err = params
+            .generate_derived_key(TEST_PASSWORD.as_bytes(), &mut derived_key)
+            .err()
+            .unwrap();
+        assert_eq!(
+            Error::KdfParamsInvalid,
+            err.downcast::<crate::Error>().unwrap()
+        );
+        let mut params = Pbkdf2Params::with_rounds(1024);
+        params.set_salt("not hex");
+        let err = params
+            .generate_derived_key(TEST_PASSWORD.as_bytes(), &mut derived_key)
+            .err()
+            .unwrap();
+        assert_eq!(
+            Error::KdfParamsInvalid,
+            err.downcast::<crate::Error>().unwrap()
+        );
+
         assert_eq!(*crate::KDF_ROUNDS.read() as u32, 262144);
 
         let v = env::var("KDF_ROUNDS");
@@ -410,7 +995,9 @@ mod tests {
         pbkdf2_param.c = 1024;
         pbkdf2_param.salt = "01020304010203040102030401020304".to_string();
         let mut derived_key = [0; CREDENTIAL_LEN];
-        pbkdf2_param.generate_derived_key(TEST_PASSWORD.as_bytes(), &mut derived_key);
+        pbkdf2_param
+            .generate_derived_key(TEST_PASSWORD.as_bytes(), &mut derived_key)
+            .unwrap();
         let dk_hex = derived_key.to_hex();
         assert_eq!("515c00df30d4eb0e5662030ccea231301ce44d685eb29aca04469f4d6b701898e75e51080a482dd46c04cf39308e7d228a0f70a45d7fa17cd4027d04c39f5e17", dk_hex);
     }
@@ -421,11 +1008,44 @@ mod tests {
         param.n = 1024;
         param.salt = "01020304010203040102030401020304".to_string();
         let mut derived_key = [0; CREDENTIAL_LEN];
-        param.generate_derived_key(TEST_PASSWORD.as_bytes(), &mut derived_key);
+        param
+            .generate_derived_key(TEST_PASSWORD.as_bytes(), &mut derived_key)
+            .unwrap();
         let dk_hex = derived_key.to_hex();
         assert_eq!("190fba2c4dcd250b67652b6ea401a286ba4afff692aa9700ce56edd5326cb23b05c9af493f8d3dccb8191437f8cb5d2c3ba718af64aee8a7f318eedf2af5eb3f", dk_hex);
     }
 
+    #[test]
+    pub fn argon2id_test() {
+        let err = Argon2Params::default().validate().err().unwrap();
+        assert_eq!(
+            Error::KdfParamsInvalid,
+            err.downcast::<crate::Error>().unwrap()
+        );
+
+        let params = Argon2Params::with_params(1024, 1, 1);
+        let crypto: Crypto<Kdf> =
+            Crypto::new_with_params(TEST_PASSWORD, "TokenCoreX".as_bytes(), params.into());
+        assert_eq!(crypto.kdf, "argon2id");
+        assert!(crypto.kdfparams.validate().is_ok());
+
+        let json = serde_json::to_string(&crypto).unwrap();
+        let crypto: Crypto<Kdf> = serde_json::from_str(&json).unwrap();
+        match crypto.kdfparams {
+            Kdf::Argon2id(ref params) => {
+                assert_eq!(params.memory, 1024);
+                assert_eq!(params.time, 1);
+                assert_eq!(params.parallelism, 1);
+            }
+            _ => panic!("argon2id params expected"),
+        }
+        assert_eq!(
+            "TokenCoreX",
+            String::from_utf8(crypto.decrypt(TEST_PASSWORD).unwrap()).unwrap()
+        );
+        assert!(!crypto.verify_password("WrongPassword"));
+    }
+
     #[test]
     pub fn decode_v3_keystore_crypto_test() {
         let data = r#"{
@@ -451,6 +1071,62 @@ mod tests {
         assert_eq!("L2hfzPyVC1jWH7n2QLTe7tVTb6btg9smp5UVzhEBxLYaSFF7sCZB", wif)
     }
 
+    #[test]
+    pub fn kdf_serde_test() {
+        let data = r#"{
+    "mac": "a10b412993ec783e854cb339b1f4165a013d41267adb561ed9ab47c209dea3ab",
+    "cipherparams": {
+      "iv": "799f757ee52b7c95aa76967fa908676c"
+    },
+    "kdfparams": {
+      "dklen": 32,
+      "r": 8,
+      "salt": "e3fa7f40fecac7f6c61326dfb6aba4697c2daba9ecd41f017e996ae15aa18a51",
+      "p": 1,
+      "n": 1024
+    },
+    "cipher": "aes-128-ctr",
+    "ciphertext": "d5c053f4893fbbaa0d58cc87d5b82abdbac55599a46fe6eb8e355487e5c4799039a4a4ae7f365db3d573946f3acb51a2cfb2aafe",
+    "kdf": "scrypt"
+  }"#;
+        let crypto: Crypto<Kdf> = serde_json::from_str(data).unwrap();
+        match crypto.kdfparams {
+            Kdf::Scrypt(ref params) => assert_eq!(params.n, 1024),
+            _ => panic!("scrypt params expected"),
+        }
+        let result = crypto.decrypt(TEST_PASSWORD).unwrap();
+        assert_eq!(
+            "L2hfzPyVC1jWH7n2QLTe7tVTb6btg9smp5UVzhEBxLYaSFF7sCZB",
+            String::from_utf8(result).unwrap()
+        );
+
+        let crypto: Crypto<Kdf> = Crypto::new_with_params(
+            TEST_PASSWORD,
+            "TokenCoreX".as_bytes(),
+            SCryptParams::with_params(1024, 8, 1).into(),
+        );
+        let json = serde_json::to_value(&crypto).unwrap();
+        assert_eq!(json["kdf"], "scrypt");
+        assert_eq!(json["kdfparams"]["n"], 1024);
+
+        let crypto: Crypto<Kdf> = Crypto::new_with_params(
+            TEST_PASSWORD,
+            "TokenCoreX".as_bytes(),
+            Pbkdf2Params::with_rounds(1024).into(),
+        );
+        let json = serde_json::to_string(&crypto).unwrap();
+        let crypto: Crypto<Kdf> = serde_json::from_str(&json).unwrap();
+        assert_eq!(crypto.kdf, "pbkdf2");
+        match crypto.kdfparams {
+            Kdf::Pbkdf2(ref params) => assert_eq!(params.c, 1024),
+            _ => panic!("pbkdf2 params expected"),
+        }
+        assert_eq!(
+            "TokenCoreX",
+            String::from_utf8(crypto.decrypt(TEST_PASSWORD).unwrap()).unwrap()
+        );
+    }
+
     #[test]
     pub fn deserialize_from_json() {
         let data = r#"
@@ -479,6 +1155,113 @@ mod tests {
         assert_eq!(crypto.ciphertext, "17ff4858e697455f4966c6072473f3501534bc20deb339b58aeb8db0bd9fe91777148d0a909f679fb6e3a7a64609034afeb72a");
     }
 
+    #[test]
+    pub fn decrypt_by_derived_key_test() {
+        let crypto: Crypto<Pbkdf2Params> = Crypto::new_with_params(
+            TEST_PASSWORD,
+            "TokenCoreX".as_bytes(),
+            Pbkdf2Params::with_rounds(1024),
+        );
+        let derived_key = crypto.generate_derived_key(TEST_PASSWORD).unwrap();
+
+        let decrypted = crypto.decrypt_by_derived_key(&derived_key).unwrap();
+        assert_eq!("TokenCoreX", String::from_utf8(decrypted).unwrap());
+
+        let ret = crypto.decrypt_by_derived_key(&[0u8; 64]);
+        assert_eq!(
+            Error::DerivedKeyIncorrect,
+            ret.err().unwrap().downcast::<crate::Error>().unwrap()
+        );
+        assert!(crypto.decrypt_by_derived_key(&[1u8; 4]).is_err());
+    }
+
+    #[test]
+    pub fn authenticated_cipher_test() {
+        assert_eq!(Cipher::Aes256Gcm, Cipher::from_str("aes-256-gcm").unwrap());
+        assert!(Cipher::from_str("aes-128-cbc").is_err());
+
+        for cipher in &[Cipher::Aes256Gcm, Cipher::ChaCha20Poly1305] {
+            let mut crypto: Crypto<Pbkdf2Params> = Crypto::new_with_cipher(
+                TEST_PASSWORD,
+                "TokenCoreX".as_bytes(),
+                Pbkdf2Params::with_rounds(1024),
+                *cipher,
+            );
+            assert_eq!(24, crypto.cipherparams.iv.len());
+            let decrypted = crypto.decrypt(TEST_PASSWORD).unwrap();
+            assert_eq!("TokenCoreX", String::from_utf8(decrypted).unwrap());
+            assert_eq!(
+                Error::PasswordIncorrect,
+                crypto
+                    .decrypt("WrongPassword")
+                    .err()
+                    .unwrap()
+                    .downcast::<crate::Error>()
+                    .unwrap()
+            );
+
+            let json = serde_json::to_string(&crypto).unwrap();
+            let decoded: Crypto<Pbkdf2Params> = serde_json::from_str(&json).unwrap();
+            assert_eq!(*cipher, decoded.cipher());
+
+            // the mac only covers the password, the tag has to catch a tampered iv
+            crypto.cipherparams.iv = "00".repeat(12);
+            let derived_key = crypto.generate_derived_key(TEST_PASSWORD).unwrap();
+            assert_eq!(
+                Error::CipherAuthFailed,
+                crypto
+                    .decrypt_by_derived_key(&derived_key)
+                    .err()
+                    .unwrap()
+                    .downcast::<crate::Error>()
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    pub fn upgrade_cipher_test() {
+        let mut crypto: Crypto<Pbkdf2Params> = Crypto::new_with_params(
+            TEST_PASSWORD,
+            "TokenCoreX".as_bytes(),
+            Pbkdf2Params::with_rounds(1024),
+        );
+        let kdfparams = crypto.kdfparams.clone();
+        let derived_key = crypto.generate_derived_key(TEST_PASSWORD).unwrap();
+        crypto
+            .upgrade_cipher(&derived_key, Cipher::Aes256Gcm)
+            .unwrap();
+        assert_eq!(Cipher::Aes256Gcm, crypto.cipher());
+        assert_eq!(kdfparams, crypto.kdfparams);
+        let decrypted = crypto.decrypt(TEST_PASSWORD).unwrap();
+        assert_eq!("TokenCoreX", String::from_utf8(decrypted).unwrap());
+
+        assert!(crypto
+            .upgrade_cipher(&[0u8; 64], Cipher::ChaCha20Poly1305)
+            .is_err());
+        assert_eq!(Cipher::Aes256Gcm, crypto.cipher());
+    }
+
+    #[test]
+    pub fn cached_derived_key_test() {
+        let mut crypto: Crypto<Pbkdf2Params> = Crypto::new_with_params(
+            TEST_PASSWORD,
+            "TokenCoreX".as_bytes(),
+            Pbkdf2Params::with_rounds(1024),
+        );
+        let derived_key = crypto.generate_derived_key(TEST_PASSWORD).unwrap();
+        crypto.cache_derived_key("TEMP_PASSWORD", &derived_key);
+
+        let decrypted = crypto.decrypt("TEMP_PASSWORD").unwrap();
+        assert_eq!("TokenCoreX", String::from_utf8(decrypted).unwrap());
+        let decrypted = crypto.decrypt(TEST_PASSWORD).unwrap();
+        assert_eq!("TokenCoreX", String::from_utf8(decrypted).unwrap());
+        assert!(crypto.decrypt("WrongPassword").is_err());
+
+        crypto.clear_cache_derived_key();
+        assert!(crypto.decrypt("TEMP_PASSWORD").is_err());
+    }
+
     #[test]
     fn test_cache_derived_key() {
         let cdk = CacheDerivedKey::new("12345678", &[1, 1, 1, 1]);
@@ -2,10 +2,16 @@ pub mod aes;
 pub mod crypto;
 pub mod hash;
 pub mod numberic_util;
+pub mod secret;
+pub mod slip39;
 
 use core::result;
-pub use crypto::{Crypto, EncPair, Pbkdf2Params};
+pub use crypto::{
+    default_kdf_rounds, Argon2Params, Cipher, Crypto, EncPair, Kdf, Pbkdf2Params, SCryptParams,
+};
 use parking_lot::RwLock;
+pub use secret::Secret;
+pub use zeroize::{Zeroize, Zeroizing};
 
 #[macro_use]
 extern crate failure;
@@ -24,6 +30,30 @@ pub enum Error {
     InvalidKeyIvLength,
     #[fail(display = "invalid_ciphertext")]
     InvalidCiphertext,
+    #[fail(display = "derived_key_incorrect")]
+    DerivedKeyIncorrect,
+    #[fail(display = "cipher_auth_failed")]
+    CipherAuthFailed,
+    #[fail(display = "unsupported_cipher")]
+    UnsupportedCipher,
+    #[fail(display = "slip39_word_invalid")]
+    Slip39WordInvalid,
+    #[fail(display = "slip39_mnemonic_invalid")]
+    Slip39MnemonicInvalid,
+    #[fail(display = "slip39_checksum_invalid")]
+    Slip39ChecksumInvalid,
+    #[fail(display = "slip39_threshold_invalid")]
+    Slip39ThresholdInvalid,
+    #[fail(display = "slip39_shares_mismatch")]
+    Slip39SharesMismatch,
+    #[fail(display = "slip39_insufficient_shares")]
+    Slip39InsufficientShares,
+    #[fail(display = "slip39_digest_invalid")]
+    Slip39DigestInvalid,
+    #[fail(display = "slip39_master_secret_invalid")]
+    Slip39MasterSecretInvalid,
+    #[fail(display = "slip39_passphrase_invalid")]
+    Slip39PassphraseInvalid,
 }
 
 lazy_static! {
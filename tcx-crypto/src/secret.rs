@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use std::ops::Deref;
+
+use parking_lot::Mutex;
+use zeroize::{Zeroize, Zeroizing};
+
+lazy_static! {
+    /// page address => the live secrets on the page, a page is unlocked with its last secret
+    static ref LOCKED_PAGES: Mutex<HashMap<usize, usize>> = Mutex::new(HashMap::new());
+}
+
+/// A secret kept in memory while a keystore is unlocked, it's wiped when dropped
+///
+/// The buffer is also locked in memory where the os allows it, so it's never written to the swap.
+/// There is no mutable access because a reallocated buffer would leave the old one behind. `T`
+/// owns a heap buffer (`Vec<u8>` or `String`), so moving a secret doesn't move the locked bytes.
+pub struct Secret<T: AsRef<[u8]> + Zeroize> {
+    value: T,
+    /// the pages locked by `new`, the buffer is empty after zeroizing so it can't tell them
+    locked: (usize, usize),
+}
+
+impl<T: AsRef<[u8]> + Zeroize> Secret<T> {
+    pub fn new(value: T) -> Self {
+        let locked = {
+            let bytes = value.as_ref();
+            lock_pages(bytes.as_ptr() as usize, bytes.len())
+        };
+        Secret { value, locked }
+    }
+}
+
+/// take over a zeroizing value without copying the buffer
+impl<T: AsRef<[u8]> + Zeroize + Default> From<Zeroizing<T>> for Secret<T> {
+    fn from(mut value: Zeroizing<T>) -> Self {
+        Secret::new(mem::replace(&mut *value, T::default()))
+    }
+}
+
+impl<T: AsRef<[u8]> + Zeroize> Deref for Secret<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: AsRef<[u8]> + Zeroize + Clone> Clone for Secret<T> {
+    fn clone(&self) -> Self {
+        Secret::new(self.value.clone())
+    }
+}
+
+impl<T: AsRef<[u8]> + Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+impl<T: AsRef<[u8]> + Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.value.zeroize();
+        unlock_pages(self.locked);
+    }
+}
+
+#[cfg(unix)]
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// the first page and the page count of a buffer
+fn page_range(addr: usize, len: usize) -> (usize, usize) {
+    if len == 0 {
+        return (0, 0);
+    }
+    let page_size = page_size();
+    let first = addr / page_size * page_size;
+    let last = (addr + len - 1) / page_size * page_size;
+    (first, (last - first) / page_size + 1)
+}
+
+/// lock the pages of a buffer and count the secrets on them, best effort, it fails when the
+/// limit of locked memory is reached and the secret is still wiped on drop
+fn lock_pages(addr: usize, len: usize) -> (usize, usize) {
+    let (first, count) = page_range(addr, len);
+    let page_size = page_size();
+    let mut pages = LOCKED_PAGES.lock();
+    for page in (0..count).map(|i| first + i * page_size) {
+        let secrets = pages.entry(page).or_insert(0);
+        if *secrets == 0 {
+            mlock(page, page_size);
+        }
+        *secrets += 1;
+    }
+    (first, count)
+}
+
+/// a page is unlocked only when no other live secret is on it
+fn unlock_pages((first, count): (usize, usize)) {
+    let page_size = page_size();
+    let mut pages = LOCKED_PAGES.lock();
+    for page in (0..count).map(|i| first + i * page_size) {
+        let last = match pages.get_mut(&page) {
+            Some(secrets) => {
+                *secrets -= 1;
+                *secrets == 0
+            }
+            None => false,
+        };
+        if last {
+            pages.remove(&page);
+            munlock(page, page_size);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn mlock(page: usize, len: usize) {
+    unsafe {
+        libc::mlock(page as *const libc::c_void, len);
+    }
+}
+
+#[cfg(unix)]
+fn munlock(page: usize, len: usize) {
+    unsafe {
+        libc::munlock(page as *const libc::c_void, len);
+    }
+}
+
+#[cfg(not(unix))]
+fn page_size() -> usize {
+    4096
+}
+
+#[cfg(not(unix))]
+fn mlock(_page: usize, _len: usize) {}
+
+#[cfg(not(unix))]
+fn munlock(_page: usize, _len: usize) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret() {
+        let secret = Secret::new("TokenCoreX".to_string());
+        assert_eq!(secret.as_str(), "TokenCoreX");
+        assert_eq!(format!("{:?}", secret), "Secret(***)");
+        assert_eq!(*secret.clone(), "TokenCoreX");
+
+        let secret = Secret::from(Zeroizing::new(vec![1u8, 2, 3]));
+        assert_eq!(secret.as_slice(), &[1u8, 2, 3]);
+    }
+
+    #[test]
+    fn test_shared_pages() {
+        let page_size = page_size();
+        assert_eq!(page_range(page_size - 1, 2), (0, 2));
+        assert_eq!(page_range(page_size, page_size), (page_size, 1));
+        assert_eq!(page_range(page_size, 0), (0, 0));
+
+        // the first pages are never mapped, the counting works even though mlock fails
+        let first = lock_pages(page_size + 8, 8);
+        let second = lock_pages(page_size + 16, page_size);
+        assert_eq!(LOCKED_PAGES.lock().get(&page_size), Some(&2));
+        unlock_pages(first);
+        assert_eq!(LOCKED_PAGES.lock().get(&page_size), Some(&1));
+        assert_eq!(LOCKED_PAGES.lock().get(&(page_size * 2)), Some(&1));
+        unlock_pages(second);
+        assert!(LOCKED_PAGES.lock().get(&page_size).is_none());
+        assert!(LOCKED_PAGES.lock().get(&(page_size * 2)).is_none());
+    }
+}
@@ -0,0 +1,688 @@
+//! SLIP-0039 Shamir's secret-sharing for mnemonic codes.
+//!
+//! The master secret is encrypted with the slip39 passphrase, split into group shares and every
+//! group share is split again into member shares. Each share is encoded as a word list with a
+//! RS1024 checksum, see https://github.com/satoshilabs/slips/blob/master/slip-0039.md
+use crate::numberic_util::random_iv;
+use crate::Error;
+use crate::Result;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+
+const RADIX_BITS: usize = 10;
+const RADIX: u32 = 1 << RADIX_BITS;
+const ID_LENGTH_BITS: u32 = 15;
+const ITERATION_EXP_LENGTH_BITS: u32 = 4;
+const CHECKSUM_LENGTH_WORDS: usize = 3;
+const MIN_STRENGTH_BYTES: usize = 16;
+const MIN_MNEMONIC_LENGTH_WORDS: usize = 20;
+const MAX_SHARE_COUNT: u8 = 16;
+const MAX_ITERATION_EXP: u8 = 15;
+const DIGEST_LENGTH_BYTES: usize = 4;
+const DIGEST_INDEX: u8 = 254;
+const SECRET_INDEX: u8 = 255;
+const BASE_ITERATION_COUNT: u32 = 10000;
+const ROUND_COUNT: u8 = 4;
+const CUSTOMIZATION_STRING: &[u8] = b"shamir";
+const CUSTOMIZATION_STRING_EXTENDABLE: &[u8] = b"shamir_extendable";
+const RS1024_GEN: [u32; 10] = [
+    0x00E0_E040,
+    0x01C1_C080,
+    0x0383_8100,
+    0x0707_0200,
+    0x0E0E_0009,
+    0x1C0C_2412,
+    0x3808_6C24,
+    0x3090_FC48,
+    0x21B1_F890,
+    0x03F3_F120,
+];
+
+lazy_static! {
+    static ref WORDLIST: Vec<&'static str> = include_str!("slip39_wordlist.txt")
+        .split_whitespace()
+        .collect();
+    static ref GF256_EXP_LOG: (Vec<u8>, Vec<u8>) = {
+        let mut exp = vec![0u8; 255];
+        let mut log = vec![0u8; 256];
+        let mut poly: u16 = 1;
+        for (i, e) in exp.iter_mut().enumerate() {
+            *e = poly as u8;
+            log[poly as usize] = i as u8;
+            // multiply by the generator 0x03 modulo the Rijndael polynomial
+            poly = (poly << 1) ^ poly;
+            if poly & 0x100 != 0 {
+                poly ^= 0x11B;
+            }
+        }
+        (exp, log)
+    };
+}
+
+/// The member threshold and member count of a share group
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GroupSpec {
+    pub member_threshold: u8,
+    pub member_count: u8,
+}
+
+/// A decoded slip39 share
+#[derive(Debug, Clone, PartialEq)]
+pub struct Share {
+    pub identifier: u16,
+    pub extendable: bool,
+    pub iteration_exponent: u8,
+    pub group_index: u8,
+    pub group_threshold: u8,
+    pub group_count: u8,
+    pub member_index: u8,
+    pub member_threshold: u8,
+    pub value: Vec<u8>,
+}
+
+impl Share {
+    pub fn from_mnemonic(mnemonic: &str) -> Result<Share> {
+        let words = mnemonic
+            .split_whitespace()
+            .map(|word| {
+                let word = word.to_lowercase();
+                WORDLIST
+                    .binary_search_by(|probe| (*probe).cmp(word.as_str()))
+                    .map(|index| index as u16)
+                    .map_err(|_| Error::Slip39WordInvalid.into())
+            })
+            .collect::<Result<Vec<u16>>>()?;
+        if words.len() < MIN_MNEMONIC_LENGTH_WORDS {
+            return Err(Error::Slip39MnemonicInvalid.into());
+        }
+
+        let id_exp = (u32::from(words[0]) << RADIX_BITS) | u32::from(words[1]);
+        let extendable = (id_exp >> ITERATION_EXP_LENGTH_BITS) & 1 == 1;
+        if rs1024_polymod(customization(extendable), &words) != 1 {
+            return Err(Error::Slip39ChecksumInvalid.into());
+        }
+
+        let params = (u32::from(words[2]) << RADIX_BITS) | u32::from(words[3]);
+        let param = |shift: u32| ((params >> shift) & 0xF) as u8;
+        let (group_threshold, group_count) = (param(12) + 1, param(8) + 1);
+        if group_threshold > group_count {
+            return Err(Error::Slip39MnemonicInvalid.into());
+        }
+
+        let value = words_to_bytes(&words[4..words.len() - CHECKSUM_LENGTH_WORDS])?;
+        Ok(Share {
+            identifier: (id_exp >> (ITERATION_EXP_LENGTH_BITS + 1)) as u16,
+            extendable,
+            iteration_exponent: (id_exp & 0xF) as u8,
+            group_index: param(16),
+            group_threshold,
+            group_count,
+            member_index: param(4),
+            member_threshold: param(0) + 1,
+            value,
+        })
+    }
+
+    pub fn to_mnemonic(&self) -> String {
+        let id_exp = (u32::from(self.identifier) << (ITERATION_EXP_LENGTH_BITS + 1))
+            | (u32::from(self.extendable) << ITERATION_EXP_LENGTH_BITS)
+            | u32::from(self.iteration_exponent);
+        let params = (u32::from(self.group_index) << 16)
+            | (u32::from(self.group_threshold - 1) << 12)
+            | (u32::from(self.group_count - 1) << 8)
+            | (u32::from(self.member_index) << 4)
+            | u32::from(self.member_threshold - 1);
+
+        let mut words = vec![
+            (id_exp / RADIX) as u16,
+            (id_exp % RADIX) as u16,
+            (params / RADIX) as u16,
+            (params % RADIX) as u16,
+        ];
+        words.extend(bytes_to_words(&self.value));
+        let checksum = rs1024_create_checksum(customization(self.extendable), &words);
+        words.extend(checksum.iter());
+
+        words
+            .iter()
+            .map(|index| WORDLIST[*index as usize])
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    fn is_same_set(&self, other: &Share) -> bool {
+        self.identifier == other.identifier
+            && self.extendable == other.extendable
+            && self.iteration_exponent == other.iteration_exponent
+            && self.group_threshold == other.group_threshold
+            && self.group_count == other.group_count
+            && self.value.len() == other.value.len()
+    }
+}
+
+/// Encrypts the master secret with the passphrase and splits it into `groups.len()` groups of
+/// which `group_threshold` groups are required to recover it. Returns the mnemonics of every
+/// group in order.
+pub fn split_master_secret(
+    master_secret: &[u8],
+    passphrase: &str,
+    group_threshold: u8,
+    groups: &[GroupSpec],
+    iteration_exponent: u8,
+) -> Result<Vec<Vec<String>>> {
+    if master_secret.len() < MIN_STRENGTH_BYTES || master_secret.len() % 2 != 0 {
+        return Err(Error::Slip39MasterSecretInvalid.into());
+    }
+    validate_passphrase(passphrase)?;
+    if iteration_exponent > MAX_ITERATION_EXP || groups.len() > MAX_SHARE_COUNT as usize {
+        return Err(Error::Slip39ThresholdInvalid.into());
+    }
+    if groups.iter().any(|group| {
+        // a 1-of-n group would just hand out n copies of the same share
+        group.member_threshold == 1 && group.member_count > 1
+    }) {
+        return Err(Error::Slip39ThresholdInvalid.into());
+    }
+
+    let identifier = {
+        let bytes = random_iv(2);
+        ((u16::from(bytes[0]) << 8) | u16::from(bytes[1])) & ((1 << ID_LENGTH_BITS) - 1)
+    };
+    let encrypted_secret = encrypt(
+        master_secret,
+        passphrase.as_bytes(),
+        iteration_exponent,
+        identifier,
+        false,
+    );
+    let group_shares = split_secret(group_threshold, groups.len() as u8, &encrypted_secret)?;
+
+    groups
+        .iter()
+        .zip(group_shares)
+        .map(|(group, (group_index, group_secret))| {
+            let member_shares =
+                split_secret(group.member_threshold, group.member_count, &group_secret)?;
+            Ok(member_shares
+                .into_iter()
+                .map(|(member_index, value)| {
+                    Share {
+                        identifier,
+                        extendable: false,
+                        iteration_exponent,
+                        group_index,
+                        group_threshold,
+                        group_count: groups.len() as u8,
+                        member_index,
+                        member_threshold: group.member_threshold,
+                        value,
+                    }
+                    .to_mnemonic()
+                })
+                .collect())
+        })
+        .collect()
+}
+
+/// Recovers the master secret from a set of mnemonics, the shares beyond the thresholds and
+/// the groups which have not reached their member threshold are ignored.
+pub fn combine_mnemonics<S: AsRef<str>>(mnemonics: &[S], passphrase: &str) -> Result<Vec<u8>> {
+    validate_passphrase(passphrase)?;
+    let shares = mnemonics
+        .iter()
+        .map(|mnemonic| Share::from_mnemonic(mnemonic.as_ref()))
+        .collect::<Result<Vec<Share>>>()?;
+    let first = match shares.first() {
+        Some(share) => share,
+        _ => return Err(Error::Slip39InsufficientShares.into()),
+    };
+    if shares.iter().any(|share| !first.is_same_set(share)) {
+        return Err(Error::Slip39SharesMismatch.into());
+    }
+
+    let mut groups: BTreeMap<u8, Vec<&Share>> = BTreeMap::new();
+    for share in &shares {
+        let members = groups.entry(share.group_index).or_insert_with(Vec::new);
+        if members
+            .iter()
+            .any(|member| member.member_threshold != share.member_threshold)
+        {
+            return Err(Error::Slip39SharesMismatch.into());
+        }
+        if let Some(member) = members
+            .iter()
+            .find(|member| member.member_index == share.member_index)
+        {
+            if member.value != share.value {
+                return Err(Error::Slip39SharesMismatch.into());
+            }
+            continue;
+        }
+        members.push(share);
+    }
+
+    let group_shares = groups
+        .iter()
+        .filter(|(_, members)| members.len() >= members[0].member_threshold as usize)
+        .take(first.group_threshold as usize)
+        .map(|(group_index, members)| {
+            let member_threshold = members[0].member_threshold;
+            let member_shares = members
+                .iter()
+                .take(member_threshold as usize)
+                .map(|member| (member.member_index, member.value.clone()))
+                .collect::<Vec<(u8, Vec<u8>)>>();
+            Ok((
+                *group_index,
+                recover_secret(member_threshold, &member_shares)?,
+            ))
+        })
+        .collect::<Result<Vec<(u8, Vec<u8>)>>>()?;
+    if group_shares.len() < first.group_threshold as usize {
+        return Err(Error::Slip39InsufficientShares.into());
+    }
+
+    let encrypted_secret = recover_secret(first.group_threshold, &group_shares)?;
+    Ok(decrypt(
+        &encrypted_secret,
+        passphrase.as_bytes(),
+        first.iteration_exponent,
+        first.identifier,
+        first.extendable,
+    ))
+}
+
+fn validate_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.bytes().all(|c| (32..=126).contains(&c)) {
+        Ok(())
+    } else {
+        Err(Error::Slip39PassphraseInvalid.into())
+    }
+}
+
+fn customization(extendable: bool) -> &'static [u8] {
+    if extendable {
+        CUSTOMIZATION_STRING_EXTENDABLE
+    } else {
+        CUSTOMIZATION_STRING
+    }
+}
+
+fn rs1024_polymod(customization: &[u8], words: &[u16]) -> u32 {
+    let values = customization
+        .iter()
+        .map(|c| u32::from(*c))
+        .chain(words.iter().map(|w| u32::from(*w)));
+    let mut chk: u32 = 1;
+    for value in values {
+        let b = chk >> 20;
+        chk = ((chk & 0xF_FFFF) << 10) ^ value;
+        for (i, gen) in RS1024_GEN.iter().enumerate() {
+            if (b >> i) & 1 == 1 {
+                chk ^= gen;
+            }
+        }
+    }
+    chk
+}
+
+fn rs1024_create_checksum(customization: &[u8], words: &[u16]) -> [u16; CHECKSUM_LENGTH_WORDS] {
+    let mut values = words.to_vec();
+    values.extend_from_slice(&[0; CHECKSUM_LENGTH_WORDS]);
+    let polymod = rs1024_polymod(customization, &values) ^ 1;
+    [
+        ((polymod >> 20) % RADIX) as u16,
+        ((polymod >> 10) % RADIX) as u16,
+        (polymod % RADIX) as u16,
+    ]
+}
+
+/// Packs the bytes into 10 bits words, the leading bits of the first word are zero padded
+fn bytes_to_words(bytes: &[u8]) -> Vec<u16> {
+    let word_count = (bytes.len() * 8 + RADIX_BITS - 1) / RADIX_BITS;
+    let mut words = Vec::with_capacity(word_count);
+    let mut acc: u32 = 0;
+    let mut bits = word_count * RADIX_BITS - bytes.len() * 8;
+    for byte in bytes {
+        acc = (acc << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= RADIX_BITS {
+            bits -= RADIX_BITS;
+            words.push((acc >> bits) as u16);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    words
+}
+
+fn words_to_bytes(words: &[u16]) -> Result<Vec<u8>> {
+    let padding = (words.len() * RADIX_BITS) % 16;
+    if padding > 8 {
+        return Err(Error::Slip39MnemonicInvalid.into());
+    }
+    let mut bytes = Vec::with_capacity((words.len() * RADIX_BITS - padding) / 8);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for (i, word) in words.iter().enumerate() {
+        acc = (acc << RADIX_BITS) | u32::from(*word);
+        bits += RADIX_BITS;
+        if i == 0 {
+            if acc >> (bits - padding) != 0 {
+                return Err(Error::Slip39MnemonicInvalid.into());
+            }
+            bits -= padding;
+            acc &= (1 << bits) - 1;
+        }
+        while bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Ok(bytes)
+}
+
+fn create_digest(random_data: &[u8], shared_secret: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_varkey(random_data).expect("hmac accepts any key length");
+    mac.input(shared_secret);
+    mac.result().code()[..DIGEST_LENGTH_BYTES].to_vec()
+}
+
+/// Lagrange interpolation of the shares at `x` over GF(256)
+fn interpolate(shares: &[(u8, Vec<u8>)], x: u8) -> Vec<u8> {
+    if let Some((_, value)) = shares.iter().find(|(index, _)| *index == x) {
+        return value.clone();
+    }
+
+    let (exp, log) = &*GF256_EXP_LOG;
+    let log_of = |a: u8, b: u8| i32::from(log[(a ^ b) as usize]);
+    let log_prod: i32 = shares.iter().map(|(index, _)| log_of(*index, x)).sum();
+
+    let mut result = vec![0u8; shares[0].1.len()];
+    for (index, value) in shares {
+        let log_others: i32 = shares.iter().map(|(other, _)| log_of(*index, *other)).sum();
+        let log_basis = ((log_prod - log_of(*index, x) - log_others) % 255 + 255) % 255;
+        for (r, v) in result.iter_mut().zip(value) {
+            if *v != 0 {
+                *r ^= exp[((i32::from(log[*v as usize]) + log_basis) % 255) as usize];
+            }
+        }
+    }
+    result
+}
+
+fn split_secret(threshold: u8, share_count: u8, secret: &[u8]) -> Result<Vec<(u8, Vec<u8>)>> {
+    if threshold < 1 || threshold > share_count || share_count > MAX_SHARE_COUNT {
+        return Err(Error::Slip39ThresholdInvalid.into());
+    }
+    if threshold == 1 {
+        return Ok((0..share_count).map(|i| (i, secret.to_vec())).collect());
+    }
+
+    let random_share_count = threshold - 2;
+    let mut shares: Vec<(u8, Vec<u8>)> = (0..random_share_count)
+        .map(|i| (i, random_iv(secret.len())))
+        .collect();
+    let random_part = random_iv(secret.len() - DIGEST_LENGTH_BYTES);
+    let mut digest = create_digest(&random_part, secret);
+    digest.extend_from_slice(&random_part);
+
+    let mut base_shares = shares.clone();
+    base_shares.push((DIGEST_INDEX, digest));
+    base_shares.push((SECRET_INDEX, secret.to_vec()));
+    for i in random_share_count..share_count {
+        shares.push((i, interpolate(&base_shares, i)));
+    }
+    Ok(shares)
+}
+
+fn recover_secret(threshold: u8, shares: &[(u8, Vec<u8>)]) -> Result<Vec<u8>> {
+    if threshold == 1 {
+        return Ok(shares[0].1.clone());
+    }
+
+    let secret = interpolate(shares, SECRET_INDEX);
+    let digest_share = interpolate(shares, DIGEST_INDEX);
+    let (digest, random_part) = digest_share.split_at(DIGEST_LENGTH_BYTES);
+    if digest != create_digest(random_part, &secret).as_slice() {
+        return Err(Error::Slip39DigestInvalid.into());
+    }
+    Ok(secret)
+}
+
+fn round_function(
+    i: u8,
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    salt: &[u8],
+    r: &[u8],
+) -> Vec<u8> {
+    let mut password = vec![i];
+    password.extend_from_slice(passphrase);
+    let mut salt = salt.to_vec();
+    salt.extend_from_slice(r);
+
+    let iterations = (BASE_ITERATION_COUNT << iteration_exponent) / u32::from(ROUND_COUNT);
+    let mut out = vec![0u8; r.len()];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(&password, &salt, iterations as usize, &mut out);
+    out
+}
+
+/// The 4 rounds Feistel network of the spec, decryption runs the rounds in reverse order
+fn feistel<I: Iterator<Item = u8>>(
+    input: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+    rounds: I,
+) -> Vec<u8> {
+    let salt = if extendable {
+        vec![]
+    } else {
+        let mut salt = CUSTOMIZATION_STRING.to_vec();
+        salt.extend_from_slice(&[(identifier >> 8) as u8, identifier as u8]);
+        salt
+    };
+
+    let (l, r) = input.split_at(input.len() / 2);
+    let (mut l, mut r) = (l.to_vec(), r.to_vec());
+    for i in rounds {
+        let f = round_function(i, passphrase, iteration_exponent, &salt, &r);
+        let next_r = l.iter().zip(f).map(|(a, b)| a ^ b).collect();
+        l = std::mem::replace(&mut r, next_r);
+    }
+    r.extend_from_slice(&l);
+    r
+}
+
+fn encrypt(
+    master_secret: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+) -> Vec<u8> {
+    feistel(
+        master_secret,
+        passphrase,
+        iteration_exponent,
+        identifier,
+        extendable,
+        0..ROUND_COUNT,
+    )
+}
+
+fn decrypt(
+    encrypted_secret: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+) -> Vec<u8> {
+    feistel(
+        encrypted_secret,
+        passphrase,
+        iteration_exponent,
+        identifier,
+        extendable,
+        (0..ROUND_COUNT).rev(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{combine_mnemonics, split_master_secret, GroupSpec, Share};
+    use crate::Error;
+
+    const SINGLE_SHARE: &str = "duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard";
+
+    #[test]
+    fn wordlist() {
+        assert_eq!(super::WORDLIST.len(), 1024);
+        assert_eq!(super::WORDLIST[0], "academic");
+        assert_eq!(super::WORDLIST[1023], "zero");
+    }
+
+    #[test]
+    fn combine_test_vectors() {
+        let secret = combine_mnemonics(&[SINGLE_SHARE], "TREZOR").unwrap();
+        assert_eq!(hex::encode(secret), "bb54aac4b89dc868ba37d9cc21b2cece");
+
+        let shares = [
+            "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+            "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+        ];
+        let secret = combine_mnemonics(&shares, "TREZOR").unwrap();
+        assert_eq!(hex::encode(secret), "b43ceb7e57a0ea8766221624d01b0864");
+
+        let err = combine_mnemonics(&shares[..1], "TREZOR").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Slip39InsufficientShares)
+        );
+    }
+
+    #[test]
+    fn share_encoding() {
+        let share = Share::from_mnemonic(SINGLE_SHARE).unwrap();
+        assert_eq!(share.identifier, 7945);
+        assert_eq!(share.group_threshold, 1);
+        assert_eq!(share.member_threshold, 1);
+        assert_eq!(share.value.len(), 16);
+        assert_eq!(share.to_mnemonic(), SINGLE_SHARE);
+
+        let invalid_checksum = SINGLE_SHARE.replace("keyboard", "kidney");
+        let err = Share::from_mnemonic(&invalid_checksum).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Slip39ChecksumInvalid)
+        );
+
+        let invalid_word = SINGLE_SHARE.replace("keyboard", "abandon");
+        let err = Share::from_mnemonic(&invalid_word).unwrap_err();
+        assert_eq!(err.downcast_ref::<Error>(), Some(&Error::Slip39WordInvalid));
+
+        let err = Share::from_mnemonic("duckling enlarge academic").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Slip39MnemonicInvalid)
+        );
+    }
+
+    #[test]
+    fn split_and_combine() {
+        let master_secret =
+            hex::decode("0c94ce4d1b2e9a2cb0d1d7f5e8a1ac4e57c7c4d3eb3e7b1b9a2b6c5b7d8e9f01")
+                .unwrap();
+        let groups = [
+            GroupSpec {
+                member_threshold: 1,
+                member_count: 1,
+            },
+            GroupSpec {
+                member_threshold: 2,
+                member_count: 3,
+            },
+            GroupSpec {
+                member_threshold: 3,
+                member_count: 5,
+            },
+        ];
+        let mnemonics = split_master_secret(&master_secret, "TREZOR", 2, &groups, 0).unwrap();
+        assert_eq!(mnemonics.len(), 3);
+        assert_eq!(mnemonics[2].len(), 5);
+        assert_eq!(mnemonics[0][0].split(' ').count(), 33);
+
+        let shares = vec![
+            mnemonics[0][0].clone(),
+            mnemonics[2][4].clone(),
+            mnemonics[2][0].clone(),
+            mnemonics[2][2].clone(),
+        ];
+        assert_eq!(combine_mnemonics(&shares, "TREZOR").unwrap(), master_secret);
+        assert_eq!(
+            combine_mnemonics(&shares, "").unwrap().len(),
+            master_secret.len()
+        );
+        assert_ne!(combine_mnemonics(&shares, "").unwrap(), master_secret);
+
+        let shares = vec![
+            mnemonics[1][1].clone(),
+            mnemonics[1][2].clone(),
+            mnemonics[2][1].clone(),
+            mnemonics[2][3].clone(),
+        ];
+        let err = combine_mnemonics(&shares, "TREZOR").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Slip39InsufficientShares)
+        );
+
+        let other = split_master_secret(&master_secret, "TREZOR", 1, &groups[..1], 0).unwrap();
+        let shares = vec![mnemonics[0][0].clone(), other[0][0].clone()];
+        let err = combine_mnemonics(&shares, "TREZOR").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Slip39SharesMismatch)
+        );
+    }
+
+    #[test]
+    fn split_invalid_params() {
+        let master_secret = [7u8; 16];
+        let group = GroupSpec {
+            member_threshold: 2,
+            member_count: 3,
+        };
+
+        let err = split_master_secret(&master_secret, "", 2, &[group], 0).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Slip39ThresholdInvalid)
+        );
+
+        let single = GroupSpec {
+            member_threshold: 1,
+            member_count: 2,
+        };
+        let err = split_master_secret(&master_secret, "", 1, &[single], 0).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Slip39ThresholdInvalid)
+        );
+
+        let err = split_master_secret(&master_secret[..15], "", 1, &[group], 0).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Slip39MasterSecretInvalid)
+        );
+
+        let err = split_master_secret(&master_secret, "密码", 1, &[group], 0).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Slip39PassphraseInvalid)
+        );
+    }
+}
@@ -1,7 +1,10 @@
 use super::Result;
 
 use crate::constant::SECP256K1_ENGINE;
-use crate::ecc::{DeterministicPrivateKey, DeterministicPublicKey, KeyError};
+use crate::ecc::{
+    zeroize_chain_code, zeroize_secret_key, DeterministicPrivateKey, DeterministicPublicKey,
+    KeyError,
+};
 
 use crate::{
     Derive, DeriveJunction, FromHex, Secp256k1PrivateKey, Secp256k1PublicKey, Ss58Codec, ToHex,
@@ -35,6 +38,15 @@ fn transform_bip32_error(err: Bip32Error) -> KeyError {
     }
 }
 
+/// the derived keys are cached while a hd keystore is unlocked, the chain code is wiped too
+/// because it derives the same children
+impl Drop for Bip32DeterministicPrivateKey {
+    fn drop(&mut self) {
+        zeroize_secret_key(&mut self.0.private_key.key);
+        zeroize_chain_code(&mut self.0.chain_code);
+    }
+}
+
 impl Bip32DeterministicPrivateKey {
     /// Construct a new master key from a seed value
     pub fn from_seed(seed: &[u8]) -> Result<Self> {
@@ -46,17 +58,21 @@ impl Bip32DeterministicPrivateKey {
 
 impl Derive for Bip32DeterministicPrivateKey {
     fn derive<T: Iterator<Item = DeriveJunction>>(&self, path: T) -> Result<Self> {
-        let mut extended_key = self.0.clone();
+        // every intermediate key is wrapped, so it's wiped when the next one replaces it
+        let mut extended_key = Bip32DeterministicPrivateKey(self.0.clone());
 
         for j in path {
             let child_number = j.try_into()?;
 
-            extended_key = extended_key
-                .ckd_priv(&SECP256K1_ENGINE, child_number)
-                .map_err(transform_bip32_error)?;
+            extended_key = Bip32DeterministicPrivateKey(
+                extended_key
+                    .0
+                    .ckd_priv(&SECP256K1_ENGINE, child_number)
+                    .map_err(transform_bip32_error)?,
+            );
         }
 
-        Ok(Bip32DeterministicPrivateKey(extended_key))
+        Ok(extended_key)
     }
 }
 
@@ -87,6 +103,7 @@ impl DeterministicPrivateKey for Bip32DeterministicPrivateKey {
     }
 
     fn private_key(&self) -> Self::PrivateKey {
+        // the copy goes straight into a key which wipes it on drop
         Secp256k1PrivateKey::from(self.0.private_key.clone())
     }
 
@@ -8,6 +8,18 @@ use std::io;
 use crate::ecc::TypedDeterministicPrivateKey::Bip32Sepc256k1;
 use serde::{Deserialize, Serialize};
 use tcx_constants::CurveType;
+use zeroize::Zeroize;
+
+/// wipe the secret key of the upstream crates, it has no api to clear it
+pub(crate) fn zeroize_secret_key(key: &mut secp256k1::SecretKey) {
+    // the slice borrows the whole array mutably, the pointer and the length are its own
+    unsafe { std::slice::from_raw_parts_mut(key.as_mut_ptr(), key.len()) }.zeroize();
+}
+
+/// wipe the chain code of a bip32 private key like `zeroize_secret_key`
+pub(crate) fn zeroize_chain_code(chain_code: &mut bitcoin::util::bip32::ChainCode) {
+    unsafe { std::slice::from_raw_parts_mut(chain_code.as_mut_ptr(), chain_code.len()) }.zeroize();
+}
 
 #[derive(Fail, Debug, PartialEq)]
 pub enum KeyError {
@@ -41,6 +53,12 @@ pub enum KeyError {
     NotEnoughMemory,
     #[fail(display = "invalid_curve_type")]
     InvalidCurveType,
+    #[fail(display = "invalid_word_count")]
+    InvalidWordCount,
+    #[fail(display = "invalid_entropy")]
+    InvalidEntropy,
+    #[fail(display = "insufficient_entropy")]
+    InsufficientEntropy,
 }
 
 /// An identifier for a type of cryptographic key.
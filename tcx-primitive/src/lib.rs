@@ -15,11 +15,14 @@ pub type Result<T> = result::Result<T, failure::Error>;
 pub use crate::bip32::{Bip32DeterministicPrivateKey, Bip32DeterministicPublicKey};
 pub use crate::derive::{get_account_path, Derive, DeriveJunction, DerivePath};
 pub use crate::ecc::{
-    DeterministicPrivateKey, DeterministicPublicKey, DeterministicType, PrivateKey, PublicKey,
-    TypedDeterministicPrivateKey, TypedDeterministicPublicKey, TypedPrivateKey,
+    DeterministicPrivateKey, DeterministicPublicKey, DeterministicType, KeyError, PrivateKey,
+    PublicKey, TypedDeterministicPrivateKey, TypedDeterministicPublicKey, TypedPrivateKey,
     TypedPrivateKeyDisplay, TypedPublicKey,
 };
-pub use crate::rand::generate_mnemonic;
+pub use crate::rand::{
+    generate_mnemonic, generate_mnemonic_with_entropy, generate_mnemonic_with_language,
+    EntropyFormat, UserEntropy,
+};
 pub use crate::secp256k1::{
     private_key_without_version, verify_private_key, Secp256k1PrivateKey, Secp256k1PublicKey,
 };
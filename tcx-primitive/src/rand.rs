@@ -1,5 +1,185 @@
 use bip39::{Language, Mnemonic, MnemonicType};
+use bitcoin_hashes::{sha256, Hash};
+use rand::RngCore;
+
+use crate::ecc::KeyError;
+use crate::Result;
+
+/// How the caller provided entropy is written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntropyFormat {
+    /// the raw entropy, e.g. from a reproducible test fixture
+    Hex,
+    /// dice rolls, digits 1 to 6
+    Dice,
+    /// coin flips, 0 or 1
+    Coins,
+}
+
+/// Entropy from the caller, e.g. rolled offline
+///
+/// It's mixed with the system rng by default, so a weak source can't make the mnemonic weaker.
+/// When it replaces the rng the mnemonic is reproducible: hex is used as is and must be exactly
+/// as long as the entropy of the mnemonic, dice rolls and coin flips are hashed by sha256 and must
+/// carry enough bits (about 2.58 bits per roll, 1 bit per flip).
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserEntropy {
+    pub format: EntropyFormat,
+    pub value: String,
+    pub replace_rng: bool,
+}
+
+impl UserEntropy {
+    fn entropy(&self, len: usize) -> Result<Vec<u8>> {
+        let value = self.value.trim();
+        let (bits, digest) = match self.format {
+            EntropyFormat::Hex => {
+                let bytes = hex::decode(value).map_err(|_| KeyError::InvalidEntropy)?;
+                if self.replace_rng {
+                    ensure_enough_entropy(bytes.len() == len)?;
+                    return Ok(bytes);
+                }
+                (bytes.len() * 8, sha256::Hash::hash(&bytes))
+            }
+            EntropyFormat::Dice => {
+                if value.is_empty() || !value.chars().all(|c| ('1'..='6').contains(&c)) {
+                    return Err(KeyError::InvalidEntropy.into());
+                }
+                (
+                    (value.len() as f64 * 6f64.log2()) as usize,
+                    sha256::Hash::hash(value.as_bytes()),
+                )
+            }
+            EntropyFormat::Coins => {
+                if value.is_empty() || !value.chars().all(|c| c == '0' || c == '1') {
+                    return Err(KeyError::InvalidEntropy.into());
+                }
+                (value.len(), sha256::Hash::hash(value.as_bytes()))
+            }
+        };
+        if self.replace_rng {
+            ensure_enough_entropy(bits >= len * 8)?;
+        }
+        Ok(digest.into_inner()[..len].to_vec())
+    }
+}
+
+fn ensure_enough_entropy(enough: bool) -> Result<()> {
+    if enough {
+        Ok(())
+    } else {
+        Err(KeyError::InsufficientEntropy.into())
+    }
+}
+
+/// the entropy length in bytes of a mnemonic of `word_count` words
+fn entropy_len(word_count: usize) -> Result<usize> {
+    match word_count {
+        12 | 15 | 18 | 21 | 24 => Ok(word_count * 4 / 3),
+        _ => Err(KeyError::InvalidWordCount.into()),
+    }
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
 
 pub fn generate_mnemonic() -> String {
-    Mnemonic::new(MnemonicType::Words12, Language::English).to_string()
+    generate_mnemonic_with_language(Language::English)
+}
+
+pub fn generate_mnemonic_with_language(language: Language) -> String {
+    Mnemonic::new(MnemonicType::Words12, language).to_string()
+}
+
+/// a mnemonic of `word_count` (12, 15, 18, 21 or 24) words from the system rng and/or the
+/// caller's entropy
+pub fn generate_mnemonic_with_entropy(
+    word_count: usize,
+    language: Language,
+    user_entropy: Option<&UserEntropy>,
+) -> Result<String> {
+    let len = entropy_len(word_count)?;
+    let entropy = match user_entropy {
+        Some(user_entropy) if user_entropy.replace_rng => user_entropy.entropy(len)?,
+        Some(user_entropy) => {
+            let mut entropy = random_bytes(len);
+            for (byte, user_byte) in entropy.iter_mut().zip(user_entropy.entropy(len)?) {
+                *byte ^= user_byte;
+            }
+            entropy
+        }
+        None => random_bytes(len),
+    };
+
+    let mnemonic = Mnemonic::from_entropy(&entropy, language)?;
+    Ok(mnemonic.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mnemonic_word_count() {
+        for word_count in &[12, 15, 18, 21, 24] {
+            let mnemonic =
+                generate_mnemonic_with_entropy(*word_count, Language::English, None).unwrap();
+            assert_eq!(mnemonic.split_whitespace().count(), *word_count);
+        }
+        let ret = generate_mnemonic_with_entropy(13, Language::English, None);
+        assert_eq!(format!("{}", ret.err().unwrap()), "invalid_word_count");
+    }
+
+    #[test]
+    fn mnemonic_from_user_entropy() {
+        let entropy = UserEntropy {
+            format: EntropyFormat::Hex,
+            value: "00000000000000000000000000000000".to_string(),
+            replace_rng: true,
+        };
+        let mnemonic =
+            generate_mnemonic_with_entropy(12, Language::English, Some(&entropy)).unwrap();
+        assert_eq!(
+            mnemonic,
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+        );
+        let ret = generate_mnemonic_with_entropy(24, Language::English, Some(&entropy));
+        assert_eq!(format!("{}", ret.err().unwrap()), "insufficient_entropy");
+
+        // mixed with the rng
+        let entropy = UserEntropy {
+            replace_rng: false,
+            ..entropy
+        };
+        let mnemonic =
+            generate_mnemonic_with_entropy(12, Language::English, Some(&entropy)).unwrap();
+        assert_ne!(
+            mnemonic,
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+        );
+
+        let dice = UserEntropy {
+            format: EntropyFormat::Dice,
+            value: "1".repeat(50),
+            replace_rng: true,
+        };
+        let mnemonic = generate_mnemonic_with_entropy(12, Language::English, Some(&dice)).unwrap();
+        assert_eq!(
+            mnemonic,
+            generate_mnemonic_with_entropy(12, Language::English, Some(&dice)).unwrap()
+        );
+        let ret = generate_mnemonic_with_entropy(24, Language::English, Some(&dice));
+        assert_eq!(format!("{}", ret.err().unwrap()), "insufficient_entropy");
+
+        let coins = UserEntropy {
+            format: EntropyFormat::Coins,
+            value: "0102".to_string(),
+            replace_rng: false,
+        };
+        let ret = generate_mnemonic_with_entropy(12, Language::English, Some(&coins));
+        assert_eq!(format!("{}", ret.err().unwrap()), "invalid_entropy");
+    }
 }
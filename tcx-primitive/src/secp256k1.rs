@@ -1,5 +1,7 @@
 use crate::constant::SECP256K1_ENGINE;
-use crate::ecc::{KeyError, PrivateKey as TraitPrivateKey, PublicKey as TraitPublicKey};
+use crate::ecc::{
+    zeroize_secret_key, KeyError, PrivateKey as TraitPrivateKey, PublicKey as TraitPublicKey,
+};
 
 use bitcoin::Network;
 
@@ -11,6 +13,7 @@ use bitcoin::util::base58;
 use bitcoin::secp256k1::Message;
 use std::io;
 use tcx_constants::{network_from_coin, CoinInfo};
+use zeroize::Zeroizing;
 
 #[cfg_attr(tarpaulin, skip)]
 fn transform_secp256k1_error(err: secp256k1::Error) -> KeyError {
@@ -32,6 +35,13 @@ pub struct Secp256k1PublicKey(pub PublicKey);
 #[derive(Clone)]
 pub struct Secp256k1PrivateKey(pub PrivateKey);
 
+/// the upstream key is `Copy`, every copy of it is kept in a `Secp256k1PrivateKey` so it's wiped
+impl Drop for Secp256k1PrivateKey {
+    fn drop(&mut self) {
+        zeroize_secret_key(&mut self.0.key);
+    }
+}
+
 impl From<PublicKey> for Secp256k1PublicKey {
     fn from(pk: PublicKey) -> Self {
         Secp256k1PublicKey(pk)
@@ -118,7 +128,7 @@ impl TraitPublicKey for Secp256k1PublicKey {
 
 impl Ss58Codec for Secp256k1PrivateKey {
     fn from_ss58check_with_version(wif: &str) -> Result<(Self, Vec<u8>)> {
-        let data = base58::from_check(wif)?;
+        let data = Zeroizing::new(base58::from_check(wif)?);
 
         let compressed = match data.len() {
             33 => false,
@@ -138,7 +148,7 @@ impl Ss58Codec for Secp256k1PrivateKey {
     }
 
     fn to_ss58check_with_version(&self, version: &[u8]) -> String {
-        let mut ret = [0; 34];
+        let mut ret = Zeroizing::new([0; 34]);
         ret[0..1].copy_from_slice(&version[0..]);
         ret[1..33].copy_from_slice(&self.0.key[..]);
         if self.0.compressed {
@@ -1,10 +1,49 @@
 use std::env;
 extern crate prost_build;
 
+// int64 fields are strings in the proto3 JSON mapping
+const INT64_FIELDS: &[&str] = &[
+    ".api.WalletResult.createdAt",
+    ".api.UnlockSessionResult.expiresAt",
+    ".api.UnlockWaitResult.remainingSeconds",
+    ".transaction.Utxo.amount",
+    ".transaction.Utxo.sequence",
+    ".transaction.BtcForkTxInput.amount",
+    ".transaction.BtcForkTxInput.fee",
+    ".transaction.CachedCell.capacity",
+];
+
+// the generated messages are serde compatible so `call_tcx_api_json` can use the proto3 JSON mapping
+fn config() -> prost_build::Config {
+    let mut config = prost_build::Config::new();
+    config.type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]");
+    config.type_attribute(".", "#[serde(rename_all = \"camelCase\")]");
+    config.field_attribute(".", "#[serde(default)]");
+    for field in INT64_FIELDS {
+        config.field_attribute(field, "#[serde(with = \"tcx_proto::json::int64\")]");
+    }
+    config.field_attribute(
+        ".api.Response.errorCategory",
+        "#[serde(with = \"crate::json_api::error_category\")]",
+    );
+    for field in &[
+        ".api.KeystoreCommonExportParam.type",
+        ".api.KeystoreCommonExportResult.type",
+        ".api.KeystoreCommonExistsParam.type",
+    ] {
+        config.field_attribute(field, "#[serde(with = \"crate::json_api::key_type\")]");
+    }
+    for field in &[".api.TcxAction.param", ".api.SignParam.input"] {
+        config.field_attribute(field, "#[serde(with = \"crate::json_api::any\")]");
+    }
+    config
+}
+
 fn main() {
     // tcx-api
     env::set_var("OUT_DIR", "../tcx/src");
-    prost_build::compile_protos(&["src/api.proto", "src/cache_derived_key.proto"], &["src/"])
+    config()
+        .compile_protos(&["src/api.proto", "src/cache_derived_key.proto"], &["src/"])
         .unwrap();
 
     //    // tcx-chain
@@ -13,14 +52,20 @@ fn main() {
 
     // tcx-tron
     env::set_var("OUT_DIR", "../tcx-tron/src");
-    prost_build::compile_protos(&["src/tron.proto"], &["src/"]).unwrap();
+    config()
+        .compile_protos(&["src/tron.proto"], &["src/"])
+        .unwrap();
 
     // tcx-btc-fork
     env::set_var("OUT_DIR", "../tcx-btc-fork/src");
-    prost_build::compile_protos(&["src/btc_fork.proto"], &["src/"]).unwrap();
+    config()
+        .compile_protos(&["src/btc_fork.proto"], &["src/"])
+        .unwrap();
 
     env::set_var("OUT_DIR", "../tcx-ckb/src");
-    prost_build::compile_protos(&["src/ckb.proto"], &["src/"]).unwrap();
+    config()
+        .compile_protos(&["src/ckb.proto"], &["src/"])
+        .unwrap();
     //    let targets = vec!["arm64-v8a", "armeabi-v7a", "x86", "x86_64"];
     //    for target in targets {
     //        println!("cargo:rustc-link-search=../../android/tokencore/build/intermediates/cmake/release/obj/{}/", target);
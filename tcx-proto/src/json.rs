@@ -0,0 +1,74 @@
+//! serde helpers for the proto3 JSON mapping of the generated messages
+
+/// int64 is encoded as a JSON string, both strings and numbers are accepted when decoding
+pub mod int64 {
+    use std::fmt;
+
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+
+    pub fn serialize<S>(value: &i64, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<i64, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(Int64Visitor)
+    }
+
+    struct Int64Visitor;
+
+    impl<'de> Visitor<'de> for Int64Visitor {
+        type Value = i64;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an int64 as a string or a number")
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<i64, E> {
+            Ok(value)
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<i64, E> {
+            if value > i64::max_value() as u64 {
+                return Err(E::custom("int64_overflow"));
+            }
+            Ok(value as i64)
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<i64, E> {
+            value.parse().map_err(E::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Amount {
+        #[serde(with = "super::int64")]
+        value: i64,
+    }
+
+    #[test]
+    fn int64_json() {
+        let amount = Amount {
+            value: -9007199254740993,
+        };
+        let json = serde_json::to_string(&amount).unwrap();
+        assert_eq!(json, r#"{"value":"-9007199254740993"}"#);
+        assert_eq!(serde_json::from_str::<Amount>(&json).unwrap(), amount);
+        assert_eq!(
+            serde_json::from_str::<Amount>(r#"{"value":100000}"#).unwrap(),
+            Amount { value: 100000 }
+        );
+        assert!(serde_json::from_str::<Amount>(r#"{"value":"1.5"}"#).is_err());
+    }
+}
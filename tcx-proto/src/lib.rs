@@ -1,3 +1,5 @@
+pub mod json;
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -102,7 +102,7 @@ mod tests {
 
     use bitcoin::util::misc::hex_bytes;
 
-    use tcx_chain::{HdKeystore, Keystore, KeystoreGuard, Metadata};
+    use tcx_chain::{HdKeystore, Keystore, KeystoreGuard, Metadata, DEFAULT_MAX_UNLOCK_FAILURES};
     use tcx_constants::{CoinInfo, TEST_PASSWORD};
     use tcx_constants::{CurveType, TEST_MNEMONIC};
     use tcx_primitive::{PrivateKey, Secp256k1PrivateKey};
@@ -152,7 +152,12 @@ mod tests {
             network: "".to_string(),
             seg_wit: "".to_string(),
         };
-        let mut guard = KeystoreGuard::unlock_by_password(&mut keystore, TEST_PASSWORD).unwrap();
+        let mut guard = KeystoreGuard::unlock_by_password(
+            &mut keystore,
+            TEST_PASSWORD,
+            DEFAULT_MAX_UNLOCK_FAILURES,
+        )
+        .unwrap();
 
         let ks = guard.keystore_mut();
 
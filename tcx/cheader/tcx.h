@@ -0,0 +1,66 @@
+#ifndef TCX_H
+#define TCX_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * `call_tcx_api_bytes` returned the reply of the method
+ */
+#define TCX_OK 0
+
+/**
+ * `call_tcx_api_bytes` returned the error `Response` of the method
+ */
+#define TCX_ERROR 1
+
+/**
+ * `call_tcx_api_bytes` was called with a null pointer, nothing is dispatched or returned
+ */
+#define TCX_NULL_POINTER -1
+
+void free_const_string(const char *s);
+
+/**
+ * free a buffer returned by `call_tcx_api_bytes`
+ */
+void free_tcx_buffer(uint8_t *ptr, uintptr_t len);
+
+/**
+ * dispatch protobuf rpc call
+ *
+ * The hex encoded `TcxAction` is decoded and dispatched the same way as `call_tcx_api_bytes`,
+ * the returned string must be released by `free_const_string`.
+ */
+const char *call_tcx_api(const char *hex_str);
+
+/**
+ * dispatch protobuf rpc call without the hex round trip
+ *
+ * `ptr`/`len` hold the encoded `TcxAction`, `ptr` may only be null when `len` is 0. On return
+ * `out_ptr`/`out_len` hold the encoded reply with `TCX_OK`, or the encoded error `Response` with
+ * `TCX_ERROR`. The buffer is owned by the caller and must be released by `free_tcx_buffer`.
+ */
+int32_t call_tcx_api_bytes(const uint8_t *ptr,
+                           uintptr_t len,
+                           uint8_t **out_ptr,
+                           uintptr_t *out_len);
+
+/**
+ * dispatch json rpc call
+ *
+ * Accepts `{"method": ..., "param": {...}}` where param follows the proto3 JSON mapping of the
+ * param message, returns `{"result": {...}}` or `{"error": {...}}` holding the error `Response`.
+ * The returned string must be released by `free_const_string`.
+ */
+const char *call_tcx_api_json(const char *json_str);
+
+void init_token_core_x(const char *json_str);
+
+void clear_err(void);
+
+const char *get_last_err_message(void);
+
+#endif /* TCX_H */
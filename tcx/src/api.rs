@@ -3,203 +3,612 @@
 /// the real action and param field is the real param of that method.
 /// When an error occurred, the `call_tcx_api` will return a `Response` which isSuccess field be false and error field is the reason
 /// which cause the error.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct TcxAction {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub method: std::string::String,
     #[prost(message, optional, tag = "2")]
+    #[serde(default)]
+    #[serde(with = "crate::json_api::any")]
     pub param: ::std::option::Option<::prost_types::Any>,
 }
 /// A common response when error occurred.
+///
+/// errorCode is stable and derived from the error type:
+/// 1xxx tcx-crypto, 2xxx tcx-chain, 3xxx tcx-primitive, 4xxx tcx-btc-fork, 5xxx tcx-ckb, 6xxx tcx api.
+/// 0 means the error is not typed yet, only the error message is available.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Response {
     #[prost(bool, tag = "1")]
+    #[serde(default)]
     pub is_success: bool,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub error: std::string::String,
+    #[prost(int32, tag = "3")]
+    #[serde(default)]
+    pub error_code: i32,
+    #[prost(enumeration = "ErrorCategory", tag = "4")]
+    #[serde(default)]
+    #[serde(with = "crate::json_api::error_category")]
+    pub error_category: i32,
+    /// the causes of the error, from outermost to innermost
+    #[prost(string, repeated, tag = "5")]
+    #[serde(default)]
+    pub context: ::std::vec::Vec<std::string::String>,
+    /// where the error was created, only captured when RUST_BACKTRACE is enabled
+    #[prost(string, tag = "6")]
+    #[serde(default)]
+    pub backtrace: std::string::String,
 }
 /// Initialization
 
 /// FUNCTION: init_token_core_x(InitTokenCoreXParam)
 ///
 /// initialize tcx by passing keystore folder and xpub encryption params
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct InitTokenCoreXParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub file_dir: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub xpub_common_key: std::string::String,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub xpub_common_iv: std::string::String,
+    /// cipher of new keystores: aes-128-ctr (default), aes-256-gcm or chacha20-poly1305
+    #[prost(string, tag = "4")]
+    #[serde(default)]
+    pub cipher: std::string::String,
+    /// re-encrypt existing keystores by the cipher on the next successful unlock
+    #[prost(bool, tag = "5")]
+    #[serde(default)]
+    pub upgrade_cipher: bool,
+    /// where the keystores are kept: directory (default), memory or sqlite
+    #[prost(string, tag = "6")]
+    #[serde(default)]
+    pub storage: std::string::String,
+    /// failed unlocks by password locking a keystore out for a day, 0 keeps the current limit (10 by default)
+    #[prost(uint32, tag = "7")]
+    #[serde(default)]
+    pub max_unlock_failures: u32,
+}
+/// FUNCTION: scan_keystores(): ScanKeystoresResult
+///
+/// reload all keystores from the folder and report every keystore file. The corrupted files and
+/// the files with a duplicate id are moved to the quarantine subfolder as `{file}.{millis}`, a
+/// corrupted file is restored from its backup, a copy of the last save, when the backup is readable
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ScanKeystoresResult {
+    #[prost(message, repeated, tag = "1")]
+    #[serde(default)]
+    pub keystores: ::std::vec::Vec<ScannedKeystore>,
+}
+/// status is one of LOADED, UNSUPPORTED_VERSION, CORRUPTED and DUPLICATE_ID, the error is only
+/// set for the corrupted files, including the ones restored from the backup
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ScannedKeystore {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub file: std::string::String,
+    #[prost(string, tag = "2")]
+    #[serde(default)]
+    pub id: std::string::String,
+    #[prost(string, tag = "3")]
+    #[serde(default)]
+    pub status: std::string::String,
+    #[prost(bool, tag = "4")]
+    #[serde(default)]
+    pub restored_from_backup: bool,
+    #[prost(string, tag = "5")]
+    #[serde(default)]
+    pub error: std::string::String,
 }
 /// Hd Store
 
 /// FUNCTION: hd_store_create(HdStoreCreateParam): WalletResult
 ///
 /// create a new hd keystore
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct HdStoreCreateParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub password: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub password_hint: std::string::String,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub name: std::string::String,
+    #[prost(message, optional, tag = "4")]
+    #[serde(default)]
+    pub kdf: ::std::option::Option<KdfParam>,
+    /// the optional bip39 passphrase, stored encrypted by the password
+    #[prost(string, tag = "5")]
+    #[serde(default)]
+    pub passphrase: std::string::String,
+    /// the wordlist of the new mnemonic: ENGLISH (default), CHINESE_SIMPLIFIED,
+    /// CHINESE_TRADITIONAL, FRENCH, ITALIAN, JAPANESE, KOREAN or SPANISH
+    #[prost(string, tag = "6")]
+    #[serde(default)]
+    pub language: std::string::String,
+    /// 12 (default), 15, 18, 21 or 24
+    #[prost(uint32, tag = "7")]
+    #[serde(default)]
+    pub word_count: u32,
+    #[prost(message, optional, tag = "8")]
+    #[serde(default)]
+    pub entropy: ::std::option::Option<EntropyParam>,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct WalletResult {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub name: std::string::String,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub source: std::string::String,
     #[prost(message, repeated, tag = "4")]
+    #[serde(default)]
     pub accounts: ::std::vec::Vec<AccountResponse>,
     #[prost(int64, tag = "5")]
+    #[serde(default)]
+    #[serde(with = "tcx_proto::json::int64")]
     pub created_at: i64,
 }
 /// FUNCTION: hd_store_import(HdStoreImportParam): WalletResult
 ///
 /// create a new hd keystore by mnemonic
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct HdStoreImportParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub mnemonic: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub password: std::string::String,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub source: std::string::String,
     #[prost(string, tag = "4")]
+    #[serde(default)]
     pub name: std::string::String,
     #[prost(string, tag = "5")]
+    #[serde(default)]
     pub password_hint: std::string::String,
     #[prost(bool, tag = "6")]
+    #[serde(default)]
     pub overwrite: bool,
+    #[prost(message, optional, tag = "7")]
+    #[serde(default)]
+    pub kdf: ::std::option::Option<KdfParam>,
+    /// the optional bip39 passphrase, stored encrypted by the password
+    #[prost(string, tag = "8")]
+    #[serde(default)]
+    pub passphrase: std::string::String,
+    /// the wordlist of the mnemonic, it's detected when empty
+    #[prost(string, tag = "9")]
+    #[serde(default)]
+    pub language: std::string::String,
+}
+/// the kdf of a new keystore, pbkdf2 with the rounds of the config is used when it's absent.
+/// kdf is "pbkdf2", "scrypt" or "argon2id", zero params mean the defaults: pbkdf2 rounds `c`,
+/// scrypt cost `n` (a power of 2), block size `r` and parallelism `p`,
+/// argon2id `memory` in KiB, `time` and `parallelism`
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KdfParam {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub kdf: std::string::String,
+    #[prost(uint32, tag = "2")]
+    #[serde(default)]
+    pub c: u32,
+    #[prost(uint32, tag = "3")]
+    #[serde(default)]
+    pub n: u32,
+    #[prost(uint32, tag = "4")]
+    #[serde(default)]
+    pub r: u32,
+    #[prost(uint32, tag = "5")]
+    #[serde(default)]
+    pub p: u32,
+    #[prost(uint32, tag = "6")]
+    #[serde(default)]
+    pub memory: u32,
+    #[prost(uint32, tag = "7")]
+    #[serde(default)]
+    pub time: u32,
+    #[prost(uint32, tag = "8")]
+    #[serde(default)]
+    pub parallelism: u32,
+}
+/// entropy from the caller for a new mnemonic, it's mixed with the system rng unless `replaceRng`.
+/// format is "HEX" (default), "DICE" (digits 1 to 6) or "COINS" (0 or 1). When it replaces the
+/// rng, hex must be exactly as long as the entropy of the mnemonic, dice rolls and coin flips are
+/// hashed by sha256 and must carry enough bits (about 2.58 bits per roll, 1 bit per flip)
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EntropyParam {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub format: std::string::String,
+    #[prost(string, tag = "2")]
+    #[serde(default)]
+    pub value: std::string::String,
+    #[prost(bool, tag = "3")]
+    #[serde(default)]
+    pub replace_rng: bool,
 }
 /// FUNCTION: hd_store_derive(HdStoreDeriveParam): AccountsResponse
 ///
 /// derive new accounts from a hd keystore
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct KeystoreCommonDeriveParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub password: std::string::String,
     #[prost(message, repeated, tag = "3")]
+    #[serde(default)]
     pub derivations: ::std::vec::Vec<keystore_common_derive_param::Derivation>,
+    /// use the token returned by unlock_session instead of the password
+    #[prost(string, tag = "4")]
+    #[serde(default)]
+    pub session_token: std::string::String,
 }
 pub mod keystore_common_derive_param {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct Derivation {
         #[prost(string, tag = "1")]
+        #[serde(default)]
         pub chain_type: std::string::String,
         #[prost(string, tag = "2")]
+        #[serde(default)]
         pub path: std::string::String,
         #[prost(string, tag = "3")]
+        #[serde(default)]
         pub network: std::string::String,
         #[prost(string, tag = "4")]
+        #[serde(default)]
         pub seg_wit: std::string::String,
         #[prost(string, tag = "5")]
+        #[serde(default)]
         pub chain_id: std::string::String,
     }
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct AccountResponse {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub chain_type: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub address: std::string::String,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub path: std::string::String,
     #[prost(string, tag = "4")]
+    #[serde(default)]
     pub extended_xpub_key: std::string::String,
+    #[prost(string, tag = "5")]
+    #[serde(default)]
+    pub label: std::string::String,
+    #[prost(string, repeated, tag = "6")]
+    #[serde(default)]
+    pub tags: ::std::vec::Vec<std::string::String>,
+    #[prost(bool, tag = "7")]
+    #[serde(default)]
+    pub hidden: bool,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct AccountsResponse {
     #[prost(message, repeated, tag = "1")]
+    #[serde(default)]
     pub accounts: ::std::vec::Vec<AccountResponse>,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct KeystoreCommonExportParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub password: std::string::String,
     #[prost(enumeration = "KeyType", tag = "3")]
+    #[serde(default)]
+    #[serde(with = "crate::json_api::key_type")]
     pub r#type: i32,
     #[prost(string, tag = "4")]
+    #[serde(default)]
     pub value: std::string::String,
 }
 /// FUNCTION: hd_store_export(KeystoreCommonExportResult): KeystoreCommonExistsResult
 ///
 /// export the mnemonic from a hd keystore
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct KeystoreCommonExportResult {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
     #[prost(enumeration = "KeyType", tag = "2")]
+    #[serde(default)]
+    #[serde(with = "crate::json_api::key_type")]
     pub r#type: i32,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub value: std::string::String,
+    /// the bip39 passphrase of a hd keystore, empty if there is none
+    #[prost(string, tag = "4")]
+    #[serde(default)]
+    pub passphrase: std::string::String,
+}
+/// FUNCTION: hd_store_export_slip39(Slip39ExportParam): Slip39ExportResult
+///
+/// split the mnemonic entropy of a hd keystore into slip39 shares, `groupThreshold` of the groups
+/// are required to recover it. The bip39 passphrase is not part of the shares, so a keystore with a
+/// passphrase can't be exported.
+///
+/// The shares are not interoperable with other slip39 wallets, they use the master secret as the
+/// bip32 seed while it's the bip39 entropy here. Recover them by hd_store_import_slip39 only.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Slip39ExportParam {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub id: std::string::String,
+    #[prost(string, tag = "2")]
+    #[serde(default)]
+    pub password: std::string::String,
+    #[prost(uint32, tag = "3")]
+    #[serde(default)]
+    pub group_threshold: u32,
+    #[prost(message, repeated, tag = "4")]
+    #[serde(default)]
+    pub groups: ::std::vec::Vec<Slip39GroupParam>,
+    /// the slip39 passphrase which encrypts the shares, printable ascii only
+    #[prost(string, tag = "5")]
+    #[serde(default)]
+    pub passphrase: std::string::String,
+    /// the passphrase encryption runs 10000 * 2^iterationExponent pbkdf2 iterations, max 15
+    #[prost(uint32, tag = "6")]
+    #[serde(default)]
+    pub iteration_exponent: u32,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Slip39GroupParam {
+    #[prost(uint32, tag = "1")]
+    #[serde(default)]
+    pub member_threshold: u32,
+    #[prost(uint32, tag = "2")]
+    #[serde(default)]
+    pub member_count: u32,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Slip39ExportResult {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub id: std::string::String,
+    #[prost(message, repeated, tag = "2")]
+    #[serde(default)]
+    pub groups: ::std::vec::Vec<Slip39Group>,
+    /// the wordlist of the mnemonic, it's required to import the shares
+    #[prost(string, tag = "3")]
+    #[serde(default)]
+    pub language: std::string::String,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Slip39Group {
+    #[prost(string, repeated, tag = "1")]
+    #[serde(default)]
+    pub shares: ::std::vec::Vec<std::string::String>,
+}
+/// FUNCTION: hd_store_import_slip39(Slip39ImportParam): WalletResult
+///
+/// create a new hd keystore by slip39 shares, enough shares of enough groups are required
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Slip39ImportParam {
+    #[prost(string, repeated, tag = "1")]
+    #[serde(default)]
+    pub shares: ::std::vec::Vec<std::string::String>,
+    /// the slip39 passphrase the shares were exported with
+    #[prost(string, tag = "2")]
+    #[serde(default)]
+    pub passphrase: std::string::String,
+    #[prost(string, tag = "3")]
+    #[serde(default)]
+    pub password: std::string::String,
+    #[prost(string, tag = "4")]
+    #[serde(default)]
+    pub name: std::string::String,
+    #[prost(string, tag = "5")]
+    #[serde(default)]
+    pub password_hint: std::string::String,
+    #[prost(bool, tag = "6")]
+    #[serde(default)]
+    pub overwrite: bool,
+    #[prost(message, optional, tag = "7")]
+    #[serde(default)]
+    pub kdf: ::std::option::Option<KdfParam>,
+    /// the wordlist of the recovered mnemonic, the `language` of the Slip39ExportResult
+    #[prost(string, tag = "8")]
+    #[serde(default)]
+    pub language: std::string::String,
 }
 /// Private Key Store
 
 /// FUNCTION: private_key_store_import(PrivateKeyStoreImportParam): WalletResult
 ///
 /// create a new private key keystore by a private key
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct PrivateKeyStoreImportParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub private_key: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub password: std::string::String,
     #[prost(bool, tag = "3")]
+    #[serde(default)]
     pub overwrite: bool,
+    #[prost(message, optional, tag = "4")]
+    #[serde(default)]
+    pub kdf: ::std::option::Option<KdfParam>,
 }
 /// Deprecated use ExportPrivateKeyParam instead
 /// FUNCTION: private_key_store_export(PrivateKeyStoreExportParam): KeystoreCommonExportResult
 ///
 /// export the private key from a private key keystore
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct PrivateKeyStoreExportParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub password: std::string::String,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub chain_type: std::string::String,
     #[prost(string, tag = "4")]
+    #[serde(default)]
     pub network: std::string::String,
 }
 /// FUNCTION: export_private_key(ExportPrivateKeyParam): KeystoreCommonExportResult
 ///
 /// export the private key from a private key keystore or a hd keystore
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ExportPrivateKeyParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub password: std::string::String,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub chain_type: std::string::String,
     #[prost(string, tag = "4")]
+    #[serde(default)]
     pub network: std::string::String,
     #[prost(string, tag = "5")]
+    #[serde(default)]
     pub main_address: std::string::String,
     #[prost(string, tag = "6")]
+    #[serde(default)]
     pub path: std::string::String,
 }
+/// Watch Only Store
+
+/// FUNCTION: watch_only_store_import(WatchOnlyStoreImportParam): WalletResult
+///
+/// create a keystore without any secret, it watches one account by an extended public key of
+/// any SLIP-132 version, a public key or a TRON/NERVOS address. The network and segWit of an
+/// extended public key come from its version, path is the account path of it
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WatchOnlyStoreImportParam {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub source: std::string::String,
+    #[prost(string, tag = "2")]
+    #[serde(default)]
+    pub value: std::string::String,
+    #[prost(string, tag = "3")]
+    #[serde(default)]
+    pub chain_type: std::string::String,
+    #[prost(string, tag = "4")]
+    #[serde(default)]
+    pub network: std::string::String,
+    #[prost(string, tag = "5")]
+    #[serde(default)]
+    pub seg_wit: std::string::String,
+    #[prost(string, tag = "6")]
+    #[serde(default)]
+    pub path: std::string::String,
+    #[prost(string, tag = "7")]
+    #[serde(default)]
+    pub password: std::string::String,
+    #[prost(string, tag = "8")]
+    #[serde(default)]
+    pub name: std::string::String,
+    #[prost(string, tag = "9")]
+    #[serde(default)]
+    pub password_hint: std::string::String,
+    #[prost(bool, tag = "10")]
+    #[serde(default)]
+    pub overwrite: bool,
+    #[prost(message, optional, tag = "11")]
+    #[serde(default)]
+    pub kdf: ::std::option::Option<KdfParam>,
+}
 /// Keystore Common
 
 /// FUNCTION: keystore_common_verify(WalletKeyParam) -> Response
 ///
 /// verify the password of the keystore
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct WalletKeyParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub password: std::string::String,
 }
 // FUNCTION: keystore_common_delete(WalletKeyParam) -> Response
@@ -209,27 +618,164 @@ pub struct WalletKeyParam {
 /// FUNCTION: keystore_common_exists(KeystoreCommonExistsParam): KeystoreCommonExistsResult
 ///
 /// Check is there a keystore was generate by the special privateKey or mnemonic
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct KeystoreCommonExistsParam {
     #[prost(enumeration = "KeyType", tag = "1")]
+    #[serde(default)]
+    #[serde(with = "crate::json_api::key_type")]
     pub r#type: i32,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub value: std::string::String,
+    /// the bip39 passphrase when the value is a mnemonic
+    #[prost(string, tag = "3")]
+    #[serde(default)]
+    pub passphrase: std::string::String,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct KeystoreCommonExistsResult {
     #[prost(bool, tag = "1")]
+    #[serde(default)]
     pub is_exists: bool,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub id: std::string::String,
 }
 /// FUNCTION: keystore_common_accounts(KeystoreCommonAccountsParam): AccountsResponse
 ///
-/// List all accounts from the keystore
+/// List all accounts from the keystore, the hidden ones are listed only when includeHidden is set
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct KeystoreCommonAccountsParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub id: std::string::String,
+    #[prost(bool, tag = "2")]
+    #[serde(default)]
+    pub include_hidden: bool,
+}
+/// FUNCTION: keystore_common_change_password(KeystoreCommonChangePasswordParam): Response
+///
+/// re-encrypt the keystore by the new password, the id, the accounts, the metadata and the kdf
+/// with its cost are kept, only the salt is fresh
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KeystoreCommonChangePasswordParam {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub id: std::string::String,
+    #[prost(string, tag = "2")]
+    #[serde(default)]
+    pub old_password: std::string::String,
+    #[prost(string, tag = "3")]
+    #[serde(default)]
+    pub new_password: std::string::String,
+    #[prost(string, tag = "4")]
+    #[serde(default)]
+    pub password_hint: std::string::String,
+}
+/// FUNCTION: keystore_common_unlock_wait(KeystoreCommonUnlockWaitParam): UnlockWaitResult
+///
+/// the wait before the next unlock by password, every failed unlock after the third doubles the
+/// wait up to an hour, and the keystore is locked out for a day after maxUnlockFailures of them
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KeystoreCommonUnlockWaitParam {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub id: std::string::String,
+}
+/// remainingSeconds is 0 when the password can be tried now
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UnlockWaitResult {
+    #[prost(int64, tag = "1")]
+    #[serde(default)]
+    #[serde(with = "tcx_proto::json::int64")]
+    pub remaining_seconds: i64,
+    #[prost(uint32, tag = "2")]
+    #[serde(default)]
+    pub failed_unlocks: u32,
+    #[prost(bool, tag = "3")]
+    #[serde(default)]
+    pub locked_out: bool,
+}
+/// FUNCTION: keystore_common_remove_account(KeystoreCommonAccountParam): Response
+/// FUNCTION: keystore_common_hide_account(KeystoreCommonAccountParam): Response
+/// FUNCTION: keystore_common_unhide_account(KeystoreCommonAccountParam): Response
+///
+/// remove a mistaken derivation or hide an account from keystore_common_accounts, the account is
+/// found by the chain type, the address and the derivation path. No password is needed because
+/// the accounts can be derived again
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KeystoreCommonAccountParam {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
+    #[prost(string, tag = "2")]
+    #[serde(default)]
+    pub chain_type: std::string::String,
+    #[prost(string, tag = "3")]
+    #[serde(default)]
+    pub address: std::string::String,
+    #[prost(string, tag = "4")]
+    #[serde(default)]
+    pub path: std::string::String,
+}
+/// FUNCTION: keystore_update_meta(KeystoreUpdateMetaParam): Response
+///
+/// rename the wallet or change the password hint, the metadata isn't secret so no password is
+/// needed. An empty name keeps the current one, the hint is replaced only when updatePasswordHint
+/// is set because an empty hint is valid
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KeystoreUpdateMetaParam {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub id: std::string::String,
+    #[prost(string, tag = "2")]
+    #[serde(default)]
+    pub name: std::string::String,
+    #[prost(string, tag = "3")]
+    #[serde(default)]
+    pub password_hint: std::string::String,
+    #[prost(bool, tag = "4")]
+    #[serde(default)]
+    pub update_password_hint: bool,
+}
+/// FUNCTION: account_update_label(AccountUpdateLabelParam): Response
+///
+/// replace the label and the tags of an account, no password is needed
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AccountUpdateLabelParam {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub id: std::string::String,
+    #[prost(string, tag = "2")]
+    #[serde(default)]
+    pub chain_type: std::string::String,
+    #[prost(string, tag = "3")]
+    #[serde(default)]
+    pub address: std::string::String,
+    #[prost(string, tag = "4")]
+    #[serde(default)]
+    pub label: std::string::String,
+    #[prost(string, repeated, tag = "5")]
+    #[serde(default)]
+    pub tags: ::std::vec::Vec<std::string::String>,
 }
 /// Sign Transaction
 
@@ -237,109 +783,262 @@ pub struct KeystoreCommonAccountsParam {
 ///
 /// Sign transaction. This api is used for sign any chain_type, you should build the right TxInput instance and
 /// put it in the `input` field
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SignParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub password: std::string::String,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub chain_type: std::string::String,
     #[prost(string, tag = "4")]
+    #[serde(default)]
     pub address: std::string::String,
     #[prost(message, optional, tag = "5")]
+    #[serde(default)]
+    #[serde(with = "crate::json_api::any")]
     pub input: ::std::option::Option<::prost_types::Any>,
+    /// use the token returned by unlock_session instead of the password
+    #[prost(string, tag = "6")]
+    #[serde(default)]
+    pub session_token: std::string::String,
+}
+/// Session
+
+/// FUNCTION: unlock_session(UnlockSessionParam): UnlockSessionResult
+///
+/// verify the password once and return a token which can be used instead of the password by
+/// sign_tx, tron_sign_msg and keystore_common_derive until it expires.
+/// ttl is in seconds and defaults to 300, chainType, address and maxUses limit the scope of the
+/// session, empty or 0 means no limit. keystore_common_derive only checks the chainType, so a
+/// session of an address can derive the other accounts of its chain
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UnlockSessionParam {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub id: std::string::String,
+    #[prost(string, tag = "2")]
+    #[serde(default)]
+    pub password: std::string::String,
+    #[prost(uint32, tag = "3")]
+    #[serde(default)]
+    pub ttl: u32,
+    #[prost(string, tag = "4")]
+    #[serde(default)]
+    pub chain_type: std::string::String,
+    #[prost(string, tag = "5")]
+    #[serde(default)]
+    pub address: std::string::String,
+    #[prost(uint32, tag = "6")]
+    #[serde(default)]
+    pub max_uses: u32,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UnlockSessionResult {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub id: std::string::String,
+    #[prost(string, tag = "2")]
+    #[serde(default)]
+    pub session_token: std::string::String,
+    /// unix timestamp in seconds
+    #[prost(int64, tag = "3")]
+    #[serde(default)]
+    #[serde(with = "tcx_proto::json::int64")]
+    pub expires_at: i64,
+}
+/// FUNCTION: lock_session(LockSessionParam): Response
+///
+/// revoke the session, isSuccess is false if the session doesn't exist or has expired
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LockSessionParam {
+    #[prost(string, tag = "1")]
+    #[serde(default)]
+    pub session_token: std::string::String,
 }
 /// Other
 // TODO: annotate following message usage
 
 /// btc-fork
+
+/// FUNCTION: btc_fork_external_address(ExternalAddressParam): ExternalAddressResult
+///
+/// derive the receive address at `0/{externalIdx}` of the account of the chainType at `address`,
+/// no password is needed since the account xpub is used. The address can be left empty when the
+/// keystore has only one account of the chainType
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ExternalAddressParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub chain_type: std::string::String,
     #[prost(uint32, tag = "3")]
+    #[serde(default)]
     pub external_idx: u32,
+    /// the address of the account, e.g. the legacy or the segwit one
+    #[prost(string, tag = "4")]
+    #[serde(default)]
+    pub address: std::string::String,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ExternalAddressResult {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub address: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub derived_path: std::string::String,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub r#type: std::string::String,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ExternalAddressExtra {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub enc_xpub: std::string::String,
     #[prost(message, optional, tag = "2")]
+    #[serde(default)]
     pub external_address: ::std::option::Option<external_address_extra::ExternalAddress>,
 }
 pub mod external_address_extra {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct ExternalAddress {
         #[prost(string, tag = "1")]
+        #[serde(default)]
         pub address: std::string::String,
         #[prost(string, tag = "2")]
+        #[serde(default)]
         pub derived_path: std::string::String,
         #[prost(string, tag = "3")]
+        #[serde(default)]
         pub r#type: std::string::String,
     }
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct BtcForkDeriveExtraParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub network: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub seg_wit: std::string::String,
 }
+/// FUNCTION: hd_store_extended_public_key(HdStoreExtendedPublicKeyParam): HdStoreExtendedPublicKeyResponse
+///
+/// export the plain extended public key of an account, the version bytes follow the network of
+/// the account
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct HdStoreExtendedPublicKeyParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub password: std::string::String,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub chain_type: std::string::String,
     #[prost(string, tag = "4")]
+    #[serde(default)]
     pub address: std::string::String,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct HdStoreExtendedPublicKeyResponse {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub extended_public_key: std::string::String,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ErrorCategory {
+    Unknown = 0,
+    Crypto = 1,
+    Keystore = 2,
+    Chain = 3,
+    Input = 4,
+}
 /// only support two types
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum KeyType {
     Mnemonic = 0,
     PrivateKey = 1,
 }
+/// FUNCTION: get_derived_key(WalletKeyParam): DerivedKeyResult
+///
+/// get the hex output of the kdf, the host can keep it in a secure storage such as the keychain
+
+/// FUNCTION: cache_derived_key(CacheDerivedKeyParam): DerivedKeyResult
+///
+/// cache a derived key in memory, after that the tempPassword unlocks the keystore without the kdf
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CacheDerivedKeyParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub derived_key: std::string::String,
     #[prost(string, tag = "3")]
+    #[serde(default)]
     pub temp_password: std::string::String,
 }
+/// FUNCTION: verify_derived_key(VerifyDerivedKeyParam): DerivedKeyResult
+///
+/// verify a derived key kept by the host
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct VerifyDerivedKeyParam {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub derived_key: std::string::String,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DerivedKeyResult {
     #[prost(string, tag = "1")]
+    #[serde(default)]
     pub id: std::string::String,
     #[prost(string, tag = "2")]
+    #[serde(default)]
     pub derived_key: std::string::String,
 }
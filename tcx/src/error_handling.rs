@@ -1,31 +1,214 @@
-use crate::filemanager::KEYSTORE_MAP;
+use crate::api::{ErrorCategory, Response};
+use crate::TOKEN_CORE;
 use core::result;
-use failure::{Backtrace, Error};
+use failure::{Context, Fail};
 use std::{cell::RefCell, mem, panic};
-pub type Result<T> = result::Result<T, Error>;
+pub type Result<T> = result::Result<T, failure::Error>;
 
 thread_local! {
-    pub static LAST_ERROR: RefCell<Option<Error>> = RefCell::new(None);
-    pub static LAST_BACKTRACE: RefCell<Option<(Option<String>, Backtrace)>> = RefCell::new(None);
+    pub static LAST_ERROR: RefCell<Option<failure::Error>> = RefCell::new(None);
+}
+
+/// Errors raised by the api layer itself
+#[derive(Fail, Debug, PartialEq)]
+pub enum Error {
+    #[fail(display = "wallet_not_found")]
+    WalletNotFound,
+    #[fail(display = "address_already_exist")]
+    AddressAlreadyExist,
+    #[fail(display = "private_keystore_cannot_export_mnemonic")]
+    PrivateKeystoreCannotExportMnemonic,
+    #[fail(display = "unsupported_chain")]
+    UnsupportedChain,
+    #[fail(display = "address_invalid")]
+    AddressInvalid,
+    #[fail(display = "unsupported_method")]
+    UnsupportedMethod,
+    #[fail(display = "invalid_json")]
+    InvalidJson,
+    #[fail(display = "unsupported_message_type")]
+    UnsupportedMessageType,
+    #[fail(display = "session_not_found")]
+    SessionNotFound,
+    #[fail(display = "session_expired")]
+    SessionExpired,
+    #[fail(display = "session_scope_mismatch")]
+    SessionScopeMismatch,
+    #[fail(display = "unsupported_watch_only_source")]
+    UnsupportedWatchOnlySource,
+    #[fail(display = "xpub_version_unsupported")]
+    XpubVersionUnsupported,
+    #[fail(display = "unsupported_storage")]
+    UnsupportedStorage,
+    #[fail(display = "bip39_passphrase_not_exportable")]
+    Bip39PassphraseNotExportable,
+    #[fail(display = "keystore_changed")]
+    KeystoreChanged,
+    #[fail(display = "mnemonic_language_required")]
+    MnemonicLanguageRequired,
 }
 
 #[cfg_attr(tarpaulin, skip)]
-#[allow(irrefutable_let_patterns)]
-fn notify_err(err: Error) {
-    if let _backtrace = err.backtrace() {
-        LAST_BACKTRACE.with(|e| {
-            *e.borrow_mut() = Some((None, Backtrace::new()));
-        });
-    }
+fn notify_err(err: failure::Error) {
     LAST_ERROR.with(|e| {
         *e.borrow_mut() = Some(err);
     });
 }
 
 fn lock_all_keystore() {
-    let mut map = KEYSTORE_MAP.write();
-    for ks in map.values_mut() {
-        ks.lock();
+    TOKEN_CORE.lock_all_keystore();
+}
+
+/// map a typed error to a stable error code and category, see `Response` in api.proto
+///
+/// The first typed error in the cause chain wins, so errors wrapped by a context keep their code
+pub fn error_code(err: &failure::Error) -> (i32, ErrorCategory) {
+    err.iter_chain()
+        .filter_map(fail_code)
+        .next()
+        .unwrap_or((0, ErrorCategory::Unknown))
+}
+
+fn fail_code(err: &dyn Fail) -> Option<(i32, ErrorCategory)> {
+    if let Some(e) = err.downcast_ref::<tcx_crypto::Error>() {
+        use tcx_crypto::Error::*;
+        let ret = match e {
+            KdfParamsInvalid => (1001, ErrorCategory::Crypto),
+            PasswordIncorrect => (1002, ErrorCategory::Crypto),
+            InvalidKeyIvLength => (1003, ErrorCategory::Crypto),
+            InvalidCiphertext => (1004, ErrorCategory::Crypto),
+            DerivedKeyIncorrect => (1005, ErrorCategory::Crypto),
+            CipherAuthFailed => (1006, ErrorCategory::Crypto),
+            UnsupportedCipher => (1007, ErrorCategory::Crypto),
+            Slip39WordInvalid => (1008, ErrorCategory::Input),
+            Slip39MnemonicInvalid => (1009, ErrorCategory::Input),
+            Slip39ChecksumInvalid => (1010, ErrorCategory::Input),
+            Slip39ThresholdInvalid => (1011, ErrorCategory::Input),
+            Slip39SharesMismatch => (1012, ErrorCategory::Input),
+            Slip39InsufficientShares => (1013, ErrorCategory::Input),
+            Slip39DigestInvalid => (1014, ErrorCategory::Input),
+            Slip39MasterSecretInvalid => (1015, ErrorCategory::Input),
+            Slip39PassphraseInvalid => (1016, ErrorCategory::Input),
+        };
+        return Some(ret);
+    }
+
+    if let Some(e) = err.downcast_ref::<tcx_chain::Error>() {
+        use tcx_chain::Error::*;
+        let ret = match e {
+            MnemonicInvalid => (2001, ErrorCategory::Input),
+            MnemonicWordInvalid => (2002, ErrorCategory::Input),
+            MnemonicLengthInvalid => (2003, ErrorCategory::Input),
+            MnemonicChecksumInvalid => (2004, ErrorCategory::Input),
+            AccountNotFound => (2005, ErrorCategory::Keystore),
+            CannotDeriveKey => (2006, ErrorCategory::Keystore),
+            KeystoreLocked => (2007, ErrorCategory::Keystore),
+            InvalidVersion => (2008, ErrorCategory::Keystore),
+            MnemonicLanguageUnsupported => (2009, ErrorCategory::Input),
+            WatchOnlyCannotSign => (2010, ErrorCategory::Keystore),
+            UnlockThrottled => (2011, ErrorCategory::Keystore),
+            KeystoreLockedOut => (2012, ErrorCategory::Keystore),
+        };
+        return Some(ret);
+    }
+
+    if let Some(e) = err.downcast_ref::<tcx_primitive::KeyError>() {
+        use tcx_primitive::KeyError::*;
+        let ret = match e {
+            InvalidEcdsa => (3001, ErrorCategory::Crypto),
+            InvalidChildNumberFormat => (3002, ErrorCategory::Input),
+            OverflowChildNumber => (3003, ErrorCategory::Input),
+            InvalidDerivationPathFormat => (3004, ErrorCategory::Input),
+            InvalidSignature => (3005, ErrorCategory::Input),
+            InvalidChildNumber => (3006, ErrorCategory::Input),
+            CannotDeriveFromHardenedKey => (3007, ErrorCategory::Crypto),
+            InvalidBase58 => (3008, ErrorCategory::Input),
+            InvalidPrivateKey => (3009, ErrorCategory::Input),
+            InvalidPublicKey => (3010, ErrorCategory::Input),
+            InvalidMessage => (3011, ErrorCategory::Input),
+            InvalidRecoveryId => (3012, ErrorCategory::Input),
+            InvalidTweak => (3013, ErrorCategory::Crypto),
+            NotEnoughMemory => (3014, ErrorCategory::Crypto),
+            InvalidCurveType => (3015, ErrorCategory::Input),
+            InvalidWordCount => (3016, ErrorCategory::Input),
+            InvalidEntropy => (3017, ErrorCategory::Input),
+            InsufficientEntropy => (3018, ErrorCategory::Input),
+        };
+        return Some(ret);
+    }
+
+    if let Some(e) = err.downcast_ref::<tcx_btc_fork::Error>() {
+        use tcx_btc_fork::Error::*;
+        let ret = match e {
+            DecryptXPubError => (4001, ErrorCategory::Crypto),
+            UnsupportedChain => (4002, ErrorCategory::Chain),
+            MissingNetwork => (4003, ErrorCategory::Input),
+        };
+        return Some(ret);
+    }
+
+    if let Some(e) = err.downcast_ref::<tcx_ckb::Error>() {
+        use tcx_ckb::Error::*;
+        let code = match e {
+            InvalidOutputPoint => 5001,
+            InvalidOutputsDataLength => 5002,
+            RequiredWitness => 5003,
+            InvalidInputCells => 5004,
+            RequiredOutputsData => 5005,
+            WitnessGroupEmpty => 5006,
+            WitnessEmpty => 5007,
+            InvalidTxHash => 5008,
+            InvalidHashType => 5009,
+            CellInputNotCached => 5010,
+            InvalidHexValue => 5011,
+        };
+        return Some((code, ErrorCategory::Chain));
+    }
+
+    let api_err = err.downcast_ref::<Error>().or_else(|| {
+        err.downcast_ref::<Context<Error>>()
+            .map(Context::get_context)
+    });
+    if let Some(e) = api_err {
+        use self::Error::*;
+        let ret = match e {
+            WalletNotFound => (6001, ErrorCategory::Keystore),
+            AddressAlreadyExist => (6002, ErrorCategory::Keystore),
+            PrivateKeystoreCannotExportMnemonic => (6003, ErrorCategory::Keystore),
+            UnsupportedChain => (6004, ErrorCategory::Chain),
+            AddressInvalid => (6005, ErrorCategory::Input),
+            UnsupportedMethod => (6006, ErrorCategory::Input),
+            InvalidJson => (6007, ErrorCategory::Input),
+            UnsupportedMessageType => (6008, ErrorCategory::Input),
+            SessionNotFound => (6009, ErrorCategory::Keystore),
+            SessionExpired => (6010, ErrorCategory::Keystore),
+            SessionScopeMismatch => (6011, ErrorCategory::Input),
+            UnsupportedWatchOnlySource => (6012, ErrorCategory::Input),
+            XpubVersionUnsupported => (6013, ErrorCategory::Input),
+            UnsupportedStorage => (6014, ErrorCategory::Input),
+            Bip39PassphraseNotExportable => (6015, ErrorCategory::Input),
+            KeystoreChanged => (6016, ErrorCategory::Keystore),
+            MnemonicLanguageRequired => (6017, ErrorCategory::Input),
+        };
+        return Some(ret);
+    }
+
+    None
+}
+
+/// build the `Response` returned by `get_last_err_message`
+pub fn error_response(err: &failure::Error) -> Response {
+    let (error_code, error_category) = error_code(err);
+    // the backtrace of the error itself, a backtrace captured here would only show the caller
+    let backtrace = err.backtrace().to_string();
+
+    Response {
+        is_success: false,
+        error: err.to_string(),
+        error_code,
+        error_category: error_category as i32,
+        context: err.iter_chain().skip(1).map(|c| c.to_string()).collect(),
+        backtrace,
     }
 }
 
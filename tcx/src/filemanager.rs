@@ -1,44 +1,243 @@
 use std::collections::HashMap;
-use std::fs;
-use std::io::Write;
-use std::path::Path;
-//use std::sync::RwLock;
+
 use parking_lot::RwLock;
-use tcx_chain::Keystore;
+use tcx_chain::{HdKeystore, Keystore, PrivateKeystore, WatchOnlyKeystore};
 
-use crate::error_handling::Result;
+use crate::api::ScannedKeystore;
+use crate::error_handling::{Error, Result};
+use crate::storage::{quarantined_name, KeystoreStorage};
+use crate::token_core::TokenCore;
 
-lazy_static! {
-    pub static ref KEYSTORE_MAP: RwLock<HashMap<String, Keystore>> = RwLock::new(HashMap::new());
-    pub static ref WALLET_FILE_DIR: RwLock<String> = RwLock::new("../test-data".to_string());
-    pub static ref IS_DEBUG: RwLock<bool> = RwLock::new(false);
+/// the storage entry of a keystore
+fn keystore_name(id: &str) -> String {
+    format!("{}.json", id)
 }
 
-pub fn clean_keystore() {
-    KEYSTORE_MAP.write().clear()
+/// parse a keystore json, `None` if the version is not handled by this library
+fn parse_keystore(contents: &str) -> Result<Option<Keystore>> {
+    let v: serde_json::Value = serde_json::from_str(contents)?;
+
+    let version = v["version"].as_i64().ok_or(Error::InvalidJson)?;
+    if version == i64::from(HdKeystore::VERSION)
+        || version == i64::from(PrivateKeystore::VERSION)
+        || version == WatchOnlyKeystore::VERSION
+    {
+        Ok(Some(Keystore::from_json(contents)?))
+    } else {
+        Ok(None)
+    }
 }
 
-pub fn cache_keystore(keystore: Keystore) {
-    KEYSTORE_MAP
-        .write()
-        .insert(keystore.id().to_owned(), keystore);
+/// move an entry to the quarantine, under a name no other quarantined entry has
+fn quarantine(storage: &dyn KeystoreStorage, name: &str) -> Result<()> {
+    let quarantined = quarantined_name(name);
+    let mut to = quarantined.to_string();
+    let mut copies = 0;
+    while storage.load(&to).is_ok() {
+        copies += 1;
+        to = format!("{}-{}", quarantined, copies);
+    }
+    storage.rename(name, &to)
 }
 
-pub fn flush_keystore(ks: &Keystore) -> Result<()> {
-    let json = ks.to_json();
+impl TokenCore {
+    pub(crate) fn clean_keystore(&self) {
+        self.keystore_map().write().clear();
+        self.stored_revisions().write().clear();
+    }
 
-    let file_dir = WALLET_FILE_DIR.read();
-    let ks_path = format!("{}/{}.json", file_dir, ks.id());
-    let path = Path::new(&ks_path);
-    let mut file = fs::File::create(path)?;
-    let _ = file.write_all(&json.as_bytes());
-    Ok(())
-}
+    pub(crate) fn cache_keystore(&self, keystore: Keystore) {
+        self.keystore_map()
+            .write()
+            .insert(keystore.id().to_owned(), keystore);
+    }
+
+    /// load one stored keystore into the cache. A damaged entry is restored from its backup if
+    /// possible, otherwise it's quarantined like the duplicate ones
+    pub(crate) fn scan_keystore_file(&self, name: &str) -> Result<ScannedKeystore> {
+        let storage = self.storage()?;
+        let mut scanned = ScannedKeystore {
+            file: name.to_string(),
+            ..ScannedKeystore::default()
+        };
+        // read before the content, a change in between is picked up by the next reload
+        let mut revision = storage.revision(name).unwrap_or(None);
+
+        let keystore = match storage.load(name).and_then(|json| parse_keystore(&json)) {
+            Ok(keystore) => keystore,
+            Err(err) => {
+                let backup = storage.load_backup(name).unwrap_or(None);
+                match backup.and_then(|json| parse_keystore(&json).ok().map(|ks| (json, ks))) {
+                    Some((json, keystore)) => {
+                        quarantine(&*storage, name)?;
+                        storage.save(name, &json)?;
+                        revision = storage.revision(name).unwrap_or(None);
+                        scanned.restored_from_backup = true;
+                        scanned.error = format!("{}", err);
+                        keystore
+                    }
+                    None => {
+                        quarantine(&*storage, name)?;
+                        scanned.status = "CORRUPTED".to_string();
+                        scanned.error = format!("{}", err);
+                        return Ok(scanned);
+                    }
+                }
+            }
+        };
+
+        let keystore = match keystore {
+            Some(keystore) => keystore,
+            None => {
+                self.stored_revisions()
+                    .write()
+                    .insert(name.to_string(), ("".to_string(), revision));
+                scanned.status = "UNSUPPORTED_VERSION".to_string();
+                return Ok(scanned);
+            }
+        };
+
+        scanned.id = keystore.id().to_string();
+        if self.keystore_map().read().contains_key(keystore.id()) {
+            quarantine(&*storage, name)?;
+            scanned.status = "DUPLICATE_ID".to_string();
+        } else {
+            self.cache_keystore(keystore);
+            self.stored_revisions()
+                .write()
+                .insert(name.to_string(), (scanned.id.to_string(), revision));
+            scanned.status = "LOADED".to_string();
+        }
+        Ok(scanned)
+    }
+
+    /// the keystore map after picking up the keystores created, changed or deleted by the other
+    /// processes sharing the storage, every handler looks up the keystores through it
+    pub(crate) fn fresh_keystore_map(&self) -> Result<&RwLock<HashMap<String, Keystore>>> {
+        self.reload_stale_keystores()?;
+        Ok(self.keystore_map())
+    }
+
+    fn reload_stale_keystores(&self) -> Result<()> {
+        let storage = self.storage()?;
+        let names = storage.list()?;
+        let known = self.stored_revisions().read().clone();
+
+        let mut vanished = vec![];
+        let mut removed = vec![];
+        for (name, (id, _)) in known.iter().filter(|(name, _)| !names.contains(name)) {
+            vanished.push(name.to_string());
+            let is_stored_elsewhere = known.iter().any(|(other, (other_id, _))| {
+                other != name && other_id == id && names.contains(other)
+            });
+            if !id.is_empty() && !is_stored_elsewhere {
+                removed.push(id.to_string());
+            }
+        }
+
+        let mut changed = vec![];
+        for name in names {
+            // the entry may be gone since it's listed
+            let revision = match storage.revision(&name) {
+                Ok(revision) => revision,
+                Err(_) => continue,
+            };
+            let known_id = match known.get(&name) {
+                Some((_, known_revision)) if known_revision.is_none() => continue,
+                Some((_, known_revision)) if *known_revision == revision => continue,
+                Some((id, _)) => id.to_string(),
+                None => "".to_string(),
+            };
+            // a damaged entry waits for the next scan, the loaded keystore is kept until then
+            match storage.load(&name).and_then(|json| parse_keystore(&json)) {
+                Ok(keystore) => changed.push((name, known_id, revision, keystore)),
+                Err(_) if !known.contains_key(&name) => {
+                    changed.push((name, known_id, revision, None))
+                }
+                Err(_) => {}
+            }
+        }
+
+        if vanished.is_empty() && changed.is_empty() {
+            return Ok(());
+        }
+
+        let mut stored_revisions = vec![];
+        let mut reloaded = vec![];
+        {
+            let mut map = self.keystore_map().write();
+            for id in removed.iter() {
+                map.remove(id);
+            }
+            for (name, known_id, revision, keystore) in changed {
+                let id = match keystore {
+                    // the same keystore copied to another entry is left to the next scan
+                    Some(ref keystore)
+                        if keystore.id() != known_id && map.contains_key(keystore.id()) =>
+                    {
+                        "".to_string()
+                    }
+                    Some(keystore) => {
+                        let id = keystore.id().to_string();
+                        map.insert(id.to_string(), keystore);
+                        reloaded.push(id.to_string());
+                        id
+                    }
+                    None => "".to_string(),
+                };
+                stored_revisions.push((name, id, revision));
+            }
+        }
+
+        for id in removed {
+            self.close_keystore_sessions(&id);
+        }
+        {
+            let map = self.keystore_map().read();
+            for keystore in reloaded.iter().filter_map(|id| map.get(id)) {
+                self.close_stale_sessions(keystore);
+            }
+        }
+        let mut revisions = self.stored_revisions().write();
+        for name in vanished {
+            revisions.remove(&name);
+        }
+        for (name, id, revision) in stored_revisions {
+            revisions.insert(name, (id, revision));
+        }
+        Ok(())
+    }
+
+    /// save a keystore changed since it was loaded. It fails with `KeystoreChanged` if another
+    /// process saved it in between, the next handler call reloads its version
+    pub(crate) fn flush_keystore(&self, ks: &Keystore) -> Result<()> {
+        let json = ks.to_json();
+        let name = keystore_name(ks.id());
+        let storage = self.storage()?;
+        let loaded = self
+            .stored_revisions()
+            .read()
+            .get(&name)
+            .and_then(|(_, revision)| revision.clone());
+        storage.save_if_unchanged(&name, &json, loaded.as_ref().map(String::as_str))?;
+
+        let revision = storage.revision(&name).unwrap_or(None);
+        self.stored_revisions()
+            .write()
+            .insert(name, (ks.id().to_string(), revision));
+        Ok(())
+    }
+
+    /// drop the backup of the previous version of a keystore, it must not outlive the password
+    /// the keystore was changed from
+    pub(crate) fn delete_keystore_backup(&self, wid: &str) -> Result<()> {
+        self.storage()?.delete_backup(&keystore_name(wid))
+    }
 
-pub fn delete_keystore_file(wid: &str) -> Result<()> {
-    let file_dir = WALLET_FILE_DIR.read();
-    let ks_path = format!("{}/{}.json", file_dir, wid);
-    let path = Path::new(&ks_path);
-    fs::remove_file(path)?;
-    Ok(())
+    pub(crate) fn delete_keystore_file(&self, wid: &str) -> Result<()> {
+        let name = keystore_name(wid);
+        self.storage()?.delete(&name)?;
+        self.stored_revisions().write().remove(&name);
+        Ok(())
+    }
 }
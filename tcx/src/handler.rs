@@ -1,49 +1,59 @@
-use std::fs;
-use std::io::Read;
-use std::path::Path;
-
 use bytes::BytesMut;
 use prost::Message;
-use serde_json::Value;
-use tcx_primitive::{get_account_path, private_key_without_version, FromHex, TypedPrivateKey};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tcx_primitive::{
+    generate_mnemonic_with_entropy, get_account_path, private_key_without_version, Derive,
+    DerivePath, EntropyFormat, FromHex, KeyError, ToHex, TypedDeterministicPublicKey,
+    TypedPrivateKey, TypedPublicKey, UserEntropy,
+};
 
 use tcx_bch::{BchAddress, BchTransaction};
 use tcx_btc_fork::{
     BtcForkAddress, BtcForkSegWitTransaction, BtcForkSignedTxOutput, BtcForkTransaction,
-    BtcForkTxInput, WifDisplay,
+    BtcForkTxInput, ExternalAddress, WifDisplay,
+};
+use tcx_chain::{
+    key_hash_from_mnemonic_with_passphrase, key_hash_from_private_key,
+    key_hash_from_watched_account, Keystore,
+};
+use tcx_chain::{
+    Account, HdKeystore, Metadata, MnemonicLanguage, PrivateKeystore, Source, WatchOnlyKeystore,
 };
-use tcx_chain::{key_hash_from_mnemonic, key_hash_from_private_key, Keystore, KeystoreGuard};
-use tcx_chain::{Account, HdKeystore, Metadata, PrivateKeystore, Source};
 use tcx_ckb::{CkbAddress, CkbTxInput};
-use tcx_crypto::{XPUB_COMMON_IV, XPUB_COMMON_KEY_128};
 use tcx_tron::TrxAddress;
 
 use crate::api::keystore_common_derive_param::Derivation;
 use crate::api::{
-    AccountResponse, AccountsResponse, ExportPrivateKeyParam, HdStoreCreateParam,
-    HdStoreImportParam, KeyType, KeystoreCommonAccountsParam, KeystoreCommonDeriveParam,
-    KeystoreCommonExistsParam, KeystoreCommonExistsResult, KeystoreCommonExportResult,
-    PrivateKeyStoreExportParam, PrivateKeyStoreImportParam, Response, WalletKeyParam, WalletResult,
+    AccountResponse, AccountsResponse, EntropyParam, ExportPrivateKeyParam, HdStoreCreateParam,
+    HdStoreImportParam, KeyType, KeystoreCommonAccountParam, KeystoreCommonAccountsParam,
+    KeystoreCommonChangePasswordParam, KeystoreCommonDeriveParam, KeystoreCommonExistsParam,
+    KeystoreCommonExistsResult, KeystoreCommonExportResult, KeystoreCommonUnlockWaitParam,
+    KeystoreUpdateMetaParam, PrivateKeyStoreExportParam, PrivateKeyStoreImportParam, Response,
+    WalletKeyParam, WalletResult,
 };
-use crate::api::{InitTokenCoreXParam, SignParam};
-use crate::error_handling::Result;
-use crate::filemanager::{cache_keystore, clean_keystore, flush_keystore, WALLET_FILE_DIR};
-use crate::filemanager::{delete_keystore_file, KEYSTORE_MAP};
+use crate::api::{
+    AccountUpdateLabelParam, CacheDerivedKeyParam, DerivedKeyResult, ExternalAddressParam,
+    ExternalAddressResult, HdStoreExtendedPublicKeyParam, HdStoreExtendedPublicKeyResponse,
+    InitTokenCoreXParam, LockSessionParam, ScanKeystoresResult, SignParam, Slip39ExportParam,
+    Slip39ExportResult, Slip39Group, Slip39ImportParam, UnlockSessionParam, UnlockSessionResult,
+    UnlockWaitResult, VerifyDerivedKeyParam, WatchOnlyStoreImportParam,
+};
+use crate::error_handling::{Error, Result};
+use crate::token_core::TokenCore;
 
-use crate::IS_DEBUG;
 use tcx_chain::tcx_ensure;
 use tcx_chain::Address;
 use tcx_chain::{MessageSigner, TransactionSigner};
 use tcx_constants::coin_info::coin_info_from_param;
-use tcx_constants::CurveType;
+use tcx_constants::{network_from_pub_version, CurveType};
 use tcx_crypto::aes::cbc::encrypt_pkcs7;
+use tcx_crypto::slip39::{combine_mnemonics, split_master_secret, GroupSpec};
+use tcx_crypto::Zeroizing;
 use tcx_primitive::{Bip32DeterministicPublicKey, Ss58Codec};
 use tcx_tron::transaction::{TronMessageInput, TronTxInput};
 
 pub(crate) fn encode_message(msg: impl Message) -> Result<Vec<u8>> {
-    if *IS_DEBUG.read() {
-        println!("{:#?}", msg);
-    }
     let mut buf = BytesMut::with_capacity(msg.encoded_len());
     msg.encode(&mut buf)?;
     Ok(buf.to_vec())
@@ -62,578 +72,1287 @@ fn derive_account<'a, 'b>(keystore: &mut Keystore, derivation: &Derivation) -> R
         "LITECOIN" => keystore.derive_coin::<BtcForkAddress>(&coin_info),
         "TRON" => keystore.derive_coin::<TrxAddress>(&coin_info),
         "NERVOS" => keystore.derive_coin::<CkbAddress>(&coin_info),
-        _ => Err(format_err!("unsupported_chain")),
+        _ => Err(Error::UnsupportedChain.into()),
+    }
+}
+
+fn key_data_from_any_format_pk(pk: &str) -> Result<Vec<u8>> {
+    let decoded = hex::decode(pk.to_string());
+    if decoded.is_ok() {
+        Ok(decoded.unwrap())
+    } else {
+        private_key_without_version(pk)
     }
 }
 
-pub fn init_token_core_x(data: &[u8]) -> Result<()> {
-    let InitTokenCoreXParam {
-        file_dir,
-        xpub_common_key,
-        xpub_common_iv,
-    } = InitTokenCoreXParam::decode(data).unwrap();
-    *WALLET_FILE_DIR.write() = file_dir.to_string();
-    *XPUB_COMMON_KEY_128.write() = xpub_common_key.to_string();
-    *XPUB_COMMON_IV.write() = xpub_common_iv.to_string();
+fn key_hash_from_any_format_pk(pk: &str) -> Result<String> {
+    let key_data = key_data_from_any_format_pk(pk)?;
+    Ok(key_hash_from_private_key(&key_data))
+}
+
+/// empty means english for new mnemonics and detecting the wordlist for imported ones
+fn mnemonic_language(language: &str) -> Result<Option<MnemonicLanguage>> {
+    if language.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(language.parse()?))
+    }
+}
 
-    scan_keystores()?;
+/// the thresholds, counts and the iteration exponent of slip39 fit in 4 bits
+fn slip39_param(value: u32) -> Result<u8> {
+    tcx_ensure!(value <= 16, tcx_crypto::Error::Slip39ThresholdInvalid);
+    Ok(value as u8)
+}
 
-    Ok(())
+/// `None` when the caller doesn't provide any entropy
+fn user_entropy(param: Option<&EntropyParam>) -> Result<Option<UserEntropy>> {
+    let param = match param {
+        Some(param) if !param.value.is_empty() => param,
+        _ => return Ok(None),
+    };
+    let format = match param.format.as_str() {
+        "" | "HEX" => EntropyFormat::Hex,
+        "DICE" => EntropyFormat::Dice,
+        "COINS" => EntropyFormat::Coins,
+        _ => return Err(KeyError::InvalidEntropy.into()),
+    };
+    Ok(Some(UserEntropy {
+        format,
+        value: param.value.to_owned(),
+        replace_rng: param.replace_rng,
+    }))
 }
 
-pub(crate) fn scan_keystores() -> Result<()> {
-    clean_keystore();
-    let file_dir = WALLET_FILE_DIR.read();
-    let p = Path::new(file_dir.as_str());
-    let walk_dir = std::fs::read_dir(p).expect("read dir");
-    for entry in walk_dir {
-        let entry = entry.expect("DirEntry");
-        let fp = entry.path();
-        if !fp
-            .file_name()
-            .expect("file_name")
-            .to_str()
-            .expect("file_name str")
-            .ends_with(".json")
-        {
-            continue;
+/// the account of a watch only keystore, it's derived from the public part only
+fn watch_only_account<A: Address>(
+    param: &WatchOnlyStoreImportParam,
+    source: Source,
+) -> Result<Account> {
+    let (public_key, ext_pub_key, coin_info) = match source {
+        Source::Xpub => {
+            let (xpk, version) =
+                Bip32DeterministicPublicKey::from_ss58check_with_version(&param.value)?;
+            // the version decides the network and the script of a btc fork extended public key
+            let coin_info = match param.chain_type.as_str() {
+                "BITCOINCASH" | "LITECOIN" => {
+                    let (network, seg_wit) =
+                        network_from_pub_version(&version).ok_or(Error::XpubVersionUnsupported)?;
+                    coin_info_from_param(&param.chain_type, &network, &seg_wit)?
+                }
+                _ => coin_info_from_param(&param.chain_type, &param.network, &param.seg_wit)?,
+            };
+            let xpk = TypedDeterministicPublicKey::Bip32Sepc256k1(xpk);
+            let public_key = xpk
+                .derive(DerivePath::from_str("0/0")?.into_iter())?
+                .public_key();
+            (Some(public_key), xpk.to_hex(), coin_info)
+        }
+        Source::PublicKey => {
+            let coin_info =
+                coin_info_from_param(&param.chain_type, &param.network, &param.seg_wit)?;
+            let public_key =
+                TypedPublicKey::from_slice(coin_info.curve, &hex::decode(&param.value)?)?;
+            (Some(public_key), "".to_owned(), coin_info)
+        }
+        Source::Address => {
+            tcx_ensure!(
+                param.chain_type == "TRON" || param.chain_type == "NERVOS",
+                Error::UnsupportedWatchOnlySource
+            );
+            let coin_info =
+                coin_info_from_param(&param.chain_type, &param.network, &param.seg_wit)?;
+            tcx_ensure!(A::is_valid(&param.value, &coin_info), Error::AddressInvalid);
+            (None, "".to_owned(), coin_info)
         }
+        _ => return Err(Error::UnsupportedWatchOnlySource.into()),
+    };
 
-        let mut f = fs::File::open(fp).expect("open file");
-        let mut contents = String::new();
+    let address = match public_key {
+        Some(public_key) => A::from_public_key(&public_key, &coin_info)?,
+        None => param.value.to_owned(),
+    };
+    let derivation_path = if source == Source::Xpub && !param.path.is_empty() {
+        format!("{}/0/0", param.path)
+    } else {
+        param.path.to_owned()
+    };
 
-        let _ = f.read_to_string(&mut contents);
-        let v: Value = serde_json::from_str(&contents).expect("read json from content");
+    Ok(Account {
+        address,
+        derivation_path,
+        curve: coin_info.curve,
+        coin: coin_info.coin,
+        network: coin_info.network,
+        seg_wit: coin_info.seg_wit,
+        ext_pub_key,
+        label: "".to_string(),
+        tags: vec![],
+        hidden: false,
+    })
+}
 
-        let version = v["version"].as_i64().expect("version");
-        if version == i64::from(HdKeystore::VERSION)
-            || version == i64::from(PrivateKeystore::VERSION)
+impl TokenCore {
+    pub fn init_token_core_x(&self, data: &[u8]) -> Result<()> {
+        let InitTokenCoreXParam {
+            file_dir,
+            xpub_common_key,
+            xpub_common_iv,
+            cipher,
+            upgrade_cipher,
+            storage,
+            max_unlock_failures,
+        } = InitTokenCoreXParam::decode(data).unwrap();
+        let mut config = self.config();
+        config.file_dir = file_dir.to_string();
+        config.xpub_common_key = xpub_common_key.to_string();
+        config.xpub_common_iv = xpub_common_iv.to_string();
+        if !cipher.is_empty() {
+            config.cipher = cipher.parse()?;
+        }
+        config.upgrade_cipher = upgrade_cipher;
+        config.storage = storage.parse()?;
+        if max_unlock_failures > 0 {
+            config.max_unlock_failures = max_unlock_failures;
+        }
+        self.set_config(config);
+
+        self.scan_keystores()?;
+
+        Ok(())
+    }
+
+    pub fn scan_keystores(&self) -> Result<ScanKeystoresResult> {
+        self.clean_keystore();
+        let mut keystores = vec![];
+        for name in self.storage()?.list()? {
+            keystores.push(self.scan_keystore_file(&name)?);
+        }
+        Ok(ScanKeystoresResult { keystores })
+    }
+
+    pub fn hd_store_create(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: HdStoreCreateParam =
+            HdStoreCreateParam::decode(data).expect("import wallet from mnemonic");
+
+        let mut meta = Metadata::default();
+        meta.name = param.name.to_owned();
+        meta.password_hint = param.password_hint.to_owned();
+        meta.source = Source::Mnemonic;
+        let language = mnemonic_language(&param.language)?.unwrap_or_default();
+        meta.mnemonic_language = Some(language);
+
+        let word_count = if param.word_count == 0 {
+            12
+        } else {
+            param.word_count as usize
+        };
+        let user_entropy = user_entropy(param.entropy.as_ref())?;
+        let mnemonic = generate_mnemonic_with_entropy(
+            word_count,
+            language.bip39_language(),
+            user_entropy.as_ref(),
+        )?;
+
+        let kdf = self.kdf_params(param.kdf.as_ref())?;
+        let ks = HdKeystore::from_mnemonic_with_kdf(
+            &mnemonic,
+            &param.password,
+            meta,
+            kdf,
+            self.cipher(),
+            &param.passphrase,
+        )?;
+
+        let keystore = Keystore::Hd(ks);
+        self.flush_keystore(&keystore)?;
+
+        let meta = keystore.meta();
+        let wallet = WalletResult {
+            id: keystore.id(),
+            name: meta.name.to_owned(),
+            source: "MNEMONIC".to_owned(),
+            accounts: vec![],
+            created_at: meta.timestamp.clone(),
+        };
+        let ret = self.encode_message(wallet)?;
+        self.cache_keystore(keystore);
+        Ok(ret)
+    }
+
+    pub fn hd_store_import(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: HdStoreImportParam =
+            HdStoreImportParam::decode(data).expect("import wallet from mnemonic");
+        self.import_mnemonic(&param)
+    }
+
+    fn import_mnemonic(&self, param: &HdStoreImportParam) -> Result<Vec<u8>> {
+        let mut founded_id: Option<String> = None;
         {
-            let keystore = Keystore::from_json(&contents)?;
-            cache_keystore(keystore);
+            let key_hash =
+                key_hash_from_mnemonic_with_passphrase(&param.mnemonic, &param.passphrase)?;
+            let map = self.fresh_keystore_map()?.read();
+            if let Some(founded) = map
+                .values()
+                .find(|keystore| keystore.key_hash() == key_hash)
+            {
+                founded_id = Some(founded.id());
+            }
         }
+
+        if founded_id.is_some() && !param.overwrite {
+            return Err(Error::AddressAlreadyExist.into());
+        }
+
+        let mut meta = Metadata::default();
+        meta.name = param.name.to_owned();
+        meta.password_hint = param.password_hint.to_owned();
+        meta.source = Source::Mnemonic;
+        meta.mnemonic_language = mnemonic_language(&param.language)?;
+
+        let kdf = self.kdf_params(param.kdf.as_ref())?;
+        let ks = HdKeystore::from_mnemonic_with_kdf(
+            &param.mnemonic,
+            &param.password,
+            meta,
+            kdf,
+            self.cipher(),
+            &param.passphrase,
+        )?;
+
+        let mut keystore = Keystore::Hd(ks);
+
+        if founded_id.is_some() {
+            keystore.set_id(&founded_id.unwrap());
+        }
+
+        self.flush_keystore(&keystore)?;
+
+        let meta = keystore.meta();
+        let wallet = WalletResult {
+            id: keystore.id(),
+            name: meta.name.to_owned(),
+            source: "MNEMONIC".to_owned(),
+            accounts: vec![],
+            created_at: meta.timestamp.clone(),
+        };
+        let ret = self.encode_message(wallet)?;
+        self.cache_keystore(keystore);
+        Ok(ret)
     }
-    Ok(())
-}
 
-pub(crate) fn hd_store_create(data: &[u8]) -> Result<Vec<u8>> {
-    let param: HdStoreCreateParam =
-        HdStoreCreateParam::decode(data).expect("import wallet from mnemonic");
+    #[deprecated(
+        since = "2.5.1",
+        note = "Please use the export_mnemonic function instead"
+    )]
+    #[allow(deprecated)]
+    pub fn hd_store_export(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: WalletKeyParam = WalletKeyParam::decode(data).expect("hd_store_export");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
 
-    let mut meta = Metadata::default();
-    meta.name = param.name.to_owned();
-    meta.password_hint = param.password_hint.to_owned();
-    meta.source = Source::Mnemonic;
+        let guard = self.unlock_by_password(keystore, &param.password)?;
 
-    let ks = HdKeystore::new(&param.password, meta);
+        let export_result = KeystoreCommonExportResult {
+            id: guard.keystore().id(),
+            r#type: KeyType::Mnemonic as i32,
+            value: guard.keystore().export()?,
+            passphrase: guard.keystore().export_passphrase()?,
+        };
 
-    let keystore = Keystore::Hd(ks);
-    flush_keystore(&keystore)?;
+        self.encode_message(export_result)
+    }
 
-    let meta = keystore.meta();
-    let wallet = WalletResult {
-        id: keystore.id(),
-        name: meta.name.to_owned(),
-        source: "MNEMONIC".to_owned(),
-        accounts: vec![],
-        created_at: meta.timestamp.clone(),
-    };
-    let ret = encode_message(wallet)?;
-    cache_keystore(keystore);
-    Ok(ret)
-}
+    pub fn hd_store_export_slip39(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: Slip39ExportParam =
+            Slip39ExportParam::decode(data).expect("hd_store_export_slip39");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
 
-pub(crate) fn hd_store_import(data: &[u8]) -> Result<Vec<u8>> {
-    let param: HdStoreImportParam =
-        HdStoreImportParam::decode(data).expect("import wallet from mnemonic");
+        let guard = self.unlock_by_password(keystore, &param.password)?;
+
+        tcx_ensure!(
+            guard.keystore().determinable(),
+            Error::PrivateKeystoreCannotExportMnemonic
+        );
+        // the shares only carry the mnemonic, a wallet recovered by them without the bip39
+        // passphrase would be another wallet
+        tcx_ensure!(
+            guard.keystore().export_passphrase()?.is_empty(),
+            Error::Bip39PassphraseNotExportable
+        );
+
+        let language = guard
+            .keystore()
+            .meta()
+            .mnemonic_language
+            .unwrap_or_default();
+        let mnemonic = Zeroizing::new(guard.keystore().export()?);
+        let master_secret = Zeroizing::new(language.entropy(&mnemonic)?);
+        let groups = param
+            .groups
+            .iter()
+            .map(|group| {
+                Ok(GroupSpec {
+                    member_threshold: slip39_param(group.member_threshold)?,
+                    member_count: slip39_param(group.member_count)?,
+                })
+            })
+            .collect::<Result<Vec<GroupSpec>>>()?;
+        let shares = split_master_secret(
+            &master_secret,
+            &param.passphrase,
+            slip39_param(param.group_threshold)?,
+            &groups,
+            slip39_param(param.iteration_exponent)?,
+        )?;
+
+        let export_result = Slip39ExportResult {
+            id: guard.keystore().id(),
+            groups: shares
+                .into_iter()
+                .map(|shares| Slip39Group { shares })
+                .collect(),
+            language: language.as_str().to_owned(),
+        };
 
-    let mut founded_id: Option<String> = None;
-    {
-        let key_hash = key_hash_from_mnemonic(&param.mnemonic)?;
-        let map = KEYSTORE_MAP.read();
-        if let Some(founded) = map
-            .values()
-            .find(|keystore| keystore.key_hash() == key_hash)
-        {
-            founded_id = Some(founded.id());
+        self.encode_message(export_result)
+    }
+
+    pub fn hd_store_import_slip39(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: Slip39ImportParam =
+            Slip39ImportParam::decode(data).expect("hd_store_import_slip39");
+
+        // the recovered mnemonic may be valid in several wordlists, so it isn't detected
+        let language =
+            mnemonic_language(&param.language)?.ok_or(Error::MnemonicLanguageRequired)?;
+        let master_secret = Zeroizing::new(combine_mnemonics(&param.shares, &param.passphrase)?);
+        let import_param = HdStoreImportParam {
+            mnemonic: language.mnemonic_from_entropy(&master_secret)?,
+            password: param.password,
+            source: "MNEMONIC".to_owned(),
+            name: param.name,
+            password_hint: param.password_hint,
+            overwrite: param.overwrite,
+            kdf: param.kdf,
+            passphrase: "".to_owned(),
+            language: param.language,
+        };
+        self.import_mnemonic(&import_param)
+    }
+
+    fn enc_xpub(&self, xpub: &str, network: &str) -> Result<String> {
+        let xpk = Bip32DeterministicPublicKey::from_hex(xpub)?;
+        let ext_pub_key: String;
+        if network == "MAINNET" {
+            ext_pub_key = xpk.to_ss58check_with_version(&[0x04, 0x88, 0xB2, 0x1E]);
+        } else {
+            ext_pub_key = xpk.to_ss58check_with_version(&[0x04, 0x35, 0x87, 0xCF]);
         }
+
+        let config = self.config();
+        let key_bytes = hex::decode(&config.xpub_common_key)?;
+        let iv_bytes = hex::decode(&config.xpub_common_iv)?;
+        let encrypted = encrypt_pkcs7(&ext_pub_key.as_bytes(), &key_bytes, &iv_bytes)?;
+        Ok(base64::encode(&encrypted))
     }
 
-    if founded_id.is_some() && !param.overwrite {
-        return Err(format_err!("{}", "address_already_exist"));
+    pub fn keystore_common_derive(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: KeystoreCommonDeriveParam =
+            KeystoreCommonDeriveParam::decode(data).expect("keystore_common_derive");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
+
+        let chain_types: Vec<&str> = param
+            .derivations
+            .iter()
+            .map(|derivation| derivation.chain_type.as_str())
+            .collect();
+        let mut guard = self.unlock_keystore(
+            keystore,
+            &param.password,
+            &param.session_token,
+            &chain_types,
+            None,
+        )?;
+
+        let mut account_responses: Vec<AccountResponse> = vec![];
+
+        for derivation in &param.derivations {
+            let account = derive_account(guard.keystore_mut(), derivation)?;
+            let enc_xpub = if account.ext_pub_key.is_empty() {
+                Ok("".to_string())
+            } else {
+                self.enc_xpub(&account.ext_pub_key.to_string(), &account.network)
+            }?;
+            let account_rsp = AccountResponse {
+                chain_type: derivation.chain_type.to_owned(),
+                address: account.address.to_owned(),
+                path: account.derivation_path.to_owned(),
+                extended_xpub_key: enc_xpub,
+                label: account.label.to_owned(),
+                tags: account.tags.clone(),
+                hidden: account.hidden,
+            };
+            account_responses.push(account_rsp);
+        }
+
+        let accounts_rsp = AccountsResponse {
+            accounts: account_responses,
+        };
+        self.flush_keystore(guard.keystore())?;
+        self.encode_message(accounts_rsp)
     }
 
-    let mut meta = Metadata::default();
-    meta.name = param.name.to_owned();
-    meta.password_hint = param.password_hint.to_owned();
-    meta.source = Source::Mnemonic;
+    pub fn export_mnemonic(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: WalletKeyParam = WalletKeyParam::decode(data).expect("export_mnemonic");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
 
-    let ks = HdKeystore::from_mnemonic(&param.mnemonic, &param.password, meta)?;
+        let guard = self.unlock_by_password(keystore, &param.password)?;
 
-    let mut keystore = Keystore::Hd(ks);
+        tcx_ensure!(
+            guard.keystore().determinable(),
+            Error::PrivateKeystoreCannotExportMnemonic
+        );
+
+        let export_result = KeystoreCommonExportResult {
+            id: guard.keystore().id(),
+            r#type: KeyType::Mnemonic as i32,
+            value: guard.keystore().export()?,
+            passphrase: guard.keystore().export_passphrase()?,
+        };
 
-    if founded_id.is_some() {
-        keystore.set_id(&founded_id.unwrap());
+        self.encode_message(export_result)
     }
 
-    flush_keystore(&keystore)?;
+    pub fn private_key_store_import(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: PrivateKeyStoreImportParam =
+            PrivateKeyStoreImportParam::decode(data).expect("private_key_store_import");
 
-    let meta = keystore.meta();
-    let wallet = WalletResult {
-        id: keystore.id(),
-        name: meta.name.to_owned(),
-        source: "MNEMONIC".to_owned(),
-        accounts: vec![],
-        created_at: meta.timestamp.clone(),
-    };
-    let ret = encode_message(wallet)?;
-    cache_keystore(keystore);
-    Ok(ret)
-}
+        let mut founded_id: Option<String> = None;
+        {
+            let key_hash = key_hash_from_any_format_pk(&param.private_key)?;
+            let map = self.fresh_keystore_map()?.read();
+            if let Some(founded) = map
+                .values()
+                .find(|keystore| keystore.key_hash() == key_hash)
+            {
+                founded_id = Some(founded.id());
+            }
+        }
 
-#[deprecated(
-    since = "2.5.1",
-    note = "Please use the export_mnemonic function instead"
-)]
-#[allow(deprecated)]
-pub(crate) fn hd_store_export(data: &[u8]) -> Result<Vec<u8>> {
-    let param: WalletKeyParam = WalletKeyParam::decode(data).expect("hd_store_export");
-    let mut map = KEYSTORE_MAP.write();
-    let keystore: &mut Keystore = match map.get_mut(&param.id) {
-        Some(keystore) => Ok(keystore),
-        _ => Err(format_err!("{}", "wallet_not_found")),
-    }?;
-
-    let guard = KeystoreGuard::unlock_by_password(keystore, &param.password)?;
-
-    let export_result = KeystoreCommonExportResult {
-        id: guard.keystore().id(),
-        r#type: KeyType::Mnemonic as i32,
-        value: guard.keystore().export()?,
-    };
+        if founded_id.is_some() && !param.overwrite {
+            return Err(Error::AddressAlreadyExist.into());
+        }
 
-    encode_message(export_result)
-}
+        let pk_bytes = key_data_from_any_format_pk(&param.private_key)?;
+        let private_key = hex::encode(pk_bytes);
+        let kdf = self.kdf_params(param.kdf.as_ref())?;
+        let pk_store = PrivateKeystore::from_private_key_with_kdf(
+            &private_key,
+            &param.password,
+            Source::Private,
+            kdf,
+            self.cipher(),
+        );
+
+        let mut keystore = Keystore::PrivateKey(pk_store);
+
+        if let Some(exist_kid) = founded_id {
+            keystore.set_id(&exist_kid)
+        }
 
-fn enc_xpub(xpub: &str, network: &str) -> Result<String> {
-    let xpk = Bip32DeterministicPublicKey::from_hex(xpub)?;
-    let ext_pub_key: String;
-    if network == "MAINNET" {
-        ext_pub_key = xpk.to_ss58check_with_version(&[0x04, 0x88, 0xB2, 0x1E]);
-    } else {
-        ext_pub_key = xpk.to_ss58check_with_version(&[0x04, 0x35, 0x87, 0xCF]);
+        self.flush_keystore(&keystore)?;
+
+        let meta = keystore.meta();
+        let wallet = WalletResult {
+            id: keystore.id(),
+            name: meta.name.to_owned(),
+            source: "PRIVATE".to_owned(),
+            accounts: vec![],
+            created_at: meta.timestamp.clone(),
+        };
+        let ret = self.encode_message(wallet)?;
+        self.cache_keystore(keystore);
+        Ok(ret)
     }
 
-    let key = tcx_crypto::XPUB_COMMON_KEY_128.read();
-    let iv = tcx_crypto::XPUB_COMMON_IV.read();
-    let key_bytes = hex::decode(&*key)?;
-    let iv_bytes = hex::decode(&*iv)?;
-    let encrypted = encrypt_pkcs7(&ext_pub_key.as_bytes(), &key_bytes, &iv_bytes)?;
-    Ok(base64::encode(&encrypted))
-}
+    pub fn watch_only_store_import(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: WatchOnlyStoreImportParam =
+            WatchOnlyStoreImportParam::decode(data).expect("watch_only_store_import");
+
+        let source = match param.source.as_str() {
+            "XPUB" => Source::Xpub,
+            "PUBLIC_KEY" => Source::PublicKey,
+            "ADDRESS" => Source::Address,
+            _ => return Err(Error::UnsupportedWatchOnlySource.into()),
+        };
+        let account = match param.chain_type.as_str() {
+            "BITCOINCASH" => watch_only_account::<BchAddress>(&param, source),
+            "LITECOIN" => watch_only_account::<BtcForkAddress>(&param, source),
+            "TRON" => watch_only_account::<TrxAddress>(&param, source),
+            "NERVOS" => watch_only_account::<CkbAddress>(&param, source),
+            _ => Err(Error::UnsupportedChain.into()),
+        }?;
 
-pub(crate) fn keystore_common_derive(data: &[u8]) -> Result<Vec<u8>> {
-    let param: KeystoreCommonDeriveParam =
-        KeystoreCommonDeriveParam::decode(data).expect("keystore_common_derive");
-    let mut map = KEYSTORE_MAP.write();
-    let keystore: &mut Keystore = match map.get_mut(&param.id) {
-        Some(keystore) => Ok(keystore),
-        _ => Err(format_err!("{}", "wallet_not_found")),
-    }?;
+        let mut founded_id: Option<String> = None;
+        {
+            let key_hash = key_hash_from_watched_account(&account);
+            let map = self.fresh_keystore_map()?.read();
+            if let Some(founded) = map
+                .values()
+                .find(|keystore| keystore.key_hash() == key_hash)
+            {
+                founded_id = Some(founded.id());
+            }
+        }
 
-    let mut guard = KeystoreGuard::unlock_by_password(keystore, &param.password)?;
+        if founded_id.is_some() && !param.overwrite {
+            return Err(Error::AddressAlreadyExist.into());
+        }
 
-    let mut account_responses: Vec<AccountResponse> = vec![];
+        let mut meta = Metadata::default();
+        meta.name = param.name.to_owned();
+        meta.password_hint = param.password_hint.to_owned();
+        meta.source = source;
 
-    for derivation in param.derivations {
-        let account = derive_account(guard.keystore_mut(), &derivation)?;
         let enc_xpub = if account.ext_pub_key.is_empty() {
-            Ok("".to_string())
+            "".to_owned()
         } else {
-            enc_xpub(&account.ext_pub_key.to_string(), &account.network)
-        }?;
+            self.enc_xpub(&account.ext_pub_key, &account.network)?
+        };
         let account_rsp = AccountResponse {
-            chain_type: derivation.chain_type.to_owned(),
+            chain_type: account.coin.to_owned(),
             address: account.address.to_owned(),
             path: account.derivation_path.to_owned(),
             extended_xpub_key: enc_xpub,
+            label: account.label.to_owned(),
+            tags: account.tags.clone(),
+            hidden: account.hidden,
         };
-        account_responses.push(account_rsp);
+
+        let kdf = self.kdf_params(param.kdf.as_ref())?;
+        let ks =
+            WatchOnlyKeystore::from_account(account, &param.password, meta, kdf, self.cipher());
+        let mut keystore = Keystore::WatchOnly(ks);
+
+        if let Some(exist_kid) = founded_id {
+            keystore.set_id(&exist_kid)
+        }
+
+        self.flush_keystore(&keystore)?;
+
+        let meta = keystore.meta();
+        let wallet = WalletResult {
+            id: keystore.id(),
+            name: meta.name.to_owned(),
+            source: param.source.to_owned(),
+            accounts: vec![account_rsp],
+            created_at: meta.timestamp.clone(),
+        };
+        let ret = self.encode_message(wallet)?;
+        self.cache_keystore(keystore);
+        Ok(ret)
     }
 
-    let accounts_rsp = AccountsResponse {
-        accounts: account_responses,
-    };
-    flush_keystore(guard.keystore())?;
-    encode_message(accounts_rsp)
-}
+    #[deprecated(
+        since = "2.5.1",
+        note = "Please use the export_private_key function instead"
+    )]
+    pub fn private_key_store_export(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: PrivateKeyStoreExportParam =
+            PrivateKeyStoreExportParam::decode(data).expect("private_key_store_export");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
 
-pub(crate) fn export_mnemonic(data: &[u8]) -> Result<Vec<u8>> {
-    let param: WalletKeyParam = WalletKeyParam::decode(data).expect("export_mnemonic");
-    let mut map = KEYSTORE_MAP.write();
-    let keystore: &mut Keystore = match map.get_mut(&param.id) {
-        Some(keystore) => Ok(keystore),
-        _ => Err(format_err!("{}", "wallet_not_found")),
-    }?;
-
-    let guard = KeystoreGuard::unlock_by_password(keystore, &param.password)?;
-
-    tcx_ensure!(
-        guard.keystore().determinable(),
-        format_err!("{}", "private_keystore_cannot_export_mnemonic")
-    );
-
-    let export_result = KeystoreCommonExportResult {
-        id: guard.keystore().id(),
-        r#type: KeyType::Mnemonic as i32,
-        value: guard.keystore().export()?,
-    };
+        let guard = self.unlock_by_password(keystore, &param.password)?;
 
-    encode_message(export_result)
-}
+        let pk_hex = Zeroizing::new(guard.keystore().export()?);
 
-fn key_data_from_any_format_pk(pk: &str) -> Result<Vec<u8>> {
-    let decoded = hex::decode(pk.to_string());
-    if decoded.is_ok() {
-        Ok(decoded.unwrap())
-    } else {
-        private_key_without_version(pk)
+        // private_key prefix is only about chain type and network
+        let coin_info = coin_info_from_param(&param.chain_type, &param.network, "")?;
+        let value = if param.chain_type.as_str() == "TRON" {
+            pk_hex.to_string()
+        } else {
+            let bytes = Zeroizing::new(hex::decode(pk_hex.as_str())?);
+            TypedPrivateKey::from_slice(CurveType::SECP256k1, &bytes)?.fmt(&coin_info)?
+        };
+
+        let export_result = KeystoreCommonExportResult {
+            id: guard.keystore().id(),
+            r#type: KeyType::PrivateKey as i32,
+            value,
+            passphrase: "".to_owned(),
+        };
+
+        self.encode_message(export_result)
     }
-}
 
-fn key_hash_from_any_format_pk(pk: &str) -> Result<String> {
-    let key_data = key_data_from_any_format_pk(pk)?;
-    Ok(key_hash_from_private_key(&key_data))
-}
+    pub fn export_private_key(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: ExportPrivateKeyParam =
+            ExportPrivateKeyParam::decode(data).expect("export_private_key");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
 
-pub(crate) fn private_key_store_import(data: &[u8]) -> Result<Vec<u8>> {
-    let param: PrivateKeyStoreImportParam =
-        PrivateKeyStoreImportParam::decode(data).expect("private_key_store_import");
+        let mut guard = self.unlock_by_password(keystore, &param.password)?;
 
-    let mut founded_id: Option<String> = None;
-    {
-        let key_hash = key_hash_from_any_format_pk(&param.private_key)?;
-        let map = KEYSTORE_MAP.read();
-        if let Some(founded) = map
-            .values()
-            .find(|keystore| keystore.key_hash() == key_hash)
-        {
-            founded_id = Some(founded.id());
-        }
+        let typed_pk = if param.path.is_empty() {
+            guard
+                .keystore_mut()
+                .export_private_key(&param.chain_type, &param.main_address, None)?
+        } else {
+            // get the relative path
+            let mut relative_path: &str = param.path.as_str();
+            if param.path.starts_with("m") {
+                let acc_path = get_account_path(relative_path)?;
+                relative_path = &relative_path[acc_path.len()..];
+            }
+
+            if relative_path.starts_with("/") {
+                relative_path = &relative_path[1..];
+            }
+
+            guard.keystore_mut().export_private_key(
+                &param.chain_type,
+                &param.main_address,
+                Some(relative_path),
+            )?
+        };
+
+        // private_key prefix is only about chain type and network
+        let coin_info = coin_info_from_param(&param.chain_type, &param.network, "")?;
+        let value = if param.chain_type.as_str() == "TRON" {
+            hex::encode(Zeroizing::new(typed_pk.to_bytes()).as_slice())
+        } else {
+            typed_pk.fmt(&coin_info)?
+        };
+
+        let export_result = KeystoreCommonExportResult {
+            id: guard.keystore().id(),
+            r#type: KeyType::PrivateKey as i32,
+            value,
+            passphrase: "".to_owned(),
+        };
+
+        self.encode_message(export_result)
     }
 
-    if founded_id.is_some() && !param.overwrite {
-        return Err(format_err!("{}", "address_already_exist"));
+    pub fn keystore_common_verify(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: WalletKeyParam = WalletKeyParam::decode(data).expect("keystore_common_delete");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
+
+        self.verify_password(keystore, &param.password)?;
+        let rsp = Response {
+            is_success: true,
+            ..Response::default()
+        };
+        self.encode_message(rsp)
     }
 
-    let pk_bytes = key_data_from_any_format_pk(&param.private_key)?;
-    let private_key = hex::encode(pk_bytes);
-    let pk_store =
-        PrivateKeystore::from_private_key(&private_key, &param.password, Source::Private);
+    pub fn keystore_common_delete(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: WalletKeyParam = WalletKeyParam::decode(data).expect("keystore_common_delete");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
 
-    let mut keystore = Keystore::PrivateKey(pk_store);
+        self.verify_password(keystore, &param.password)?;
+        self.delete_keystore_file(&param.id)?;
+        map.remove(&param.id);
+        self.close_keystore_sessions(&param.id);
 
-    if let Some(exist_kid) = founded_id {
-        keystore.set_id(&exist_kid)
+        let rsp = Response {
+            is_success: true,
+            ..Response::default()
+        };
+        self.encode_message(rsp)
     }
 
-    flush_keystore(&keystore)?;
+    pub fn keystore_common_change_password(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: KeystoreCommonChangePasswordParam =
+            KeystoreCommonChangePasswordParam::decode(data)
+                .expect("keystore_common_change_password");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
 
-    let meta = keystore.meta();
-    let wallet = WalletResult {
-        id: keystore.id(),
-        name: meta.name.to_owned(),
-        source: "PRIVATE".to_owned(),
-        accounts: vec![],
-        created_at: meta.timestamp.clone(),
-    };
-    let ret = encode_message(wallet)?;
-    cache_keystore(keystore);
-    Ok(ret)
-}
+        let derived_key = self.derive_key(keystore, &param.old_password)?;
+        // the cached keystore is replaced only after the new one is persisted
+        let mut changed = Keystore::from_json(&keystore.to_json())?;
+        changed.change_password(
+            &derived_key,
+            &param.new_password,
+            &param.password_hint,
+            None,
+        )?;
+        self.flush_keystore(&changed)?;
+        *keystore = changed;
+        self.close_keystore_sessions(&param.id);
+        self.delete_keystore_backup(&param.id)?;
 
-#[deprecated(
-    since = "2.5.1",
-    note = "Please use the export_private_key function instead"
-)]
-pub(crate) fn private_key_store_export(data: &[u8]) -> Result<Vec<u8>> {
-    let param: PrivateKeyStoreExportParam =
-        PrivateKeyStoreExportParam::decode(data).expect("private_key_store_export");
-    let mut map = KEYSTORE_MAP.write();
-    let keystore: &mut Keystore = match map.get_mut(&param.id) {
-        Some(keystore) => Ok(keystore),
-        _ => Err(format_err!("{}", "wallet_not_found")),
-    }?;
-
-    let guard = KeystoreGuard::unlock_by_password(keystore, &param.password)?;
-
-    let pk_hex = guard.keystore().export()?;
-
-    // private_key prefix is only about chain type and network
-    let coin_info = coin_info_from_param(&param.chain_type, &param.network, "")?;
-    let value = if param.chain_type.as_str() == "TRON" {
-        Ok(pk_hex.to_string())
-    } else {
-        let bytes = hex::decode(pk_hex.to_string())?;
-        let typed_pk = TypedPrivateKey::from_slice(CurveType::SECP256k1, &bytes)?;
-        typed_pk.fmt(&coin_info)
-    }?;
-
-    let export_result = KeystoreCommonExportResult {
-        id: guard.keystore().id(),
-        r#type: KeyType::PrivateKey as i32,
-        value,
-    };
+        let rsp = Response {
+            is_success: true,
+            ..Response::default()
+        };
+        self.encode_message(rsp)
+    }
 
-    encode_message(export_result)
-}
+    pub fn keystore_common_unlock_wait(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: KeystoreCommonUnlockWaitParam =
+            KeystoreCommonUnlockWaitParam::decode(data).expect("keystore_common_unlock_wait");
+        let map = self.fresh_keystore_map()?.read();
+        let keystore: &Keystore = match map.get(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
 
-pub(crate) fn export_private_key(data: &[u8]) -> Result<Vec<u8>> {
-    let param: ExportPrivateKeyParam =
-        ExportPrivateKeyParam::decode(data).expect("export_private_key");
-    let mut map = KEYSTORE_MAP.write();
-    let keystore: &mut Keystore = match map.get_mut(&param.id) {
-        Some(keystore) => Ok(keystore),
-        _ => Err(format_err!("{}", "wallet_not_found")),
-    }?;
-
-    let mut guard = KeystoreGuard::unlock_by_password(keystore, &param.password)?;
-
-    let pk_hex = if param.path.is_empty() {
-        guard
-            .keystore_mut()
-            .export_private_key(&param.chain_type, &param.main_address, None)?
-    } else {
-        // get the relative path
-        let mut relative_path: &str = param.path.as_str();
-        if param.path.starts_with("m") {
-            let acc_path = get_account_path(relative_path)?;
-            relative_path = &relative_path[acc_path.len()..];
-        }
+        let max_failures = self.config().max_unlock_failures;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let ret = UnlockWaitResult {
+            remaining_seconds: keystore.unlock_wait(now, max_failures),
+            failed_unlocks: keystore.failed_unlocks(),
+            locked_out: keystore.is_locked_out(max_failures),
+        };
+        self.encode_message(ret)
+    }
 
-        if relative_path.starts_with("/") {
-            relative_path = &relative_path[1..];
-        }
+    pub fn keystore_common_remove_account(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: KeystoreCommonAccountParam =
+            KeystoreCommonAccountParam::decode(data).expect("keystore_common_remove_account");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
 
-        guard.keystore_mut().export_private_key(
-            &param.chain_type,
-            &param.main_address,
-            Some(relative_path),
-        )?
-    };
+        // the cached keystore is replaced only after the change is persisted
+        let mut changed = keystore.locked_copy();
+        changed.remove_account(&param.chain_type, &param.address, &param.path)?;
+        self.flush_keystore(&changed)?;
+        *keystore = changed;
 
-    // private_key prefix is only about chain type and network
-    let coin_info = coin_info_from_param(&param.chain_type, &param.network, "")?;
-    let value = if param.chain_type.as_str() == "TRON" {
-        Ok(pk_hex.to_string())
-    } else {
-        let bytes = hex::decode(pk_hex.to_string())?;
-        let typed_pk = TypedPrivateKey::from_slice(CurveType::SECP256k1, &bytes)?;
-        typed_pk.fmt(&coin_info)
-    }?;
-
-    let export_result = KeystoreCommonExportResult {
-        id: guard.keystore().id(),
-        r#type: KeyType::PrivateKey as i32,
-        value,
-    };
+        let rsp = Response {
+            is_success: true,
+            ..Response::default()
+        };
+        self.encode_message(rsp)
+    }
 
-    encode_message(export_result)
-}
+    pub fn keystore_common_hide_account(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: KeystoreCommonAccountParam =
+            KeystoreCommonAccountParam::decode(data).expect("keystore_common_hide_account");
+        self.set_account_hidden(&param, true)
+    }
 
-pub(crate) fn keystore_common_verify(data: &[u8]) -> Result<Vec<u8>> {
-    let param: WalletKeyParam = WalletKeyParam::decode(data).expect("keystore_common_delete");
-    let map = KEYSTORE_MAP.read();
-    let keystore: &Keystore = match map.get(&param.id) {
-        Some(keystore) => Ok(keystore),
-        _ => Err(format_err!("{}", "wallet_not_found")),
-    }?;
+    pub fn keystore_common_unhide_account(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: KeystoreCommonAccountParam =
+            KeystoreCommonAccountParam::decode(data).expect("keystore_common_unhide_account");
+        self.set_account_hidden(&param, false)
+    }
+
+    fn set_account_hidden(
+        &self,
+        param: &KeystoreCommonAccountParam,
+        hidden: bool,
+    ) -> Result<Vec<u8>> {
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
+
+        let mut changed = keystore.locked_copy();
+        changed.set_account_hidden(&param.chain_type, &param.address, &param.path, hidden)?;
+        self.flush_keystore(&changed)?;
+        *keystore = changed;
 
-    if keystore.verify_password(&param.password) {
         let rsp = Response {
             is_success: true,
-            error: "".to_owned(),
+            ..Response::default()
         };
-        encode_message(rsp)
-    } else {
-        Err(format_err!("{}", "password_incorrect"))
+        self.encode_message(rsp)
     }
-}
 
-pub(crate) fn keystore_common_delete(data: &[u8]) -> Result<Vec<u8>> {
-    let param: WalletKeyParam = WalletKeyParam::decode(data).expect("keystore_common_delete");
-    let mut map = KEYSTORE_MAP.write();
-    let keystore: &Keystore = match map.get(&param.id) {
-        Some(keystore) => Ok(keystore),
-        _ => Err(format_err!("{}", "wallet_not_found")),
-    }?;
+    pub fn keystore_update_meta(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: KeystoreUpdateMetaParam =
+            KeystoreUpdateMetaParam::decode(data).expect("keystore_update_meta");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
 
-    if keystore.verify_password(&param.password) {
-        delete_keystore_file(&param.id)?;
-        map.remove(&param.id);
+        // the cached keystore is replaced only after the change is persisted
+        let mut changed = keystore.locked_copy();
+        if !param.name.is_empty() {
+            changed.set_name(&param.name);
+        }
+        if param.update_password_hint {
+            changed.set_password_hint(&param.password_hint);
+        }
+        self.flush_keystore(&changed)?;
+        *keystore = changed;
 
         let rsp = Response {
             is_success: true,
-            error: "".to_owned(),
+            ..Response::default()
         };
-        encode_message(rsp)
-    } else {
-        Err(format_err!("{}", "password_incorrect"))
+        self.encode_message(rsp)
     }
-}
 
-pub(crate) fn keystore_common_exists(data: &[u8]) -> Result<Vec<u8>> {
-    let param: KeystoreCommonExistsParam =
-        KeystoreCommonExistsParam::decode(data).expect("keystore_common_exists params");
-    let key_hash: String;
-    if param.r#type == KeyType::Mnemonic as i32 {
-        key_hash = key_hash_from_mnemonic(&param.value)?;
-    } else {
-        key_hash = key_hash_from_any_format_pk(&param.value)?;
-    }
-    let map = &mut KEYSTORE_MAP.write();
-
-    let founded: Option<&Keystore> = map
-        .values()
-        .find(|keystore| keystore.key_hash() == key_hash);
-    let result: KeystoreCommonExistsResult;
-    if let Some(ks) = founded {
-        result = KeystoreCommonExistsResult {
-            is_exists: true,
-            id: ks.id(),
+    pub fn account_update_label(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: AccountUpdateLabelParam =
+            AccountUpdateLabelParam::decode(data).expect("account_update_label");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
+
+        let mut changed = keystore.locked_copy();
+        changed.update_account_label(
+            &param.chain_type,
+            &param.address,
+            &param.label,
+            &param.tags,
+        )?;
+        self.flush_keystore(&changed)?;
+        *keystore = changed;
+
+        let rsp = Response {
+            is_success: true,
+            ..Response::default()
+        };
+        self.encode_message(rsp)
+    }
+
+    pub fn keystore_common_exists(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: KeystoreCommonExistsParam =
+            KeystoreCommonExistsParam::decode(data).expect("keystore_common_exists params");
+        let key_hash: String;
+        if param.r#type == KeyType::Mnemonic as i32 {
+            key_hash = key_hash_from_mnemonic_with_passphrase(&param.value, &param.passphrase)?;
+        } else {
+            key_hash = key_hash_from_any_format_pk(&param.value)?;
         }
-    } else {
-        result = KeystoreCommonExistsResult {
-            is_exists: false,
-            id: "".to_owned(),
+        let map = &mut self.fresh_keystore_map()?.write();
+
+        let founded: Option<&Keystore> = map
+            .values()
+            .find(|keystore| keystore.key_hash() == key_hash);
+        let result: KeystoreCommonExistsResult;
+        if let Some(ks) = founded {
+            result = KeystoreCommonExistsResult {
+                is_exists: true,
+                id: ks.id(),
+            }
+        } else {
+            result = KeystoreCommonExistsResult {
+                is_exists: false,
+                id: "".to_owned(),
+            }
         }
+        self.encode_message(result)
     }
-    encode_message(result)
-}
 
-pub(crate) fn keystore_common_accounts(data: &[u8]) -> Result<Vec<u8>> {
-    let param: KeystoreCommonAccountsParam =
-        KeystoreCommonAccountsParam::decode(data).expect("keystore_common_accounts params");
-    let map = KEYSTORE_MAP.read();
-    let keystore: &Keystore = match map.get(&param.id) {
-        Some(keystore) => Ok(keystore),
-        _ => Err(format_err!("{}", "wallet_not_found")),
-    }?;
-
-    let mut accounts: Vec<AccountResponse> = vec![];
-    for account in keystore.accounts() {
-        let enc_xpub = if account.ext_pub_key.is_empty() {
-            "".to_string()
+    pub fn keystore_common_accounts(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: KeystoreCommonAccountsParam =
+            KeystoreCommonAccountsParam::decode(data).expect("keystore_common_accounts params");
+        let map = self.fresh_keystore_map()?.read();
+        let keystore: &Keystore = match map.get(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
+
+        let mut accounts: Vec<AccountResponse> = vec![];
+        for account in keystore.accounts() {
+            if account.hidden && !param.include_hidden {
+                continue;
+            }
+            let enc_xpub = if account.ext_pub_key.is_empty() {
+                "".to_string()
+            } else {
+                self.enc_xpub(&account.ext_pub_key, &account.network)?
+            };
+            // let enc_xpub = self.enc_xpub(&account.ext_pub_key, &account.network)?;
+            let acc_rsp = AccountResponse {
+                chain_type: account.coin.to_owned(),
+                address: account.address.to_owned(),
+                path: account.derivation_path.to_owned(),
+                extended_xpub_key: enc_xpub.to_owned(),
+                label: account.label.to_owned(),
+                tags: account.tags.clone(),
+                hidden: account.hidden,
+            };
+            accounts.push(acc_rsp);
+        }
+
+        let accounts_rsp = AccountsResponse { accounts };
+        self.encode_message(accounts_rsp)
+    }
+
+    pub fn btc_fork_external_address(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: ExternalAddressParam =
+            ExternalAddressParam::decode(data).expect("btc_fork_external_address");
+        let map = self.fresh_keystore_map()?.read();
+        let keystore: &Keystore = match map.get(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
+
+        let account = if param.address.is_empty() {
+            let mut accounts = keystore
+                .accounts()
+                .iter()
+                .filter(|acc| acc.coin == param.chain_type);
+            match (accounts.next(), accounts.next()) {
+                (Some(account), None) => Some(account),
+                // several accounts of the chain, e.g. a legacy and a segwit one
+                (Some(_), Some(_)) => return Err(Error::AddressInvalid.into()),
+                _ => None,
+            }
         } else {
-            enc_xpub(&account.ext_pub_key, &account.network)?
+            keystore.account(&param.chain_type, &param.address)
+        }
+        .ok_or(tcx_chain::Error::AccountNotFound)?;
+        let external_address = match param.chain_type.as_str() {
+            "BITCOINCASH" => {
+                ExternalAddress::from_account::<BchAddress>(account, param.external_idx)
+            }
+            "LITECOIN" => {
+                ExternalAddress::from_account::<BtcForkAddress>(account, param.external_idx)
+            }
+            _ => Err(Error::UnsupportedChain.into()),
+        }?;
+
+        let ret = ExternalAddressResult {
+            address: external_address.address,
+            derived_path: external_address.derived_path,
+            r#type: external_address.addr_type,
         };
-        // let enc_xpub = enc_xpub(&account.ext_pub_key, &account.network)?;
-        let acc_rsp = AccountResponse {
-            chain_type: account.coin.to_owned(),
-            address: account.address.to_owned(),
-            path: account.derivation_path.to_owned(),
-            extended_xpub_key: enc_xpub.to_owned(),
+        self.encode_message(ret)
+    }
+
+    pub fn hd_store_extended_public_key(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: HdStoreExtendedPublicKeyParam =
+            HdStoreExtendedPublicKeyParam::decode(data).expect("hd_store_extended_public_key");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
+
+        self.verify_password(keystore, &param.password)?;
+
+        let account = keystore
+            .account(&param.chain_type, &param.address)
+            .ok_or(tcx_chain::Error::AccountNotFound)?;
+        tcx_ensure!(
+            !account.ext_pub_key.is_empty(),
+            tcx_chain::Error::CannotDeriveKey
+        );
+
+        let xpk = Bip32DeterministicPublicKey::from_hex(&account.ext_pub_key)?;
+        let coin_info = coin_info_from_param(&account.coin, &account.network, &account.seg_wit)?;
+        let extended_public_key = BtcForkAddress::extended_public_key(&xpk, &coin_info)?;
+
+        let ret = HdStoreExtendedPublicKeyResponse {
+            extended_public_key,
         };
-        accounts.push(acc_rsp);
+        self.encode_message(ret)
     }
 
-    let accounts_rsp = AccountsResponse { accounts };
-    encode_message(accounts_rsp)
-}
+    pub fn get_derived_key(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: WalletKeyParam = WalletKeyParam::decode(data).expect("get_derived_key");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
 
-pub(crate) fn sign_tx(data: &[u8]) -> Result<Vec<u8>> {
-    let param: SignParam = SignParam::decode(data).expect("SignTxParam");
+        let derived_key = self.derive_key(keystore, &param.password)?;
+        let ret = DerivedKeyResult {
+            id: param.id.to_owned(),
+            derived_key: derived_key.to_string(),
+        };
+        self.encode_message(ret)
+    }
 
-    let mut map = KEYSTORE_MAP.write();
-    let keystore: &mut Keystore = match map.get_mut(&param.id) {
-        Some(keystore) => Ok(keystore),
-        _ => Err(format_err!("{}", "wallet_not_found")),
-    }?;
+    pub fn cache_derived_key(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: CacheDerivedKeyParam =
+            CacheDerivedKeyParam::decode(data).expect("cache_derived_key");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
 
-    let mut guard = KeystoreGuard::unlock_by_password(keystore, &param.password)?;
-    match param.chain_type.as_str() {
-        "BITCOINCASH" | "LITECOIN" => sign_btc_fork_transaction(&param, guard.keystore_mut()),
-        "TRON" => sign_tron_tx(&param, guard.keystore_mut()),
-        "NERVOS" => sign_nervos_ckb(&param, guard.keystore_mut()),
-        _ => Err(format_err!("unsupported_chain")),
+        keystore.cache_derived_key(&param.temp_password, &param.derived_key)?;
+        let ret = DerivedKeyResult {
+            id: param.id.to_owned(),
+            derived_key: param.derived_key.to_owned(),
+        };
+        self.encode_message(ret)
     }
-}
 
-pub(crate) fn sign_btc_fork_transaction(
-    param: &SignParam,
-    keystore: &mut Keystore,
-) -> Result<Vec<u8>> {
-    let input: BtcForkTxInput =
-        BtcForkTxInput::decode(&param.input.as_ref().expect("tx_input").value.clone())
-            .expect("BitcoinForkTransactionInput");
-    let coin = coin_info_from_param(&param.chain_type, &input.network, &input.seg_wit)?;
-
-    let signed_tx: BtcForkSignedTxOutput = if param.chain_type.as_str() == "BITCOINCASH" {
-        if !BchAddress::is_valid(&input.to, &coin) {
-            return Err(format_err!("address_invalid"));
-        }
-        let tran = BchTransaction::new(input, coin);
-        keystore.sign_transaction(&param.chain_type, &param.address, &tran)?
-    } else if input.seg_wit.as_str() != "NONE" {
-        if !BtcForkAddress::is_valid(&input.to, &coin) {
-            return Err(format_err!("address_invalid"));
-        }
-        let tran = BtcForkSegWitTransaction::new(input, coin);
-        keystore.sign_transaction(&param.chain_type, &param.address, &tran)?
-    } else {
-        if !BtcForkAddress::is_valid(&input.to, &coin) {
-            return Err(format_err!("address_invalid"));
+    pub fn verify_derived_key(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: VerifyDerivedKeyParam =
+            VerifyDerivedKeyParam::decode(data).expect("verify_derived_key");
+        let map = self.fresh_keystore_map()?.read();
+        let keystore: &Keystore = match map.get(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
+
+        tcx_ensure!(
+            keystore.verify_derived_key(&param.derived_key),
+            tcx_crypto::Error::DerivedKeyIncorrect
+        );
+        let ret = DerivedKeyResult {
+            id: param.id.to_owned(),
+            derived_key: param.derived_key.to_owned(),
+        };
+        self.encode_message(ret)
+    }
+
+    pub fn unlock_session(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: UnlockSessionParam = UnlockSessionParam::decode(data).expect("unlock_session");
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
+
+        let derived_key = self.derive_key(keystore, &param.password)?;
+        let (session_token, expires_at) = self.open_session(
+            keystore,
+            derived_key,
+            param.ttl,
+            &param.chain_type,
+            &param.address,
+            param.max_uses,
+        )?;
+        let ret = UnlockSessionResult {
+            id: param.id.to_owned(),
+            session_token,
+            expires_at,
+        };
+        self.encode_message(ret)
+    }
+
+    pub fn lock_session(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: LockSessionParam = LockSessionParam::decode(data).expect("lock_session");
+        let rsp = Response {
+            is_success: self.close_session(&param.session_token),
+            ..Response::default()
+        };
+        self.encode_message(rsp)
+    }
+
+    pub fn sign_tx(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: SignParam = SignParam::decode(data).expect("SignTxParam");
+
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
+
+        // fail before unlocking, the signers hide the error of the keystore
+        tcx_ensure!(
+            !keystore.is_watch_only(),
+            tcx_chain::Error::WatchOnlyCannotSign
+        );
+
+        let mut guard = self.unlock_keystore(
+            keystore,
+            &param.password,
+            &param.session_token,
+            &[param.chain_type.as_str()],
+            Some(&param.address),
+        )?;
+        match param.chain_type.as_str() {
+            "BITCOINCASH" | "LITECOIN" => {
+                self.sign_btc_fork_transaction(&param, guard.keystore_mut())
+            }
+            "TRON" => self.sign_tron_tx(&param, guard.keystore_mut()),
+            "NERVOS" => self.sign_nervos_ckb(&param, guard.keystore_mut()),
+            _ => Err(Error::UnsupportedChain.into()),
         }
-        let tran = BtcForkTransaction::new(input, coin);
-        keystore.sign_transaction(&param.chain_type, &param.address, &tran)?
-    };
-    encode_message(signed_tx)
-}
+    }
 
-pub(crate) fn sign_nervos_ckb(param: &SignParam, keystore: &mut Keystore) -> Result<Vec<u8>> {
-    let input: CkbTxInput =
-        CkbTxInput::decode(&param.input.as_ref().expect("tx_iput").value.clone())
-            .expect("CkbTxInput");
-    let signed_tx = keystore.sign_transaction(&param.chain_type, &param.address, &input)?;
-    encode_message(signed_tx)
-}
+    pub(crate) fn sign_btc_fork_transaction(
+        &self,
+        param: &SignParam,
+        keystore: &mut Keystore,
+    ) -> Result<Vec<u8>> {
+        let input: BtcForkTxInput =
+            BtcForkTxInput::decode(&param.input.as_ref().expect("tx_input").value.clone())
+                .expect("BitcoinForkTransactionInput");
+        let coin = coin_info_from_param(&param.chain_type, &input.network, &input.seg_wit)?;
+
+        let signed_tx: BtcForkSignedTxOutput = if param.chain_type.as_str() == "BITCOINCASH" {
+            if !BchAddress::is_valid(&input.to, &coin) {
+                return Err(Error::AddressInvalid.into());
+            }
+            let tran = BchTransaction::new(input, coin);
+            keystore.sign_transaction(&param.chain_type, &param.address, &tran)?
+        } else if input.seg_wit.as_str() != "NONE" {
+            if !BtcForkAddress::is_valid(&input.to, &coin) {
+                return Err(Error::AddressInvalid.into());
+            }
+            let tran = BtcForkSegWitTransaction::new(input, coin);
+            keystore.sign_transaction(&param.chain_type, &param.address, &tran)?
+        } else {
+            if !BtcForkAddress::is_valid(&input.to, &coin) {
+                return Err(Error::AddressInvalid.into());
+            }
+            let tran = BtcForkTransaction::new(input, coin);
+            keystore.sign_transaction(&param.chain_type, &param.address, &tran)?
+        };
+        self.encode_message(signed_tx)
+    }
 
-pub(crate) fn sign_tron_tx(param: &SignParam, keystore: &mut Keystore) -> Result<Vec<u8>> {
-    let input: TronTxInput =
-        TronTxInput::decode(&param.input.as_ref().expect("tx_input").value.clone())
-            .expect("TronTxInput");
-    let signed_tx = keystore.sign_transaction(&param.chain_type, &param.address, &input)?;
+    /// the unsigned transaction of a btc fork account, no password is needed because no key is
+    /// used, so a watch only keystore can prepare the transaction signed elsewhere
+    pub fn btc_fork_prepare_tx(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: SignParam = SignParam::decode(data).expect("btc_fork_prepare_tx");
 
-    encode_message(signed_tx)
-}
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
+        tcx_ensure!(
+            param.chain_type == "BITCOINCASH" || param.chain_type == "LITECOIN",
+            Error::UnsupportedChain
+        );
+        keystore
+            .account(&param.chain_type, &param.address)
+            .ok_or(tcx_chain::Error::AccountNotFound)?;
+
+        let input: BtcForkTxInput =
+            BtcForkTxInput::decode(&param.input.as_ref().expect("tx_input").value.clone())
+                .expect("BitcoinForkTransactionInput");
+        let coin = coin_info_from_param(&param.chain_type, &input.network, &input.seg_wit)?;
+
+        let unsigned_tx = if param.chain_type.as_str() == "BITCOINCASH" {
+            if !BchAddress::is_valid(&input.to, &coin) {
+                return Err(Error::AddressInvalid.into());
+            }
+            let tran = BchTransaction::new(input, coin);
+            let change = tran.change_script_pubkey(keystore, &param.chain_type, &param.address)?;
+            tran.unsigned_transaction(change)?
+        } else if input.seg_wit.as_str() != "NONE" {
+            if !BtcForkAddress::is_valid(&input.to, &coin) {
+                return Err(Error::AddressInvalid.into());
+            }
+            let tran = BtcForkSegWitTransaction::new(input, coin);
+            let change = tran.change_script_pubkey(keystore, &param.chain_type, &param.address)?;
+            tran.unsigned_transaction(change)?
+        } else {
+            if !BtcForkAddress::is_valid(&input.to, &coin) {
+                return Err(Error::AddressInvalid.into());
+            }
+            let tran = BtcForkTransaction::new(input, coin);
+            let change = tran.change_script_pubkey(keystore, &param.chain_type, &param.address)?;
+            tran.unsigned_transaction(change)?
+        };
+        self.encode_message(unsigned_tx)
+    }
 
-pub(crate) fn tron_sign_message(data: &[u8]) -> Result<Vec<u8>> {
-    let param: SignParam = SignParam::decode(data).expect("SignParam");
-
-    let mut map = KEYSTORE_MAP.write();
-    let keystore: &mut Keystore = match map.get_mut(&param.id) {
-        Some(keystore) => Ok(keystore),
-        _ => Err(format_err!("{}", "wallet_not_found")),
-    }?;
-
-    let mut guard = KeystoreGuard::unlock_by_password(keystore, &param.password)?;
-    let input: TronMessageInput =
-        TronMessageInput::decode(param.input.expect("TronMessageInput").value.clone())
-            .expect("TronMessageInput");
-    let signed_tx = guard
-        .keystore_mut()
-        .sign_message(&param.chain_type, &param.address, &input)?;
-    encode_message(signed_tx)
-}
+    pub(crate) fn sign_nervos_ckb(
+        &self,
+        param: &SignParam,
+        keystore: &mut Keystore,
+    ) -> Result<Vec<u8>> {
+        let input: CkbTxInput =
+            CkbTxInput::decode(&param.input.as_ref().expect("tx_iput").value.clone())
+                .expect("CkbTxInput");
+        let signed_tx = keystore.sign_transaction(&param.chain_type, &param.address, &input)?;
+        self.encode_message(signed_tx)
+    }
 
-pub(crate) fn unlock_then_crash(data: &[u8]) -> Result<Vec<u8>> {
-    let param: WalletKeyParam = WalletKeyParam::decode(data).unwrap();
-    let mut map = KEYSTORE_MAP.write();
-    let keystore: &mut Keystore = match map.get_mut(&param.id) {
-        Some(keystore) => Ok(keystore),
-        _ => Err(format_err!("{}", "wallet_not_found")),
-    }?;
+    pub(crate) fn sign_tron_tx(
+        &self,
+        param: &SignParam,
+        keystore: &mut Keystore,
+    ) -> Result<Vec<u8>> {
+        let input: TronTxInput =
+            TronTxInput::decode(&param.input.as_ref().expect("tx_input").value.clone())
+                .expect("TronTxInput");
+        let signed_tx = keystore.sign_transaction(&param.chain_type, &param.address, &input)?;
+
+        self.encode_message(signed_tx)
+    }
 
-    let _guard = KeystoreGuard::unlock_by_password(keystore, &param.password)?;
-    panic!("test_unlock_then_crash");
+    pub fn tron_sign_message(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: SignParam = SignParam::decode(data).expect("SignParam");
+
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
+
+        // fail before unlocking, the signers hide the error of the keystore
+        tcx_ensure!(
+            !keystore.is_watch_only(),
+            tcx_chain::Error::WatchOnlyCannotSign
+        );
+
+        let mut guard = self.unlock_keystore(
+            keystore,
+            &param.password,
+            &param.session_token,
+            &[param.chain_type.as_str()],
+            Some(&param.address),
+        )?;
+        let input: TronMessageInput =
+            TronMessageInput::decode(param.input.expect("TronMessageInput").value.clone())
+                .expect("TronMessageInput");
+        let signed_tx =
+            guard
+                .keystore_mut()
+                .sign_message(&param.chain_type, &param.address, &input)?;
+        self.encode_message(signed_tx)
+    }
+
+    pub(crate) fn unlock_then_crash(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let param: WalletKeyParam = WalletKeyParam::decode(data).unwrap();
+        let mut map = self.fresh_keystore_map()?.write();
+        let keystore: &mut Keystore = match map.get_mut(&param.id) {
+            Some(keystore) => Ok(keystore),
+            _ => Err(Error::WalletNotFound),
+        }?;
+
+        let _guard = self.unlock_by_password(keystore, &param.password)?;
+        panic!("test_unlock_then_crash");
+    }
 }
@@ -0,0 +1,338 @@
+//! `call_tcx_api_json` support
+//!
+//! Params and results use the proto3 JSON mapping of the messages: lowerCamelCase field names,
+//! enums by name, int64 as string and `Any` as an object with an extra `@type` field.
+
+use failure::ResultExt;
+use prost::Message;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use tcx_btc_fork::{BtcForkSignedTxOutput, BtcForkTxInput, BtcForkUnsignedTxOutput};
+use tcx_ckb::{CkbTxInput, CkbTxOutput};
+use tcx_tron::transaction::{TronMessageInput, TronMessageOutput, TronTxInput, TronTxOutput};
+
+use crate::api::{
+    AccountUpdateLabelParam, AccountsResponse, CacheDerivedKeyParam, DerivedKeyResult,
+    ExportPrivateKeyParam, ExternalAddressParam, ExternalAddressResult, HdStoreCreateParam,
+    HdStoreExtendedPublicKeyParam, HdStoreExtendedPublicKeyResponse, HdStoreImportParam,
+    InitTokenCoreXParam, KeystoreCommonAccountParam, KeystoreCommonAccountsParam,
+    KeystoreCommonChangePasswordParam, KeystoreCommonDeriveParam, KeystoreCommonExistsParam,
+    KeystoreCommonExistsResult, KeystoreCommonExportResult, KeystoreCommonUnlockWaitParam,
+    KeystoreUpdateMetaParam, LockSessionParam, PrivateKeyStoreExportParam,
+    PrivateKeyStoreImportParam, Response, ScanKeystoresResult, SignParam, Slip39ExportParam,
+    Slip39ExportResult, Slip39ImportParam, UnlockSessionParam, UnlockSessionResult,
+    UnlockWaitResult, VerifyDerivedKeyParam, WalletKeyParam, WalletResult,
+    WatchOnlyStoreImportParam,
+};
+use crate::call_method;
+use crate::error_handling::{error_response, Error, Result};
+use crate::handler::encode_message;
+
+#[derive(Deserialize)]
+struct JsonAction {
+    method: String,
+    #[serde(default)]
+    param: Value,
+}
+
+macro_rules! json_messages {
+    ($($name:expr => $message:ty,)*) => {
+        fn message_from_json(type_name: &str, value: Value) -> Result<Vec<u8>> {
+            match type_name {
+                $($name => {
+                    let message: $message =
+                        serde_json::from_value(value).context(Error::InvalidJson)?;
+                    encode_message(message)
+                })*
+                _ => Err(Error::UnsupportedMessageType.into()),
+            }
+        }
+
+        fn message_to_json(type_name: &str, data: &[u8]) -> Result<Value> {
+            match type_name {
+                $($name => Ok(serde_json::to_value(<$message>::decode(data)?)?),)*
+                _ => Err(Error::UnsupportedMessageType.into()),
+            }
+        }
+    };
+}
+
+json_messages! {
+    "api.Response" => Response,
+    "api.InitTokenCoreXParam" => InitTokenCoreXParam,
+    "api.ScanKeystoresResult" => ScanKeystoresResult,
+    "api.HdStoreCreateParam" => HdStoreCreateParam,
+    "api.HdStoreImportParam" => HdStoreImportParam,
+    "api.WalletResult" => WalletResult,
+    "api.WalletKeyParam" => WalletKeyParam,
+    "api.KeystoreCommonDeriveParam" => KeystoreCommonDeriveParam,
+    "api.AccountsResponse" => AccountsResponse,
+    "api.KeystoreCommonExportResult" => KeystoreCommonExportResult,
+    "api.Slip39ExportParam" => Slip39ExportParam,
+    "api.Slip39ExportResult" => Slip39ExportResult,
+    "api.Slip39ImportParam" => Slip39ImportParam,
+    "api.PrivateKeyStoreImportParam" => PrivateKeyStoreImportParam,
+    "api.PrivateKeyStoreExportParam" => PrivateKeyStoreExportParam,
+    "api.ExportPrivateKeyParam" => ExportPrivateKeyParam,
+    "api.WatchOnlyStoreImportParam" => WatchOnlyStoreImportParam,
+    "api.KeystoreCommonExistsParam" => KeystoreCommonExistsParam,
+    "api.KeystoreCommonExistsResult" => KeystoreCommonExistsResult,
+    "api.KeystoreCommonAccountsParam" => KeystoreCommonAccountsParam,
+    "api.KeystoreCommonChangePasswordParam" => KeystoreCommonChangePasswordParam,
+    "api.KeystoreCommonUnlockWaitParam" => KeystoreCommonUnlockWaitParam,
+    "api.UnlockWaitResult" => UnlockWaitResult,
+    "api.KeystoreCommonAccountParam" => KeystoreCommonAccountParam,
+    "api.KeystoreUpdateMetaParam" => KeystoreUpdateMetaParam,
+    "api.AccountUpdateLabelParam" => AccountUpdateLabelParam,
+    "api.SignParam" => SignParam,
+    "api.ExternalAddressParam" => ExternalAddressParam,
+    "api.ExternalAddressResult" => ExternalAddressResult,
+    "api.CacheDerivedKeyParam" => CacheDerivedKeyParam,
+    "api.VerifyDerivedKeyParam" => VerifyDerivedKeyParam,
+    "api.DerivedKeyResult" => DerivedKeyResult,
+    "api.HdStoreExtendedPublicKeyParam" => HdStoreExtendedPublicKeyParam,
+    "api.HdStoreExtendedPublicKeyResponse" => HdStoreExtendedPublicKeyResponse,
+    "api.UnlockSessionParam" => UnlockSessionParam,
+    "api.UnlockSessionResult" => UnlockSessionResult,
+    "api.LockSessionParam" => LockSessionParam,
+    "transaction.BtcForkTxInput" => BtcForkTxInput,
+    "transaction.BtcForkSignedTxOutput" => BtcForkSignedTxOutput,
+    "transaction.BtcForkUnsignedTxOutput" => BtcForkUnsignedTxOutput,
+    "transaction.TronTxInput" => TronTxInput,
+    "transaction.TronTxOutput" => TronTxOutput,
+    "transaction.TronMessageInput" => TronMessageInput,
+    "transaction.TronMessageOutput" => TronMessageOutput,
+    "transaction.CkbTxInput" => CkbTxInput,
+    "transaction.CkbTxOutput" => CkbTxOutput,
+}
+
+/// the param and result message of a method, `None` means there is no message
+fn method_messages(
+    method: &str,
+    param: &Value,
+) -> Result<(Option<&'static str>, Option<&'static str>)> {
+    let messages = match method {
+        "init_token_core_x" => (Some("api.InitTokenCoreXParam"), None),
+        "scan_keystores" => (None, Some("api.ScanKeystoresResult")),
+        "hd_store_create" => (Some("api.HdStoreCreateParam"), Some("api.WalletResult")),
+        "hd_store_import" => (Some("api.HdStoreImportParam"), Some("api.WalletResult")),
+        "hd_store_export" | "export_mnemonic" => (
+            Some("api.WalletKeyParam"),
+            Some("api.KeystoreCommonExportResult"),
+        ),
+        "hd_store_export_slip39" => (
+            Some("api.Slip39ExportParam"),
+            Some("api.Slip39ExportResult"),
+        ),
+        "hd_store_import_slip39" => (Some("api.Slip39ImportParam"), Some("api.WalletResult")),
+        "keystore_common_derive" => (
+            Some("api.KeystoreCommonDeriveParam"),
+            Some("api.AccountsResponse"),
+        ),
+        "private_key_store_import" => (
+            Some("api.PrivateKeyStoreImportParam"),
+            Some("api.WalletResult"),
+        ),
+        "private_key_store_export" => (
+            Some("api.PrivateKeyStoreExportParam"),
+            Some("api.KeystoreCommonExportResult"),
+        ),
+        "export_private_key" => (
+            Some("api.ExportPrivateKeyParam"),
+            Some("api.KeystoreCommonExportResult"),
+        ),
+        "watch_only_store_import" => (
+            Some("api.WatchOnlyStoreImportParam"),
+            Some("api.WalletResult"),
+        ),
+        "keystore_common_verify" | "keystore_common_delete" => {
+            (Some("api.WalletKeyParam"), Some("api.Response"))
+        }
+        "keystore_common_change_password" => (
+            Some("api.KeystoreCommonChangePasswordParam"),
+            Some("api.Response"),
+        ),
+        "keystore_common_unlock_wait" => (
+            Some("api.KeystoreCommonUnlockWaitParam"),
+            Some("api.UnlockWaitResult"),
+        ),
+        "keystore_common_remove_account"
+        | "keystore_common_hide_account"
+        | "keystore_common_unhide_account" => {
+            (Some("api.KeystoreCommonAccountParam"), Some("api.Response"))
+        }
+        "keystore_update_meta" => (Some("api.KeystoreUpdateMetaParam"), Some("api.Response")),
+        "account_update_label" => (Some("api.AccountUpdateLabelParam"), Some("api.Response")),
+        "keystore_common_exists" => (
+            Some("api.KeystoreCommonExistsParam"),
+            Some("api.KeystoreCommonExistsResult"),
+        ),
+        "keystore_common_accounts" => (
+            Some("api.KeystoreCommonAccountsParam"),
+            Some("api.AccountsResponse"),
+        ),
+        "btc_fork_external_address" => (
+            Some("api.ExternalAddressParam"),
+            Some("api.ExternalAddressResult"),
+        ),
+        "hd_store_extended_public_key" => (
+            Some("api.HdStoreExtendedPublicKeyParam"),
+            Some("api.HdStoreExtendedPublicKeyResponse"),
+        ),
+        "get_derived_key" => (Some("api.WalletKeyParam"), Some("api.DerivedKeyResult")),
+        "cache_derived_key" => (
+            Some("api.CacheDerivedKeyParam"),
+            Some("api.DerivedKeyResult"),
+        ),
+        "verify_derived_key" => (
+            Some("api.VerifyDerivedKeyParam"),
+            Some("api.DerivedKeyResult"),
+        ),
+        "unlock_session" => (
+            Some("api.UnlockSessionParam"),
+            Some("api.UnlockSessionResult"),
+        ),
+        "lock_session" => (Some("api.LockSessionParam"), Some("api.Response")),
+        "sign_tx" => {
+            let output = match param["chainType"].as_str().unwrap_or_default() {
+                "BITCOINCASH" | "LITECOIN" => "transaction.BtcForkSignedTxOutput",
+                "TRON" => "transaction.TronTxOutput",
+                "NERVOS" => "transaction.CkbTxOutput",
+                _ => return Err(Error::UnsupportedChain.into()),
+            };
+            (Some("api.SignParam"), Some(output))
+        }
+        "btc_fork_prepare_tx" => (
+            Some("api.SignParam"),
+            Some("transaction.BtcForkUnsignedTxOutput"),
+        ),
+        "tron_sign_msg" => (Some("api.SignParam"), Some("transaction.TronMessageOutput")),
+        _ => return Err(Error::UnsupportedMethod.into()),
+    };
+    Ok(messages)
+}
+
+pub(crate) fn call_json(json: &str) -> Result<String> {
+    let action: JsonAction = serde_json::from_str(json).context(Error::InvalidJson)?;
+    let method = action.method.to_lowercase();
+    let (param_message, result_message) = method_messages(&method, &action.param)?;
+
+    let param = match param_message {
+        Some(type_name) => message_from_json(type_name, action.param)?,
+        None => vec![],
+    };
+    let reply = call_method(&method, &param)?;
+    let result = match result_message {
+        Some(type_name) => message_to_json(type_name, &reply)?,
+        None => Value::Null,
+    };
+
+    Ok(json!({ "result": result }).to_string())
+}
+
+pub(crate) fn error_json(err: &failure::Error) -> String {
+    json!({ "error": error_response(err) }).to_string()
+}
+
+fn type_name(type_url: &str) -> &str {
+    type_url.rsplit('/').next().unwrap_or_default()
+}
+
+/// serde with module of `google.protobuf.Any` fields
+pub(crate) mod any {
+    use prost_types::Any;
+    use serde::de::Error as DeError;
+    use serde::ser::Error as SerError;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde_json::Value;
+
+    use super::{message_from_json, message_to_json, type_name};
+
+    pub fn serialize<S>(any: &Option<Any>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let any = match any {
+            Some(any) => any,
+            None => return serializer.serialize_none(),
+        };
+        let mut value =
+            message_to_json(type_name(&any.type_url), &any.value).map_err(S::Error::custom)?;
+        if let Value::Object(ref mut fields) = value {
+            fields.insert("@type".to_string(), Value::String(any.type_url.clone()));
+        }
+        value.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Any>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut fields = match Option::<Value>::deserialize(deserializer)? {
+            Some(Value::Object(fields)) => fields,
+            Some(_) => return Err(D::Error::custom("any_must_be_object")),
+            None => return Ok(None),
+        };
+        let type_url = match fields.remove("@type") {
+            Some(Value::String(type_url)) => type_url,
+            _ => return Err(D::Error::custom("any_type_required")),
+        };
+        let value = message_from_json(type_name(&type_url), Value::Object(fields))
+            .map_err(D::Error::custom)?;
+        Ok(Some(Any { type_url, value }))
+    }
+}
+
+macro_rules! json_enum {
+    ($module:ident, $enum:ident, { $($variant:ident => $name:expr,)* }) => {
+        /// serde with module of enum fields, which are encoded by name
+        pub(crate) mod $module {
+            use serde::de::Error as DeError;
+            use serde::{Deserialize, Deserializer, Serializer};
+            use serde_json::Value;
+
+            use crate::api::$enum;
+
+            pub fn serialize<S>(value: &i32, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                match $enum::from_i32(*value) {
+                    $(Some($enum::$variant) => serializer.serialize_str($name),)*
+                    None => serializer.serialize_i32(*value),
+                }
+            }
+
+            pub fn deserialize<'de, D>(deserializer: D) -> Result<i32, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                match Value::deserialize(deserializer)? {
+                    Value::String(name) => match name.as_str() {
+                        $($name => Ok($enum::$variant as i32),)*
+                        _ => Err(D::Error::custom("unknown_enum_value")),
+                    },
+                    Value::Number(ref number) if number.is_i64() => {
+                        Ok(number.as_i64().unwrap_or_default() as i32)
+                    }
+                    Value::Null => Ok(0),
+                    _ => Err(D::Error::custom("unknown_enum_value")),
+                }
+            }
+        }
+    };
+}
+
+json_enum!(key_type, KeyType, {
+    Mnemonic => "MNEMONIC",
+    PrivateKey => "PRIVATE_KEY",
+});
+
+json_enum!(error_category, ErrorCategory, {
+    Unknown => "UNKNOWN",
+    Crypto => "CRYPTO",
+    Keystore => "KEYSTORE",
+    Chain => "CHAIN",
+    Input => "INPUT",
+});
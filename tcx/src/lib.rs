@@ -1,27 +1,23 @@
 use std::ffi::{CStr, CString};
 
 use std::os::raw::c_char;
+use std::slice;
 
 use prost::Message;
 use serde_json::Value;
 
-use tcx_crypto::{KDF_ROUNDS, XPUB_COMMON_IV, XPUB_COMMON_KEY_128};
-
 pub mod api;
-use crate::api::{Response, TcxAction};
+use crate::api::TcxAction;
 pub mod error_handling;
 pub mod handler;
-use crate::error_handling::{landingpad, Result, LAST_BACKTRACE, LAST_ERROR};
-#[allow(deprecated)]
-use crate::handler::{
-    encode_message, export_mnemonic, export_private_key, hd_store_create, hd_store_export,
-    hd_store_import, keystore_common_accounts, keystore_common_delete, keystore_common_derive,
-    keystore_common_exists, keystore_common_verify, private_key_store_export,
-    private_key_store_import, sign_tx, tron_sign_message, unlock_then_crash,
-};
+mod json_api;
+use crate::error_handling::{error_response, landingpad, Error, Result, LAST_ERROR};
+use crate::handler::encode_message;
 mod filemanager;
-use crate::filemanager::WALLET_FILE_DIR;
-use parking_lot::RwLock;
+mod session;
+pub mod storage;
+pub mod token_core;
+pub use crate::token_core::{Config, TokenCore};
 
 extern crate serde_json;
 
@@ -31,7 +27,8 @@ extern crate failure;
 extern crate lazy_static;
 
 lazy_static! {
-    pub static ref IS_DEBUG: RwLock<bool> = RwLock::new(false);
+    /// The instance behind the C api
+    pub static ref TOKEN_CORE: TokenCore = TokenCore::default();
 }
 
 #[no_mangle]
@@ -39,7 +36,17 @@ pub unsafe extern "C" fn free_const_string(s: *const c_char) {
     if s.is_null() {
         return;
     }
-    CStr::from_ptr(s);
+    drop(CString::from_raw(s as *mut c_char));
+}
+
+/// free a buffer returned by `call_tcx_api_bytes`
+#[no_mangle]
+pub unsafe extern "C" fn free_tcx_buffer(ptr: *mut u8, len: usize) {
+    if ptr.is_null() {
+        return;
+    }
+    let buf: *mut [u8] = slice::from_raw_parts_mut(ptr, len);
+    drop(Box::from_raw(buf));
 }
 
 fn parse_arguments(json_str: *const c_char) -> Value {
@@ -50,60 +57,144 @@ fn parse_arguments(json_str: *const c_char) -> Value {
 
 /// dispatch protobuf rpc call
 ///
-#[allow(deprecated)]
+/// The hex encoded `TcxAction` is decoded and dispatched the same way as `call_tcx_api_bytes`,
+/// the returned string must be released by `free_const_string`.
 #[no_mangle]
 pub unsafe extern "C" fn call_tcx_api(hex_str: *const c_char) -> *const c_char {
     let hex_c_str = CStr::from_ptr(hex_str);
     let hex_str = hex_c_str.to_str().expect("parse_arguments to_str");
 
     let data = hex::decode(hex_str).expect("parse_arguments hex decode");
-    let action: TcxAction = TcxAction::decode(data).expect("decode tcx api");
-    let reply: Vec<u8> = match action.method.to_lowercase().as_str() {
-        "init_token_core_x" => landingpad(|| {
-            handler::init_token_core_x(&action.param.unwrap().value).unwrap();
-            Ok(vec![])
-        }),
-        "scan_keystores" => landingpad(|| {
-            handler::scan_keystores().unwrap();
-            Ok(vec![])
-        }),
-        "hd_store_create" => landingpad(|| hd_store_create(&action.param.unwrap().value)),
-        "hd_store_import" => landingpad(|| hd_store_import(&action.param.unwrap().value)),
-        "hd_store_export" => landingpad(|| hd_store_export(&action.param.unwrap().value)),
-        "export_mnemonic" => landingpad(|| export_mnemonic(&action.param.unwrap().value)),
-        "keystore_common_derive" => {
-            landingpad(|| keystore_common_derive(&action.param.unwrap().value))
-        }
+    let reply = dispatch(&data);
 
-        "private_key_store_import" => {
-            landingpad(|| private_key_store_import(&action.param.unwrap().value))
-        }
-        "private_key_store_export" => {
-            landingpad(|| private_key_store_export(&action.param.unwrap().value))
-        }
-        "export_private_key" => landingpad(|| export_private_key(&action.param.unwrap().value)),
-        "keystore_common_verify" => {
-            landingpad(|| keystore_common_verify(&action.param.unwrap().value))
-        }
-        "keystore_common_delete" => {
-            landingpad(|| keystore_common_delete(&action.param.unwrap().value))
-        }
-        "keystore_common_exists" => {
-            landingpad(|| keystore_common_exists(&action.param.unwrap().value))
-        }
-        "keystore_common_accounts" => {
-            landingpad(|| keystore_common_accounts(&action.param.unwrap().value))
-        }
+    let ret_str = hex::encode(reply);
+    CString::new(ret_str).unwrap().into_raw()
+}
 
-        "sign_tx" => landingpad(|| sign_tx(&action.param.unwrap().value)),
+/// `call_tcx_api_bytes` returned the reply of the method
+pub const TCX_OK: i32 = 0;
+/// `call_tcx_api_bytes` returned the error `Response` of the method
+pub const TCX_ERROR: i32 = 1;
+/// `call_tcx_api_bytes` was called with a null pointer, nothing is dispatched or returned
+pub const TCX_NULL_POINTER: i32 = -1;
 
-        "tron_sign_msg" => landingpad(|| tron_sign_message(&action.param.unwrap().value)),
-        // !!! WARNING !!! used for test only
-        "unlock_then_crash" => landingpad(|| unlock_then_crash(&action.param.unwrap().value)),
-        _ => landingpad(|| Err(format_err!("unsupported_method"))),
+/// dispatch protobuf rpc call without the hex round trip
+///
+/// `ptr`/`len` hold the encoded `TcxAction`, `ptr` may only be null when `len` is 0. On return
+/// `out_ptr`/`out_len` hold the encoded reply with `TCX_OK`, or the encoded error `Response` with
+/// `TCX_ERROR`. The buffer is owned by the caller and must be released by `free_tcx_buffer`.
+#[no_mangle]
+pub unsafe extern "C" fn call_tcx_api_bytes(
+    ptr: *const u8,
+    len: usize,
+    out_ptr: *mut *mut u8,
+    out_len: *mut usize,
+) -> i32 {
+    if out_ptr.is_null() || out_len.is_null() || (ptr.is_null() && len > 0) {
+        return TCX_NULL_POINTER;
+    }
+    let data: &[u8] = if ptr.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(ptr, len)
     };
 
-    let ret_str = hex::encode(reply);
+    clear_err();
+    let reply = dispatch(data);
+    let (code, reply) = LAST_ERROR.with(|e| match *e.borrow() {
+        Some(ref err) => (
+            TCX_ERROR,
+            encode_message(error_response(err)).expect("encode error"),
+        ),
+        None => (TCX_OK, reply),
+    });
+
+    let buf = reply.into_boxed_slice();
+    *out_len = buf.len();
+    *out_ptr = Box::into_raw(buf) as *mut u8;
+    code
+}
+
+unsafe fn dispatch(data: &[u8]) -> Vec<u8> {
+    landingpad(|| {
+        let action: TcxAction = TcxAction::decode(data)?;
+        let param = action.param.map(|param| param.value).unwrap_or_default();
+        call_method(&action.method, &param)
+    })
+}
+
+/// call the handler of `method` with the encoded param, shared by all the entry points
+#[allow(deprecated)]
+pub(crate) fn call_method(method: &str, param: &[u8]) -> Result<Vec<u8>> {
+    match method.to_lowercase().as_str() {
+        "init_token_core_x" => {
+            TOKEN_CORE.init_token_core_x(param)?;
+            Ok(vec![])
+        }
+        "scan_keystores" => {
+            let result = TOKEN_CORE.scan_keystores()?;
+            TOKEN_CORE.encode_message(result)
+        }
+        "hd_store_create" => TOKEN_CORE.hd_store_create(param),
+        "hd_store_import" => TOKEN_CORE.hd_store_import(param),
+        "hd_store_export" => TOKEN_CORE.hd_store_export(param),
+        "export_mnemonic" => TOKEN_CORE.export_mnemonic(param),
+        "hd_store_export_slip39" => TOKEN_CORE.hd_store_export_slip39(param),
+        "hd_store_import_slip39" => TOKEN_CORE.hd_store_import_slip39(param),
+        "keystore_common_derive" => TOKEN_CORE.keystore_common_derive(param),
+
+        "private_key_store_import" => TOKEN_CORE.private_key_store_import(param),
+        "private_key_store_export" => TOKEN_CORE.private_key_store_export(param),
+        "export_private_key" => TOKEN_CORE.export_private_key(param),
+        "watch_only_store_import" => TOKEN_CORE.watch_only_store_import(param),
+        "keystore_common_verify" => TOKEN_CORE.keystore_common_verify(param),
+        "keystore_common_delete" => TOKEN_CORE.keystore_common_delete(param),
+        "keystore_common_change_password" => TOKEN_CORE.keystore_common_change_password(param),
+        "keystore_common_unlock_wait" => TOKEN_CORE.keystore_common_unlock_wait(param),
+        "keystore_common_remove_account" => TOKEN_CORE.keystore_common_remove_account(param),
+        "keystore_common_hide_account" => TOKEN_CORE.keystore_common_hide_account(param),
+        "keystore_common_unhide_account" => TOKEN_CORE.keystore_common_unhide_account(param),
+        "keystore_update_meta" => TOKEN_CORE.keystore_update_meta(param),
+        "account_update_label" => TOKEN_CORE.account_update_label(param),
+        "keystore_common_exists" => TOKEN_CORE.keystore_common_exists(param),
+        "keystore_common_accounts" => TOKEN_CORE.keystore_common_accounts(param),
+
+        "btc_fork_external_address" => TOKEN_CORE.btc_fork_external_address(param),
+        "hd_store_extended_public_key" => TOKEN_CORE.hd_store_extended_public_key(param),
+
+        "get_derived_key" => TOKEN_CORE.get_derived_key(param),
+        "cache_derived_key" => TOKEN_CORE.cache_derived_key(param),
+        "verify_derived_key" => TOKEN_CORE.verify_derived_key(param),
+
+        "unlock_session" => TOKEN_CORE.unlock_session(param),
+        "lock_session" => TOKEN_CORE.lock_session(param),
+
+        "sign_tx" => TOKEN_CORE.sign_tx(param),
+        "btc_fork_prepare_tx" => TOKEN_CORE.btc_fork_prepare_tx(param),
+
+        "tron_sign_msg" => TOKEN_CORE.tron_sign_message(param),
+        // !!! WARNING !!! used for test only
+        "unlock_then_crash" => TOKEN_CORE.unlock_then_crash(param),
+        _ => Err(Error::UnsupportedMethod.into()),
+    }
+}
+
+/// dispatch json rpc call
+///
+/// Accepts `{"method": ..., "param": {...}}` where param follows the proto3 JSON mapping of the
+/// param message, returns `{"result": {...}}` or `{"error": {...}}` holding the error `Response`.
+/// The returned string must be released by `free_const_string`.
+#[no_mangle]
+pub unsafe extern "C" fn call_tcx_api_json(json_str: *const c_char) -> *const c_char {
+    let json_c_str = CStr::from_ptr(json_str);
+    let json_str = json_c_str.to_str().expect("parse_arguments to_str");
+
+    clear_err();
+    let reply = landingpad(|| json_api::call_json(json_str));
+    let ret_str = LAST_ERROR.with(|e| match *e.borrow() {
+        Some(ref err) => json_api::error_json(err),
+        None => reply,
+    });
     CString::new(ret_str).unwrap().into_raw()
 }
 
@@ -120,17 +211,34 @@ fn init_token_core_x_internal(v: &Value) -> Result<()> {
     let xpub_common_key = v["xpubCommonKey128"].as_str().expect("XPubCommonKey128");
     let xpub_common_iv = v["xpubCommonIv"].as_str().expect("xpubCommonIv");
 
+    let mut config = TOKEN_CORE.config();
     if let Some(is_debug) = v["isDebug"].as_bool() {
-        *IS_DEBUG.write() = is_debug;
+        config.is_debug = is_debug;
         if is_debug {
-            *KDF_ROUNDS.write() = 1024;
+            config.kdf_rounds = 1024;
+        }
+    }
+
+    if let Some(cipher) = v["cipher"].as_str() {
+        config.cipher = cipher.parse()?;
+    }
+    if let Some(upgrade_cipher) = v["upgradeCipher"].as_bool() {
+        config.upgrade_cipher = upgrade_cipher;
+    }
+    if let Some(storage) = v["storage"].as_str() {
+        config.storage = storage.parse()?;
+    }
+    if let Some(max_unlock_failures) = v["maxUnlockFailures"].as_u64() {
+        if max_unlock_failures > 0 {
+            config.max_unlock_failures = max_unlock_failures as u32;
         }
     }
 
-    *WALLET_FILE_DIR.write() = file_dir.to_string();
-    *XPUB_COMMON_KEY_128.write() = xpub_common_key.to_string();
-    *XPUB_COMMON_IV.write() = xpub_common_iv.to_string();
-    let _ = handler::scan_keystores();
+    config.file_dir = file_dir.to_string();
+    config.xpub_common_key = xpub_common_key.to_string();
+    config.xpub_common_iv = xpub_common_iv.to_string();
+    TOKEN_CORE.set_config(config);
+    TOKEN_CORE.scan_keystores()?;
     Ok(())
 }
 
@@ -139,19 +247,13 @@ pub unsafe extern "C" fn clear_err() {
     LAST_ERROR.with(|e| {
         *e.borrow_mut() = None;
     });
-    LAST_BACKTRACE.with(|e| {
-        *e.borrow_mut() = None;
-    });
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn get_last_err_message() -> *const c_char {
     LAST_ERROR.with(|e| {
         if let Some(ref err) = *e.borrow() {
-            let rsp = Response {
-                is_success: false,
-                error: err.to_string(),
-            };
+            let rsp = error_response(err);
             // eprintln!("{:#?}", rsp);
             let rsp_bytes = encode_message(rsp).expect("encode error");
             let ret_str = hex::encode(rsp_bytes);
@@ -165,7 +267,6 @@ pub unsafe extern "C" fn get_last_err_message() -> *const c_char {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::filemanager::KEYSTORE_MAP;
     use std::ffi::{CStr, CString};
     use std::fs::remove_file;
     use std::os::raw::c_char;
@@ -173,22 +274,36 @@ mod tests {
     use std::path::Path;
 
     use crate::api::keystore_common_derive_param::Derivation;
+    use crate::api::{AccountUpdateLabelParam, KeystoreUpdateMetaParam};
     use crate::api::{
-        AccountsResponse, ExportPrivateKeyParam, HdStoreCreateParam, InitTokenCoreXParam, KeyType,
-        KeystoreCommonAccountsParam, KeystoreCommonDeriveParam, KeystoreCommonExistsParam,
-        KeystoreCommonExistsResult, KeystoreCommonExportResult, PrivateKeyStoreExportParam,
-        PrivateKeyStoreImportParam, Response, SignParam, WalletKeyParam,
+        AccountsResponse, ErrorCategory, ExportPrivateKeyParam, HdStoreCreateParam,
+        InitTokenCoreXParam, KeyType, KeystoreCommonAccountParam, KeystoreCommonAccountsParam,
+        KeystoreCommonDeriveParam, KeystoreCommonExistsParam, KeystoreCommonExistsResult,
+        KeystoreCommonExportResult, PrivateKeyStoreExportParam, PrivateKeyStoreImportParam,
+        Response, ScanKeystoresResult, SignParam, WalletKeyParam,
     };
+    use crate::api::{CacheDerivedKeyParam, DerivedKeyResult, VerifyDerivedKeyParam};
+    use crate::api::{EntropyParam, KdfParam, KeystoreCommonChangePasswordParam, LockSessionParam};
+    use crate::api::{ExternalAddressParam, ExternalAddressResult};
+    use crate::api::{HdStoreExtendedPublicKeyParam, HdStoreExtendedPublicKeyResponse};
     use crate::api::{HdStoreImportParam, WalletResult};
-    use crate::handler::hd_store_import;
-    use crate::handler::{encode_message, private_key_store_import};
+    use crate::api::{KeystoreCommonUnlockWaitParam, UnlockWaitResult};
+    use crate::api::{Slip39ExportParam, Slip39ExportResult, Slip39GroupParam, Slip39ImportParam};
+    use crate::api::{UnlockSessionParam, UnlockSessionResult, WatchOnlyStoreImportParam};
+    use crate::handler::encode_message;
     use crate::init_token_core_x;
+    use crate::storage::{DirectoryStorage, KeystoreStorage, StorageType};
+    use failure::Fail;
     use prost::Message;
-    use tcx_chain::Keystore;
+    use serde_json::json;
+    use tcx_chain::{Keystore, MnemonicLanguage};
     use tcx_constants::{TEST_MNEMONIC, TEST_PASSWORD};
+    use tcx_crypto::aes::cbc::decrypt_pkcs7;
+    use tcx_crypto::Cipher;
 
     use std::fs;
     use tcx_btc_fork::transaction::BtcForkTxInput;
+    use tcx_btc_fork::transaction::BtcForkUnsignedTxOutput;
     use tcx_btc_fork::transaction::Utxo;
 
     use tcx_ckb::{CachedCell, CellInput, CkbTxInput, CkbTxOutput, OutPoint, Script, Witness};
@@ -214,14 +329,22 @@ mod tests {
             fs::create_dir_all(p).expect("shoud create filedir");
         }
 
-        *tcx_crypto::KDF_ROUNDS.write() = 1024;
+        let mut config = TOKEN_CORE.config();
+        config.kdf_rounds = 1024;
+        TOKEN_CORE.set_config(config);
         let param = InitTokenCoreXParam {
             file_dir: "/tmp/imtoken/wallets".to_string(),
             xpub_common_key: "B888D25EC8C12BD5043777B1AC49F872".to_string(),
             xpub_common_iv: "9C0C30889CBCC5E01AB5B2BB88715799".to_string(),
+            cipher: "aes-128-ctr".to_string(),
+            upgrade_cipher: false,
+            storage: "".to_string(),
+            max_unlock_failures: 0,
         };
 
-        handler::init_token_core_x(&encode_message(param).unwrap()).expect("should init tcx");
+        TOKEN_CORE
+            .init_token_core_x(&encode_message(param).unwrap())
+            .expect("should init tcx");
     }
 
     fn teardown() {
@@ -230,13 +353,12 @@ mod tests {
         for entry in walk_dir {
             let entry = entry.expect("DirEntry");
             let fp = entry.path();
-            if !fp
+            let file_name = fp
                 .file_name()
                 .expect("file_name")
                 .to_str()
-                .expect("file_name str")
-                .ends_with(".json")
-            {
+                .expect("file_name str");
+            if !file_name.ends_with(".json") && !file_name.ends_with(".json.bak") {
                 continue;
             }
 
@@ -262,8 +384,13 @@ mod tests {
             name: "test-wallet".to_string(),
             password_hint: "imtoken".to_string(),
             overwrite: true,
+            kdf: None,
+            passphrase: "".to_string(),
+            language: "".to_string(),
         };
-        let ret = hd_store_import(&encode_message(param).unwrap()).unwrap();
+        let ret = TOKEN_CORE
+            .hd_store_import(&encode_message(param).unwrap())
+            .unwrap();
         WalletResult::decode(&ret).unwrap()
     }
 
@@ -272,9 +399,12 @@ mod tests {
             private_key: "L2hfzPyVC1jWH7n2QLTe7tVTb6btg9smp5UVzhEBxLYaSFF7sCZB".to_string(),
             password: TEST_PASSWORD.to_string(),
             overwrite: true,
+            kdf: None,
         };
 
-        let ret = private_key_store_import(&encode_message(param).unwrap()).unwrap();
+        let ret = TOKEN_CORE
+            .private_key_store_import(&encode_message(param).unwrap())
+            .unwrap();
         WalletResult::decode(ret).unwrap()
     }
 
@@ -284,6 +414,7 @@ mod tests {
         let param = KeystoreCommonDeriveParam {
             id: wallet.id.to_string(),
             password: TEST_PASSWORD.to_string(),
+            session_token: "".to_string(),
             derivations: vec![derivation],
         };
 
@@ -301,6 +432,7 @@ mod tests {
         let param = KeystoreCommonDeriveParam {
             id: wallet.id.to_string(),
             password: TEST_PASSWORD.to_string(),
+            session_token: "".to_string(),
             derivations: vec![derivation],
         };
 
@@ -348,7 +480,7 @@ mod tests {
             init_token_core_x(_to_c_char(init_params));
         }
 
-        let map = KEYSTORE_MAP.read();
+        let map = TOKEN_CORE.keystore_map().read();
         let ks: &Keystore = map.get(WALLET_ID).unwrap();
         assert_eq!(ks.id(), WALLET_ID);
     }
@@ -363,6 +495,9 @@ mod tests {
                 name: "call_tcx_api".to_string(),
                 password_hint: "".to_string(),
                 overwrite: true,
+                kdf: None,
+                passphrase: "".to_string(),
+                language: "".to_string(),
             };
             let ret_bytes = call_api("hd_store_import", import_param).unwrap();
             let ret: WalletResult = WalletResult::decode(ret_bytes).unwrap();
@@ -370,6 +505,361 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_multiple_instances() {
+        let dirs = ["/tmp/imtoken/instance_a", "/tmp/imtoken/instance_b"];
+        let instances: Vec<TokenCore> = dirs
+            .iter()
+            .map(|dir| {
+                fs::create_dir_all(dir).expect("shoud create filedir");
+                TokenCore::new(Config {
+                    file_dir: dir.to_string(),
+                    kdf_rounds: 1024,
+                    ..Config::default()
+                })
+            })
+            .collect();
+
+        let param = HdStoreImportParam {
+            mnemonic: TEST_MNEMONIC.to_string(),
+            password: TEST_PASSWORD.to_string(),
+            source: "MNEMONIC".to_string(),
+            name: "instance_a".to_string(),
+            password_hint: "".to_string(),
+            overwrite: true,
+            kdf: None,
+            passphrase: "".to_string(),
+            language: "".to_string(),
+        };
+        let ret = instances[0]
+            .hd_store_import(&encode_message(param).unwrap())
+            .unwrap();
+        let wallet = WalletResult::decode(ret).unwrap();
+
+        instances[1].scan_keystores().unwrap();
+        assert!(instances[0].keystore_map().read().contains_key(&wallet.id));
+        assert!(!instances[1].keystore_map().read().contains_key(&wallet.id));
+        assert!(!TOKEN_CORE.keystore_map().read().contains_key(&wallet.id));
+
+        remove_file(format!("{}/{}.json", dirs[0], wallet.id)).expect("should remove file");
+    }
+
+    fn import_to_instance(instance: &TokenCore) -> WalletResult {
+        let param = HdStoreImportParam {
+            mnemonic: TEST_MNEMONIC.to_string(),
+            password: TEST_PASSWORD.to_string(),
+            source: "MNEMONIC".to_string(),
+            name: "test-wallet".to_string(),
+            password_hint: "".to_string(),
+            overwrite: true,
+            kdf: None,
+            passphrase: "".to_string(),
+            language: "".to_string(),
+        };
+        let ret = instance
+            .hd_store_import(&encode_message(param).unwrap())
+            .unwrap();
+        WalletResult::decode(ret).unwrap()
+    }
+
+    #[test]
+    fn test_reload_changes_of_other_instances() {
+        let dir = "/tmp/imtoken/shared";
+        fs::create_dir_all(dir).expect("shoud create filedir");
+        let config = Config {
+            file_dir: dir.to_string(),
+            kdf_rounds: 1024,
+            ..Config::default()
+        };
+        let app = TokenCore::new(config.clone());
+        let extension = TokenCore::new(config);
+        app.scan_keystores().unwrap();
+        extension.scan_keystores().unwrap();
+
+        let wallet = import_to_instance(&app);
+        assert!(Path::new(&format!("{}/.lock", dir)).exists());
+        let param = WalletKeyParam {
+            id: wallet.id.to_string(),
+            password: TEST_PASSWORD.to_string(),
+        };
+        let ret = extension
+            .keystore_common_verify(&encode_message(param.clone()).unwrap())
+            .unwrap();
+        assert!(Response::decode(ret).unwrap().is_success);
+
+        let meta_param = KeystoreUpdateMetaParam {
+            id: wallet.id.to_string(),
+            name: "renamed".to_string(),
+            password_hint: "".to_string(),
+            update_password_hint: false,
+        };
+        app.keystore_update_meta(&encode_message(meta_param).unwrap())
+            .unwrap();
+        extension
+            .keystore_common_verify(&encode_message(param.clone()).unwrap())
+            .unwrap();
+        assert_eq!(
+            extension.keystore_map().read()[&wallet.id].meta().name,
+            "renamed"
+        );
+
+        // a save of a stale keystore doesn't overwrite the change of the other instance
+        let stale =
+            Keystore::from_json(&extension.keystore_map().read()[&wallet.id].to_json()).unwrap();
+        let meta_param = KeystoreUpdateMetaParam {
+            id: wallet.id.to_string(),
+            name: "renamed again".to_string(),
+            password_hint: "".to_string(),
+            update_password_hint: false,
+        };
+        app.keystore_update_meta(&encode_message(meta_param).unwrap())
+            .unwrap();
+        let ret = extension.flush_keystore(&stale);
+        assert_eq!(format!("{}", ret.err().unwrap()), "keystore_changed");
+        extension
+            .keystore_common_verify(&encode_message(param.clone()).unwrap())
+            .unwrap();
+        assert_eq!(
+            extension.keystore_map().read()[&wallet.id].meta().name,
+            "renamed again"
+        );
+
+        // a session survives the reload of a keystore, until the password is changed elsewhere
+        let session_param = UnlockSessionParam {
+            id: wallet.id.to_string(),
+            password: TEST_PASSWORD.to_string(),
+            ttl: 0,
+            chain_type: "".to_string(),
+            address: "".to_string(),
+            max_uses: 0,
+        };
+        let ret = extension
+            .unlock_session(&encode_message(session_param).unwrap())
+            .unwrap();
+        let token = UnlockSessionResult::decode(ret).unwrap().session_token;
+        let meta_param = KeystoreUpdateMetaParam {
+            id: wallet.id.to_string(),
+            name: "renamed".to_string(),
+            password_hint: "".to_string(),
+            update_password_hint: false,
+        };
+        app.keystore_update_meta(&encode_message(meta_param).unwrap())
+            .unwrap();
+        extension
+            .keystore_common_verify(&encode_message(param.clone()).unwrap())
+            .unwrap();
+        assert!(extension.sessions().read().contains_key(&token));
+
+        let password_param = KeystoreCommonChangePasswordParam {
+            id: wallet.id.to_string(),
+            old_password: TEST_PASSWORD.to_string(),
+            new_password: "NEW PASSWORD".to_string(),
+            password_hint: "".to_string(),
+        };
+        app.keystore_common_change_password(&encode_message(password_param).unwrap())
+            .unwrap();
+        let param = WalletKeyParam {
+            id: wallet.id.to_string(),
+            password: "NEW PASSWORD".to_string(),
+        };
+        extension
+            .keystore_common_verify(&encode_message(param.clone()).unwrap())
+            .unwrap();
+        assert!(!extension.sessions().read().contains_key(&token));
+
+        app.keystore_common_delete(&encode_message(param.clone()).unwrap())
+            .unwrap();
+        let ret = extension.keystore_common_verify(&encode_message(param).unwrap());
+        assert_eq!(format!("{}", ret.err().unwrap()), "wallet_not_found");
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        let instance = TokenCore::new(Config {
+            file_dir: "/tmp/imtoken/memory".to_string(),
+            kdf_rounds: 1024,
+            storage: StorageType::Memory,
+            ..Config::default()
+        });
+        let wallet = import_to_instance(&instance);
+        assert!(!Path::new("/tmp/imtoken/memory").exists());
+
+        let result = instance.scan_keystores().unwrap();
+        assert_eq!(result.keystores.len(), 1);
+        assert_eq!(result.keystores[0].id, wallet.id);
+        assert!(instance.keystore_map().read().contains_key(&wallet.id));
+
+        let param = WalletKeyParam {
+            id: wallet.id.to_string(),
+            password: TEST_PASSWORD.to_string(),
+        };
+        instance
+            .keystore_common_delete(&encode_message(param).unwrap())
+            .unwrap();
+        assert!(instance.scan_keystores().unwrap().keystores.is_empty());
+    }
+
+    #[test]
+    fn test_max_unlock_failures_of_instance() {
+        let instance = TokenCore::new(Config {
+            file_dir: "/tmp/imtoken/limited".to_string(),
+            kdf_rounds: 1024,
+            storage: StorageType::Memory,
+            max_unlock_failures: 2,
+            ..Config::default()
+        });
+        let wallet = import_to_instance(&instance);
+        let param = WalletKeyParam {
+            id: wallet.id.to_string(),
+            password: "WRONG PASSWORD".to_string(),
+        };
+        for _ in 0..2 {
+            let ret = instance.keystore_common_verify(&encode_message(param.clone()).unwrap());
+            assert_eq!(format!("{}", ret.err().unwrap()), "password_incorrect");
+        }
+        let param = KeystoreCommonUnlockWaitParam {
+            id: wallet.id.to_string(),
+        };
+        let ret = instance
+            .keystore_common_unlock_wait(&encode_message(param).unwrap())
+            .unwrap();
+        assert!(UnlockWaitResult::decode(ret).unwrap().locked_out);
+
+        // the other instances keep the default limit
+        let map = instance.keystore_map().read();
+        assert!(!map[&wallet.id].is_locked_out(Config::default().max_unlock_failures));
+    }
+
+    #[test]
+    fn test_unlock_throttling_of_upgrade_cipher() {
+        let throttled_instance = |max_unlock_failures| {
+            TokenCore::new(Config {
+                file_dir: "/tmp/imtoken/upgrade".to_string(),
+                kdf_rounds: 1024,
+                cipher: Cipher::Aes256Gcm,
+                upgrade_cipher: true,
+                storage: StorageType::Memory,
+                max_unlock_failures,
+                ..Config::default()
+            })
+        };
+        let export = |instance: &TokenCore, id: &str, password: &str| {
+            let param = WalletKeyParam {
+                id: id.to_string(),
+                password: password.to_string(),
+            };
+            instance.export_mnemonic(&encode_message(param).unwrap())
+        };
+
+        // the keystore already uses the configured cipher
+        let instance = throttled_instance(4);
+        let wallet = import_to_instance(&instance);
+        for _ in 0..3 {
+            let ret = export(&instance, &wallet.id, "WRONG PASSWORD");
+            assert_eq!(format!("{}", ret.err().unwrap()), "password_incorrect");
+        }
+        let ret = export(&instance, &wallet.id, TEST_PASSWORD);
+        assert_eq!(format!("{}", ret.err().unwrap()), "unlock_throttled");
+
+        let instance = throttled_instance(2);
+        let wallet = import_to_instance(&instance);
+        for _ in 0..2 {
+            let ret = export(&instance, &wallet.id, "WRONG PASSWORD");
+            assert_eq!(format!("{}", ret.err().unwrap()), "password_incorrect");
+        }
+        let ret = export(&instance, &wallet.id, TEST_PASSWORD);
+        assert_eq!(format!("{}", ret.err().unwrap()), "keystore_locked_out");
+    }
+
+    #[test]
+    fn test_directory_storage_revision() {
+        let dir = "/tmp/imtoken/revision";
+        fs::create_dir_all(dir).expect("shoud create filedir");
+        let storage = DirectoryStorage::new(dir);
+        storage.save("entry.json", "{\"a\":1}").unwrap();
+        let revision = storage.revision("entry.json").unwrap();
+        assert_eq!(storage.revision("entry.json").unwrap(), revision);
+
+        // the same size saved right after is still another revision
+        storage.save("entry.json", "{\"a\":2}").unwrap();
+        let changed = storage.revision("entry.json").unwrap();
+        assert_ne!(changed, revision);
+        assert_eq!(
+            storage.load_backup("entry.json").unwrap().unwrap(),
+            "{\"a\":1}"
+        );
+
+        storage.delete("entry.json").unwrap();
+        assert!(storage.revision("entry.json").is_err());
+    }
+
+    #[test]
+    fn test_sqlite_storage() {
+        let dir = "/tmp/imtoken/sqlite";
+        fs::create_dir_all(dir).expect("shoud create filedir");
+        let _ = remove_file(format!("{}/keystores.sqlite", dir));
+        let config = Config {
+            file_dir: dir.to_string(),
+            kdf_rounds: 1024,
+            storage: StorageType::Sqlite,
+            ..Config::default()
+        };
+        let wallet = import_to_instance(&TokenCore::new(config.clone()));
+        assert!(!Path::new(&format!("{}/{}.json", dir, wallet.id)).exists());
+
+        let instance = TokenCore::new(config);
+        let storage = instance.storage().unwrap();
+        storage.save("corrupted.json", "{").unwrap();
+        let result = instance.scan_keystores().unwrap();
+        let mut statuses: Vec<&str> = result
+            .keystores
+            .iter()
+            .map(|scanned| scanned.status.as_str())
+            .collect();
+        statuses.sort();
+        assert_eq!(statuses, vec!["CORRUPTED", "LOADED"]);
+        assert!(instance.keystore_map().read().contains_key(&wallet.id));
+
+        // a file quarantined again doesn't replace the first one
+        storage.save("corrupted.json", "{{").unwrap();
+        instance.scan_keystores().unwrap();
+        let conn = rusqlite::Connection::open(format!("{}/keystores.sqlite", dir)).unwrap();
+        let mut stmt = conn
+            .prepare("SELECT content FROM keystore WHERE name LIKE 'quarantine/corrupted.json.%'")
+            .unwrap();
+        let mut quarantined = stmt
+            .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .unwrap();
+        quarantined.sort();
+        assert_eq!(quarantined, vec!["{", "{{"]);
+
+        let name = format!("{}.json", wallet.id);
+        let json = storage.load(&name).unwrap();
+        let revision = storage.revision(&name).unwrap();
+        assert!(storage.save_if_unchanged(&name, &json, None).is_err());
+        assert!(storage.save_if_unchanged(&name, &json, Some("0")).is_err());
+        storage
+            .save_if_unchanged(&name, &json, revision.as_ref().map(String::as_str))
+            .unwrap();
+
+        storage.save("other.json", "{}").unwrap();
+        assert!(storage
+            .rename(&format!("{}.json", wallet.id), "other.json")
+            .is_err());
+        assert_eq!(storage.load("other.json").unwrap(), "{}");
+        storage.delete("other.json").unwrap();
+        storage
+            .rename(&format!("{}.json", wallet.id), "renamed.json")
+            .unwrap();
+        assert_eq!(storage.list().unwrap(), vec!["renamed.json"]);
+        storage.delete("renamed.json").unwrap();
+        assert!(storage.delete("renamed.json").is_err());
+
+        remove_file(format!("{}/keystores.sqlite", dir)).expect("should remove file");
+    }
+
     #[test]
     pub fn test_scan_keystores() {
         let init_params = r#"
@@ -385,7 +875,7 @@ mod tests {
         }
         let keystore_count;
         {
-            let mut map = KEYSTORE_MAP.write();
+            let mut map = TOKEN_CORE.keystore_map().write();
             keystore_count = map.len();
             map.clear();
             assert_eq!(0, map.len());
@@ -396,12 +886,141 @@ mod tests {
         };
         let _ = call_api("scan_keystores", empty);
         {
-            let map = KEYSTORE_MAP.write();
+            let map = TOKEN_CORE.keystore_map().write();
 
             assert_eq!(keystore_count, map.len());
         }
     }
 
+    #[test]
+    pub fn test_flush_keystore_keeps_backup() {
+        run_test(|| {
+            let wallet = import_default_wallet();
+            let ks_path = format!("/tmp/imtoken/wallets/{}.json", wallet.id);
+            let bak_path = format!("{}.bak", ks_path);
+            let old_json = std::fs::read_to_string(&ks_path).unwrap();
+
+            let param = KeystoreCommonChangePasswordParam {
+                id: wallet.id.to_string(),
+                old_password: TEST_PASSWORD.to_string(),
+                new_password: "NEW PASSWORD".to_string(),
+                password_hint: "".to_string(),
+            };
+            call_api("keystore_common_change_password", param).unwrap();
+            // the backup of the old password's ciphertext is dropped with the password
+            assert!(!Path::new(&bak_path).exists());
+            let changed_json = std::fs::read_to_string(&ks_path).unwrap();
+
+            let param = KeystoreUpdateMetaParam {
+                id: wallet.id.to_string(),
+                name: "renamed".to_string(),
+                password_hint: "".to_string(),
+                update_password_hint: false,
+            };
+            call_api("keystore_update_meta", param).unwrap();
+            assert!(!Path::new(&format!("{}.tmp", ks_path)).exists());
+
+            // the backup is the previous version
+            assert_eq!(std::fs::read_to_string(&bak_path).unwrap(), changed_json);
+            let old_keystore: serde_json::Value = serde_json::from_str(&old_json).unwrap();
+            let ciphertext = old_keystore["crypto"]["ciphertext"].as_str().unwrap();
+            assert!(!changed_json.contains(ciphertext));
+
+            // a damaged keystore file is restored from the backup and reported
+            std::fs::write(&ks_path, "{\"version\": 11000, \"crypto").unwrap();
+            let ret = call_api("scan_keystores", WalletKeyParam::default()).unwrap();
+            let result = ScanKeystoresResult::decode(&ret).unwrap();
+            let scanned = result
+                .keystores
+                .iter()
+                .find(|scanned| scanned.id == wallet.id)
+                .unwrap();
+            assert_eq!(scanned.status, "LOADED");
+            assert!(scanned.restored_from_backup);
+            assert!(!scanned.error.is_empty());
+            {
+                let map = TOKEN_CORE.keystore_map().read();
+                let keystore = map.get(&wallet.id).unwrap();
+                assert_ne!(keystore.meta().name, "renamed");
+                assert!(keystore.verify_password("NEW PASSWORD"));
+            }
+            let quarantine = std::fs::read_dir("/tmp/imtoken/wallets/quarantine").unwrap();
+            for entry in quarantine {
+                let path = entry.unwrap().path();
+                if path.to_str().unwrap().contains(&wallet.id) {
+                    remove_file(path).unwrap();
+                }
+            }
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: "NEW PASSWORD".to_string(),
+            };
+            call_api("keystore_common_delete", param).unwrap();
+            assert!(!Path::new(&ks_path).exists());
+            assert!(!Path::new(&bak_path).exists());
+        })
+    }
+
+    #[test]
+    pub fn test_scan_keystores_report() {
+        run_test(|| {
+            let wallet = import_default_wallet();
+            let wallet_dir = "/tmp/imtoken/wallets";
+            let ks_path = format!("{}/{}.json", wallet_dir, wallet.id);
+            std::fs::copy(&ks_path, format!("{}/duplicate.json", wallet_dir)).unwrap();
+            std::fs::write(format!("{}/corrupted.json", wallet_dir), "{\"version\"").unwrap();
+            std::fs::write(format!("{}/foreign.json", wallet_dir), "{\"version\": 1}").unwrap();
+
+            let ret = call_api("scan_keystores", WalletKeyParam::default()).unwrap();
+            let result = ScanKeystoresResult::decode(&ret).unwrap();
+            let status = |file: &str| {
+                result
+                    .keystores
+                    .iter()
+                    .find(|scanned| scanned.file == file)
+                    .map(|scanned| scanned.status.to_string())
+                    .unwrap()
+            };
+            assert_eq!(status("corrupted.json"), "CORRUPTED");
+            assert_eq!(status("foreign.json"), "UNSUPPORTED_VERSION");
+            let mut statuses = vec![
+                status(&format!("{}.json", wallet.id)),
+                status("duplicate.json"),
+            ];
+            statuses.sort();
+            assert_eq!(statuses, vec!["DUPLICATE_ID", "LOADED"]);
+            assert!(TOKEN_CORE.keystore_map().read().contains_key(&wallet.id));
+
+            assert!(Path::new(&format!("{}/foreign.json", wallet_dir)).exists());
+            remove_file(format!("{}/foreign.json", wallet_dir)).unwrap();
+
+            // quarantined again, the first copy is kept
+            std::fs::write(format!("{}/corrupted.json", wallet_dir), "{\"version\"").unwrap();
+            call_api("scan_keystores", WalletKeyParam::default()).unwrap();
+            let quarantined: Vec<String> = std::fs::read_dir(format!("{}/quarantine", wallet_dir))
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_str().unwrap().to_string())
+                .collect();
+            let files = vec![
+                "corrupted.json".to_string(),
+                "duplicate.json".to_string(),
+                format!("{}.json", wallet.id),
+            ];
+            let copies = |file: &str| -> Vec<&String> {
+                quarantined
+                    .iter()
+                    .filter(|name| name.starts_with(&format!("{}.", file)))
+                    .collect()
+            };
+            assert_eq!(copies(&files[0]).len(), 2);
+            assert_eq!(copies(&files[1]).len() + copies(&files[2]).len(), 1);
+            for name in files.iter().flat_map(|file| copies(file)) {
+                remove_file(format!("{}/quarantine/{}", wallet_dir, name)).unwrap();
+            }
+        })
+    }
+
     #[test]
     pub fn test_hd_store_create() {
         run_test(|| {
@@ -409,6 +1028,11 @@ mod tests {
                 password: TEST_PASSWORD.to_string(),
                 password_hint: "".to_string(),
                 name: "aaa".to_string(),
+                kdf: None,
+                passphrase: "".to_string(),
+                language: "".to_string(),
+                word_count: 0,
+                entropy: None,
             };
 
             let ret = call_api("hd_store_create", param).unwrap();
@@ -422,652 +1046,2187 @@ mod tests {
     }
 
     #[test]
-    pub fn test_hd_store_import() {
+    pub fn test_hd_store_kdf() {
         run_test(|| {
-            let import_result: WalletResult = import_default_wallet();
-            assert_eq!(import_result.source, "MNEMONIC");
-            let derivation = Derivation {
-                chain_type: "BITCOINCASH".to_string(),
-                path: "m/44'/145'/0'/0/0".to_string(),
-                network: "MAINNET".to_string(),
-                seg_wit: "NONE".to_string(),
-                chain_id: "".to_string(),
+            let kdf = KdfParam {
+                kdf: "scrypt".to_string(),
+                n: 1024,
+                ..KdfParam::default()
             };
-            let param = KeystoreCommonDeriveParam {
-                id: import_result.id.to_string(),
+            let param = HdStoreCreateParam {
                 password: TEST_PASSWORD.to_string(),
-                derivations: vec![derivation],
+                password_hint: "".to_string(),
+                name: "scrypt".to_string(),
+                kdf: Some(kdf),
+                passphrase: "".to_string(),
+                language: "".to_string(),
+                word_count: 0,
+                entropy: None,
             };
+            let ret = call_api("hd_store_create", param).unwrap();
+            let created: WalletResult = WalletResult::decode(&ret).unwrap();
 
-            let ret = call_api("keystore_common_derive", param).unwrap();
-            let result: AccountsResponse = AccountsResponse::decode(&ret).unwrap();
-            assert_eq!(result.accounts.first().unwrap().chain_type, "BITCOINCASH");
-            assert_eq!(
-                result.accounts.first().unwrap().address,
-                "qzld7dav7d2sfjdl6x9snkvf6raj8lfxjcj5fa8y2r"
-            );
-            remove_created_wallet(&import_result.id);
-        })
-    }
-
-    #[test]
-    pub fn test_hd_store_import_invalid_params() {
-        run_test(|| {
-            let invalid_mnemonics = vec![
-                "inject kidney empty canal shadow pact comfort wife crush horse",
-                "inject kidney empty canal shadow pact comfort wife crush horse wife wife",
-                "inject kidney empty canal shadow pact comfort wife crush horse hello",
-            ];
-            for mn in invalid_mnemonics {
-                let param = HdStoreImportParam {
-                    mnemonic: mn.to_string(),
-                    password: TEST_PASSWORD.to_string(),
-                    source: "MNEMONIC".to_string(),
-                    name: "test-wallet".to_string(),
-                    password_hint: "imtoken".to_string(),
-                    overwrite: true,
-                };
-
-                let ret = call_api("hd_store_import", param);
-                assert!(ret.is_err());
+            let kdf = KdfParam {
+                kdf: "pbkdf2".to_string(),
+                c: 2048,
+                ..KdfParam::default()
+            };
+            let param = HdStoreImportParam {
+                mnemonic: TEST_MNEMONIC.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                source: "MNEMONIC".to_string(),
+                name: "pbkdf2".to_string(),
+                password_hint: "".to_string(),
+                overwrite: true,
+                kdf: Some(kdf),
+                passphrase: "".to_string(),
+                language: "".to_string(),
+            };
+            let ret = call_api("hd_store_import", param).unwrap();
+            let imported: WalletResult = WalletResult::decode(&ret).unwrap();
+
+            for (wallet, kdf, key, value) in &[
+                (&created, "scrypt", "n", 1024),
+                (&imported, "pbkdf2", "c", 2048),
+            ] {
+                let path = format!("/tmp/imtoken/wallets/{}.json", wallet.id);
+                let json: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+                assert_eq!(json["crypto"]["kdf"], *kdf);
+                assert_eq!(json["crypto"]["kdfparams"][key], *value);
             }
-        })
-    }
 
-    #[test]
-    pub fn test_hd_store_import_ltc() {
-        run_test(|| {
-            let import_result: WalletResult = import_default_wallet();
+            // both are loaded back from the files
+            call_api("scan_keystores", WalletKeyParam::default()).unwrap();
+            {
+                let map = TOKEN_CORE.keystore_map().read();
+                assert!(map[&created.id].verify_password(TEST_PASSWORD));
+                assert!(map[&imported.id].verify_password(TEST_PASSWORD));
+            }
 
-            let derivation = Derivation {
-                chain_type: "LITECOIN".to_string(),
-                path: "m/44'/1'/0'/0/0".to_string(),
-                network: "TESTNET".to_string(),
-                seg_wit: "NONE".to_string(),
-                chain_id: "".to_string(),
+            let kdf = KdfParam {
+                kdf: "scrypt".to_string(),
+                n: 1000,
+                ..KdfParam::default()
             };
-            let param = KeystoreCommonDeriveParam {
-                id: import_result.id.to_string(),
+            let param = HdStoreCreateParam {
                 password: TEST_PASSWORD.to_string(),
-                derivations: vec![derivation],
+                password_hint: "".to_string(),
+                name: "scrypt".to_string(),
+                kdf: Some(kdf),
+                passphrase: "".to_string(),
+                language: "".to_string(),
+                word_count: 0,
+                entropy: None,
             };
+            let ret = call_api("hd_store_create", param);
+            assert_eq!(format!("{}", ret.err().unwrap()), "kdf_params_invalid");
 
-            let ret = call_api("keystore_common_derive", param).unwrap();
-            let result: AccountsResponse = AccountsResponse::decode(&ret).unwrap();
-            assert_eq!(result.accounts.first().unwrap().chain_type, "LITECOIN");
-            assert_eq!(
-                result.accounts.first().unwrap().address,
-                "mkeNU5nVnozJiaACDELLCsVUc8Wxoh1rQN"
-            );
-
-            remove_created_wallet(&import_result.id);
+            remove_created_wallet(&created.id);
+            remove_created_wallet(&imported.id);
         })
     }
 
     #[test]
-    pub fn test_hd_store_export() {
+    pub fn test_hd_store_passphrase() {
         run_test(|| {
             let wallet = import_default_wallet();
 
-            let param = WalletKeyParam {
-                id: wallet.id.to_string(),
-                password: TEST_PASSWORD.to_string(),
+            let param = HdStoreImportParam {
+                mnemonic: TEST_MNEMONIC.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                source: "MNEMONIC".to_string(),
+                name: "hidden".to_string(),
+                password_hint: "".to_string(),
+                overwrite: false,
+                kdf: None,
+                passphrase: "TREZOR".to_string(),
+                language: "".to_string(),
             };
-            let ret = call_api("hd_store_export", param).unwrap();
-            let result: KeystoreCommonExportResult =
-                KeystoreCommonExportResult::decode(&ret).unwrap();
+            let ret = call_api("hd_store_import", param.clone()).unwrap();
+            let hidden = WalletResult::decode(&ret).unwrap();
+            assert_ne!(hidden.id, wallet.id);
+            let ret = call_api("hd_store_import", param);
+            assert_eq!(format!("{}", ret.err().unwrap()), "address_already_exist");
 
-            assert_eq!(result.r#type, KeyType::Mnemonic as i32);
-            assert_eq!(result.value, TEST_MNEMONIC);
-        })
-    }
-
-    #[test]
-    pub fn test_export_mnemonic() {
-        run_test(|| {
-            let wallet = import_default_wallet();
+            let param = KeystoreCommonExistsParam {
+                r#type: KeyType::Mnemonic as i32,
+                value: TEST_MNEMONIC.to_string(),
+                passphrase: "TREZOR".to_string(),
+            };
+            let ret = call_api("keystore_common_exists", param).unwrap();
+            let result = KeystoreCommonExistsResult::decode(&ret).unwrap();
+            assert!(result.is_exists);
+            assert_eq!(result.id, hidden.id);
 
             let param = WalletKeyParam {
-                id: wallet.id.to_string(),
+                id: hidden.id.to_string(),
                 password: TEST_PASSWORD.to_string(),
             };
             let ret = call_api("export_mnemonic", param).unwrap();
-            let result: KeystoreCommonExportResult =
-                KeystoreCommonExportResult::decode(&ret).unwrap();
-
-            assert_eq!(result.r#type, KeyType::Mnemonic as i32);
-            assert_eq!(result.value, TEST_MNEMONIC);
-
-            let wallet = import_default_pk_store();
+            let export_result = KeystoreCommonExportResult::decode(&ret).unwrap();
+            assert_eq!(export_result.value, TEST_MNEMONIC);
+            assert_eq!(export_result.passphrase, "TREZOR");
 
             let param = WalletKeyParam {
                 id: wallet.id.to_string(),
                 password: TEST_PASSWORD.to_string(),
             };
-            unsafe { clear_err() };
-            let ret = call_api("export_mnemonic", param);
-            assert!(ret.is_err());
-            assert_eq!(
-                format!("{}", ret.err().unwrap()),
-                "private_keystore_cannot_export_mnemonic"
-            );
+            let ret = call_api("export_mnemonic", param).unwrap();
+            let export_result = KeystoreCommonExportResult::decode(&ret).unwrap();
+            assert_eq!(export_result.passphrase, "");
+
+            remove_created_wallet(&wallet.id);
+            remove_created_wallet(&hidden.id);
         })
     }
 
     #[test]
-    pub fn test_keystore_common_store_derive() {
+    pub fn test_hd_store_mnemonic_language() {
         run_test(|| {
+            let french = "abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abaisser\u{3000}abeille";
             let param = HdStoreImportParam {
-                mnemonic: OTHER_MNEMONIC.to_string(),
+                mnemonic: french.to_string(),
                 password: TEST_PASSWORD.to_string(),
                 source: "MNEMONIC".to_string(),
-                name: "test-wallet".to_string(),
-                password_hint: "imtoken".to_string(),
+                name: "french".to_string(),
+                password_hint: "".to_string(),
                 overwrite: true,
+                kdf: None,
+                passphrase: "".to_string(),
+                language: "".to_string(),
             };
             let ret = call_api("hd_store_import", param).unwrap();
-            let import_result: WalletResult = WalletResult::decode(&ret).unwrap();
+            let wallet = WalletResult::decode(&ret).unwrap();
+            let path = format!("/tmp/imtoken/wallets/{}.json", wallet.id);
+            let json: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+            assert_eq!(json["imTokenMeta"]["mnemonicLanguage"], "FRENCH");
 
-            let derivations = vec![
-                Derivation {
-                    chain_type: "LITECOIN".to_string(),
-                    path: "m/44'/2'/0'/0/0".to_string(),
-                    network: "MAINNET".to_string(),
-                    seg_wit: "NONE".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "LITECOIN".to_string(),
-                    path: "m/49'/2'/0'/0/0".to_string(),
-                    network: "MAINNET".to_string(),
-                    seg_wit: "P2WPKH".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "LITECOIN".to_string(),
-                    path: "m/49'/1'/0'/0/0".to_string(),
-                    network: "TESTNET".to_string(),
-                    seg_wit: "NONE".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "TRON".to_string(),
-                    path: "m/44'/195'/0'/0/0".to_string(),
-                    network: "".to_string(),
-                    seg_wit: "".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "NERVOS".to_string(),
-                    path: "m/44'/309'/0'/0/0".to_string(),
-                    network: "TESTNET".to_string(),
-                    seg_wit: "".to_string(),
-                    chain_id: "".to_string(),
-                },
-            ];
-            let param = KeystoreCommonDeriveParam {
-                id: import_result.id.to_string(),
-                password: TEST_PASSWORD.to_string(),
-                derivations,
+            let param = KeystoreCommonExistsParam {
+                r#type: KeyType::Mnemonic as i32,
+                value: french.replace("\u{3000}", " "),
+                passphrase: "".to_string(),
             };
-            let derived_accounts_bytes = call_api("keystore_common_derive", param).unwrap();
-            let derived_accounts: AccountsResponse =
-                AccountsResponse::decode(derived_accounts_bytes).unwrap();
-            assert_eq!(5, derived_accounts.accounts.len());
-            assert_eq!(
-                "LQ3JqCohgLQ3x1CJXYERnJTy1ySaqr1E32",
-                derived_accounts.accounts[0].address
-            );
-            assert_eq!("/EhDRyPFcj1UGx8i+WiJSIeBSyaN0pX7Oq3wXqwO5M9T1aRhfLpsNPGAPLf07K+p+B0OdQW1ogVbDQCWkIwVXZLPY+njp9LjXaICiWGEeidR1TwBZSwOMRKE68wJWH/7puxYfY/Rq1+d2GFv6NxSCw==", derived_accounts.accounts[0].extended_xpub_key);
-
-            assert_eq!(
-                "MQUu6P7wsLQZfVZMuFWB7UXiheuVTM7RYF",
-                derived_accounts.accounts[1].address
-            );
-            assert_eq!("A5LUzJcPB4r54wqr8EjFh9fe0L87spIN9KJKtzHV6QJXBH6GEAiYT57uftpJITx613HdIXXzi8VJ30TmG8erBF30oD1DnbDmGmDo4sdRTdQSsp9NuprhZ3Y3PR9+xzdc2tKDblRL5dLZswaPxCOQcw==", derived_accounts.accounts[1].extended_xpub_key);
-
-            assert_eq!(
-                "mvdDMnRsqjqzvCyYyRXpvscmnU1FxodhkE",
-                derived_accounts.accounts[2].address
-            );
-            assert_eq!("eZIL4e0a8qw18Pve92iLfehteHDA+kqjwv91aKE+2hNN3arkq20yY2Mx6q4WAowFv0QRfIi6QlrhafJKUpjiC469NNZagCSHLaECYliEwmwTgC97zXmVJDB6MJi79y+mznf8G7Few8+u6UfiXELN5g==", derived_accounts.accounts[2].extended_xpub_key);
+            let ret = call_api("keystore_common_exists", param).unwrap();
+            let result = KeystoreCommonExistsResult::decode(&ret).unwrap();
+            assert_eq!(result.id, wallet.id);
 
+            let create_param = HdStoreCreateParam {
+                password: TEST_PASSWORD.to_string(),
+                password_hint: "".to_string(),
+                name: "japanese".to_string(),
+                kdf: None,
+                passphrase: "".to_string(),
+                language: "JAPANESE".to_string(),
+                word_count: 0,
+                entropy: None,
+            };
+            let ret = call_api("hd_store_create", create_param.clone()).unwrap();
+            let created = WalletResult::decode(&ret).unwrap();
+            let param = WalletKeyParam {
+                id: created.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+            };
+            let ret = call_api("export_mnemonic", param).unwrap();
+            let export_result = KeystoreCommonExportResult::decode(&ret).unwrap();
             assert_eq!(
-                "TLZnqkrSNLUWNrZMug8u9b6pJ3XcTGbzDV",
-                derived_accounts.accounts[3].address
+                MnemonicLanguage::detect(&export_result.value).unwrap(),
+                MnemonicLanguage::Japanese
             );
-            assert_eq!("Sla41n5BdHqc1QmqA9DXjWNx13Fpq18u19jCaMbYbxClsPr7cr/gzXsbE+08wfNLuGgtVVY4/prpnv3/pdJ8KA/I/iOKvelKxuJgN9n2O5Q54CmObc0qJVZxcAQM0PbrKE9YJyGDkJNMLM+OmjEwjg==", derived_accounts.accounts[3].extended_xpub_key);
 
+            let param = HdStoreCreateParam {
+                language: "KLINGON".to_string(),
+                ..create_param
+            };
+            let ret = call_api("hd_store_create", param);
             assert_eq!(
-                "ckt1qyqgkffut7e7md39tp5ts9vxssj7wdw8z4cquyflka",
-                derived_accounts.accounts[4].address
+                format!("{}", ret.err().unwrap()),
+                "mnemonic_language_unsupported"
             );
 
-            remove_created_wallet(&import_result.id);
+            remove_created_wallet(&wallet.id);
+            remove_created_wallet(&created.id);
         })
     }
 
     #[test]
-    pub fn test_hd_store_derive_invalid_param() {
+    pub fn test_hd_store_create_with_entropy() {
         run_test(|| {
-            let import_result: WalletResult = import_default_wallet();
+            let param = HdStoreCreateParam {
+                password: TEST_PASSWORD.to_string(),
+                password_hint: "".to_string(),
+                name: "fixture".to_string(),
+                kdf: None,
+                passphrase: "".to_string(),
+                language: "".to_string(),
+                word_count: 24,
+                entropy: Some(EntropyParam {
+                    format: "HEX".to_string(),
+                    value: "00".repeat(32),
+                    replace_rng: true,
+                }),
+            };
+            let ret = call_api("hd_store_create", param.clone()).unwrap();
+            let created = WalletResult::decode(&ret).unwrap();
+            let export_param = WalletKeyParam {
+                id: created.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+            };
+            let ret = call_api("export_mnemonic", export_param).unwrap();
+            let export_result = KeystoreCommonExportResult::decode(&ret).unwrap();
+            assert_eq!(
+                export_result.value,
+                format!("{} art", vec!["abandon"; 23].join(" "))
+            );
 
-            let invalid_derivations = vec![
-                Derivation {
-                    chain_type: "WRONG_CHAIN_TYPE".to_string(),
-                    path: "m/44'/2'/0'/0/0".to_string(),
-                    network: "MAINNET".to_string(),
-                    seg_wit: "NONE".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "LITECOIN".to_string(),
-                    path: "WRONG/PATH".to_string(),
-                    network: "MAINNET".to_string(),
-                    seg_wit: "P2WPKH".to_string(),
-                    chain_id: "".to_string(),
+            let ret = call_api(
+                "hd_store_create",
+                HdStoreCreateParam {
+                    word_count: 13,
+                    ..param.clone()
                 },
-                Derivation {
-                    chain_type: "LITECOIN".to_string(),
-                    path: "49'/1'/0'/0/0".to_string(),
-                    network: "TESTNET".to_string(),
-                    seg_wit: "NONE".to_string(),
-                    chain_id: "".to_string(),
+            );
+            assert_eq!(format!("{}", ret.err().unwrap()), "invalid_word_count");
+
+            let ret = call_api(
+                "hd_store_create",
+                HdStoreCreateParam {
+                    entropy: Some(EntropyParam {
+                        format: "DICE".to_string(),
+                        value: "123456".to_string(),
+                        replace_rng: true,
+                    }),
+                    ..param
                 },
-            ];
-            for derivation in invalid_derivations {
-                let param = KeystoreCommonDeriveParam {
-                    id: import_result.id.to_string(),
-                    password: TEST_PASSWORD.to_string(),
-                    derivations: vec![derivation],
-                };
-                let ret = call_api("keystore_common_derive", param);
-                assert!(ret.is_err());
-            }
+            );
+            assert_eq!(format!("{}", ret.err().unwrap()), "insufficient_entropy");
 
-            remove_created_wallet(&import_result.id);
+            remove_created_wallet(&created.id);
         })
     }
 
     #[test]
-    pub fn test_private_key_store_import() {
+    pub fn test_hd_store_slip39() {
         run_test(|| {
-            let import_result: WalletResult = import_default_pk_store();
-
-            assert_eq!(0, import_result.accounts.len());
+            let wallet = import_default_wallet();
 
-            let derivations = vec![
-                Derivation {
-                    chain_type: "LITECOIN".to_string(),
-                    path: "m/44'/2'/0'/0/0".to_string(),
-                    network: "MAINNET".to_string(),
-                    seg_wit: "NONE".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "LITECOIN".to_string(),
-                    path: "m/49'/2'/0'/0/0".to_string(),
-                    network: "MAINNET".to_string(),
-                    seg_wit: "P2WPKH".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "LITECOIN".to_string(),
-                    path: "m/49'/1'/0'/0/0".to_string(),
-                    network: "TESTNET".to_string(),
-                    seg_wit: "NONE".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "TRON".to_string(),
-                    path: "m/44'/195'/0'/0/0".to_string(),
-                    network: "".to_string(),
-                    seg_wit: "".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "NERVOS".to_string(),
-                    path: "m/44'/309'/0'/0/0".to_string(),
-                    network: "TESTNET".to_string(),
-                    seg_wit: "".to_string(),
-                    chain_id: "".to_string(),
-                },
-            ];
-            let param = KeystoreCommonDeriveParam {
-                id: import_result.id.to_string(),
+            let param = Slip39ExportParam {
+                id: wallet.id.to_string(),
                 password: TEST_PASSWORD.to_string(),
-                derivations,
+                group_threshold: 2,
+                groups: vec![
+                    Slip39GroupParam {
+                        member_threshold: 2,
+                        member_count: 3,
+                    },
+                    Slip39GroupParam {
+                        member_threshold: 1,
+                        member_count: 1,
+                    },
+                ],
+                passphrase: "TREZOR".to_string(),
+                iteration_exponent: 0,
             };
-            let derived_accounts_bytes = call_api("keystore_common_derive", param).unwrap();
-            let derived_accounts: AccountsResponse =
-                AccountsResponse::decode(derived_accounts_bytes).unwrap();
-            assert_eq!(5, derived_accounts.accounts.len());
+            let ret = call_api("hd_store_export_slip39", param).unwrap();
+            let exported = Slip39ExportResult::decode(&ret).unwrap();
+            assert_eq!(exported.id, wallet.id);
+            assert_eq!(exported.groups.len(), 2);
+            assert_eq!(exported.groups[0].shares.len(), 3);
+            assert_eq!(exported.groups[0].shares[0].split(' ').count(), 20);
+            assert_eq!(exported.language, "ENGLISH");
+
+            let mut param = Slip39ImportParam {
+                shares: vec![
+                    exported.groups[0].shares[2].to_string(),
+                    exported.groups[1].shares[0].to_string(),
+                    exported.groups[0].shares[0].to_string(),
+                ],
+                passphrase: "TREZOR".to_string(),
+                password: TEST_PASSWORD.to_string(),
+                name: "slip39".to_string(),
+                password_hint: "".to_string(),
+                overwrite: false,
+                kdf: None,
+                language: "".to_string(),
+            };
+            let ret = call_api("hd_store_import_slip39", param.clone());
             assert_eq!(
-                "LgGNTHMkgETS7oQcoekvACJQcH355xECog",
-                derived_accounts.accounts[0].address
+                format!("{}", ret.err().unwrap()),
+                "mnemonic_language_required"
             );
-            assert_eq!("", derived_accounts.accounts[0].extended_xpub_key);
 
-            assert_eq!(
-                "MR5Hu9zXPX3o9QuYNJGft1VMpRP418QDfW",
-                derived_accounts.accounts[1].address
-            );
-            assert_eq!("", derived_accounts.accounts[1].extended_xpub_key);
+            param.language = exported.language.to_string();
+            let ret = call_api("hd_store_import_slip39", param.clone());
+            assert_eq!(format!("{}", ret.err().unwrap()), "address_already_exist");
+
+            param.overwrite = true;
+            let ret = call_api("hd_store_import_slip39", param.clone()).unwrap();
+            let imported = WalletResult::decode(&ret).unwrap();
+            assert_eq!(imported.id, wallet.id);
+
+            let export_param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+            };
+            let ret = call_api("export_mnemonic", export_param).unwrap();
+            let export_result = KeystoreCommonExportResult::decode(&ret).unwrap();
+            assert_eq!(export_result.value, TEST_MNEMONIC);
 
+            param.shares.truncate(2);
+            let ret = call_api("hd_store_import_slip39", param);
             assert_eq!(
-                "n2ZNV88uQbede7C5M5jzi6SyG4GVuPpng6",
-                derived_accounts.accounts[2].address
+                format!("{}", ret.err().unwrap()),
+                "slip39_insufficient_shares"
             );
-            assert_eq!("", derived_accounts.accounts[2].extended_xpub_key);
 
+            remove_created_wallet(&wallet.id);
+
+            let pk_store = import_default_pk_store();
+            let param = Slip39ExportParam {
+                id: pk_store.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                group_threshold: 1,
+                groups: vec![Slip39GroupParam {
+                    member_threshold: 1,
+                    member_count: 1,
+                }],
+                passphrase: "".to_string(),
+                iteration_exponent: 0,
+            };
+            let ret = call_api("hd_store_export_slip39", param.clone());
             assert_eq!(
-                "TXo4VDm8Qc5YBSjPhu8pMaxzTApSvLshWG",
-                derived_accounts.accounts[3].address
+                format!("{}", ret.err().unwrap()),
+                "private_keystore_cannot_export_mnemonic"
             );
-            assert_eq!("", derived_accounts.accounts[3].extended_xpub_key);
+            remove_created_wallet(&pk_store.id);
 
-            assert_eq!(
-                "ckt1qyqpavderq5jjxh6qhxeks4t706kglffkyassx7h5z",
+            // the shares can't carry the bip39 passphrase
+            let import_param = HdStoreImportParam {
+                mnemonic: TEST_MNEMONIC.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                source: "MNEMONIC".to_string(),
+                name: "hidden".to_string(),
+                password_hint: "".to_string(),
+                overwrite: true,
+                kdf: None,
+                passphrase: "TREZOR".to_string(),
+                language: "".to_string(),
+            };
+            let ret = call_api("hd_store_import", import_param).unwrap();
+            let hidden = WalletResult::decode(&ret).unwrap();
+            let param = Slip39ExportParam {
+                id: hidden.id.to_string(),
+                ..param
+            };
+            let ret = call_api("hd_store_export_slip39", param);
+            assert_eq!(
+                format!("{}", ret.err().unwrap()),
+                "bip39_passphrase_not_exportable"
+            );
+
+            remove_created_wallet(&hidden.id);
+        })
+    }
+
+    #[test]
+    pub fn test_hd_store_slip39_of_non_english_wallet() {
+        run_test(|| {
+            let french = "abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abeille";
+            let param = HdStoreImportParam {
+                mnemonic: french.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                source: "MNEMONIC".to_string(),
+                name: "french".to_string(),
+                password_hint: "".to_string(),
+                overwrite: true,
+                kdf: None,
+                passphrase: "".to_string(),
+                language: "".to_string(),
+            };
+            let ret = call_api("hd_store_import", param).unwrap();
+            let wallet = WalletResult::decode(&ret).unwrap();
+
+            let param = Slip39ExportParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                group_threshold: 1,
+                groups: vec![Slip39GroupParam {
+                    member_threshold: 1,
+                    member_count: 1,
+                }],
+                passphrase: "".to_string(),
+                iteration_exponent: 0,
+            };
+            let ret = call_api("hd_store_export_slip39", param).unwrap();
+            let exported = Slip39ExportResult::decode(&ret).unwrap();
+            assert_eq!(exported.language, "FRENCH");
+
+            let param = Slip39ImportParam {
+                shares: exported.groups[0].shares.clone(),
+                passphrase: "".to_string(),
+                password: TEST_PASSWORD.to_string(),
+                name: "french".to_string(),
+                password_hint: "".to_string(),
+                overwrite: true,
+                kdf: None,
+                language: exported.language,
+            };
+            let ret = call_api("hd_store_import_slip39", param).unwrap();
+            let imported = WalletResult::decode(&ret).unwrap();
+            assert_eq!(imported.id, wallet.id);
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+            };
+            let ret = call_api("export_mnemonic", param).unwrap();
+            let export_result = KeystoreCommonExportResult::decode(&ret).unwrap();
+            assert_eq!(export_result.value, french);
+
+            remove_created_wallet(&wallet.id);
+        })
+    }
+
+    #[test]
+    pub fn test_upgrade_cipher() {
+        run_test(|| {
+            let wallet = import_default_wallet();
+            let path = format!("/tmp/imtoken/wallets/{}.json", wallet.id);
+            let read_cipher = || {
+                let json: Value =
+                    serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+                json["crypto"]["cipher"].as_str().unwrap().to_string()
+            };
+            assert_eq!(read_cipher(), "aes-128-ctr");
+
+            let param = InitTokenCoreXParam {
+                file_dir: "/tmp/imtoken/wallets".to_string(),
+                xpub_common_key: "B888D25EC8C12BD5043777B1AC49F872".to_string(),
+                xpub_common_iv: "9C0C30889CBCC5E01AB5B2BB88715799".to_string(),
+                cipher: "aes-256-gcm".to_string(),
+                upgrade_cipher: true,
+                storage: "".to_string(),
+                max_unlock_failures: 0,
+            };
+            call_api("init_token_core_x", param).unwrap();
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: "WRONG PASSWORD".to_string(),
+            };
+            let ret = call_api("export_mnemonic", param);
+            assert_eq!(format!("{}", ret.err().unwrap()), "password_incorrect");
+            assert_eq!(read_cipher(), "aes-128-ctr");
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+            };
+            let ret = call_api("export_mnemonic", param.clone()).unwrap();
+            let export_result = KeystoreCommonExportResult::decode(&ret).unwrap();
+            assert_eq!(export_result.value, TEST_MNEMONIC);
+            assert_eq!(read_cipher(), "aes-256-gcm");
+
+            // reload the upgraded file
+            TOKEN_CORE.scan_keystores().unwrap();
+            let ret = call_api("export_mnemonic", param).unwrap();
+            let export_result = KeystoreCommonExportResult::decode(&ret).unwrap();
+            assert_eq!(export_result.value, TEST_MNEMONIC);
+
+            let param = HdStoreCreateParam {
+                password: TEST_PASSWORD.to_string(),
+                password_hint: "".to_string(),
+                name: "gcm".to_string(),
+                kdf: None,
+                passphrase: "".to_string(),
+                language: "".to_string(),
+                word_count: 0,
+                entropy: None,
+            };
+            let ret = call_api("hd_store_create", param).unwrap();
+            let created = WalletResult::decode(&ret).unwrap();
+            let json: Value = serde_json::from_str(
+                &fs::read_to_string(format!("/tmp/imtoken/wallets/{}.json", created.id)).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(json["crypto"]["cipher"], "aes-256-gcm");
+
+            let param = InitTokenCoreXParam {
+                file_dir: "/tmp/imtoken/wallets".to_string(),
+                xpub_common_key: "B888D25EC8C12BD5043777B1AC49F872".to_string(),
+                xpub_common_iv: "9C0C30889CBCC5E01AB5B2BB88715799".to_string(),
+                cipher: "aes-128-cbc".to_string(),
+                upgrade_cipher: false,
+                storage: "".to_string(),
+                max_unlock_failures: 0,
+            };
+            let ret = call_api("init_token_core_x", param);
+            assert_eq!(format!("{}", ret.err().unwrap()), "unsupported_cipher");
+
+            remove_created_wallet(&wallet.id);
+            remove_created_wallet(&created.id);
+        })
+    }
+
+    #[test]
+    pub fn test_private_key_store_import_argon2id() {
+        run_test(|| {
+            let kdf = KdfParam {
+                kdf: "argon2id".to_string(),
+                memory: 1024,
+                time: 1,
+                parallelism: 1,
+                ..KdfParam::default()
+            };
+            let param = PrivateKeyStoreImportParam {
+                private_key: "L2hfzPyVC1jWH7n2QLTe7tVTb6btg9smp5UVzhEBxLYaSFF7sCZB".to_string(),
+                password: TEST_PASSWORD.to_string(),
+                overwrite: true,
+                kdf: Some(kdf.clone()),
+            };
+            let ret = call_api("private_key_store_import", param).unwrap();
+            let wallet: WalletResult = WalletResult::decode(&ret).unwrap();
+
+            let path = format!("/tmp/imtoken/wallets/{}.json", wallet.id);
+            let json: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+            assert_eq!(json["crypto"]["kdf"], "argon2id");
+            assert_eq!(json["crypto"]["kdfparams"]["time"], 1);
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+            };
+            let ret = call_api("keystore_common_verify", param).unwrap();
+            assert!(Response::decode(&ret).unwrap().is_success);
+
+            let param = HdStoreCreateParam {
+                password: TEST_PASSWORD.to_string(),
+                password_hint: "".to_string(),
+                name: "argon2id".to_string(),
+                kdf: Some(KdfParam {
+                    parallelism: 256,
+                    ..kdf
+                }),
+                passphrase: "".to_string(),
+                language: "".to_string(),
+                word_count: 0,
+                entropy: None,
+            };
+            let ret = call_api("hd_store_create", param);
+            assert_eq!(format!("{}", ret.err().unwrap()), "kdf_params_invalid");
+
+            remove_created_wallet(&wallet.id);
+        })
+    }
+
+    #[test]
+    pub fn test_hd_store_import() {
+        run_test(|| {
+            let import_result: WalletResult = import_default_wallet();
+            assert_eq!(import_result.source, "MNEMONIC");
+            let derivation = Derivation {
+                chain_type: "BITCOINCASH".to_string(),
+                path: "m/44'/145'/0'/0/0".to_string(),
+                network: "MAINNET".to_string(),
+                seg_wit: "NONE".to_string(),
+                chain_id: "".to_string(),
+            };
+            let param = KeystoreCommonDeriveParam {
+                id: import_result.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
+                derivations: vec![derivation],
+            };
+
+            let ret = call_api("keystore_common_derive", param).unwrap();
+            let result: AccountsResponse = AccountsResponse::decode(&ret).unwrap();
+            assert_eq!(result.accounts.first().unwrap().chain_type, "BITCOINCASH");
+            assert_eq!(
+                result.accounts.first().unwrap().address,
+                "qzld7dav7d2sfjdl6x9snkvf6raj8lfxjcj5fa8y2r"
+            );
+            remove_created_wallet(&import_result.id);
+        })
+    }
+
+    #[test]
+    pub fn test_hd_store_import_invalid_params() {
+        run_test(|| {
+            let invalid_mnemonics = vec![
+                "inject kidney empty canal shadow pact comfort wife crush horse",
+                "inject kidney empty canal shadow pact comfort wife crush horse wife wife",
+                "inject kidney empty canal shadow pact comfort wife crush horse hello",
+            ];
+            for mn in invalid_mnemonics {
+                let param = HdStoreImportParam {
+                    mnemonic: mn.to_string(),
+                    password: TEST_PASSWORD.to_string(),
+                    source: "MNEMONIC".to_string(),
+                    name: "test-wallet".to_string(),
+                    password_hint: "imtoken".to_string(),
+                    overwrite: true,
+                    kdf: None,
+                    passphrase: "".to_string(),
+                    language: "".to_string(),
+                };
+
+                let ret = call_api("hd_store_import", param);
+                assert!(ret.is_err());
+            }
+        })
+    }
+
+    #[test]
+    pub fn test_hd_store_import_ltc() {
+        run_test(|| {
+            let import_result: WalletResult = import_default_wallet();
+
+            let derivation = Derivation {
+                chain_type: "LITECOIN".to_string(),
+                path: "m/44'/1'/0'/0/0".to_string(),
+                network: "TESTNET".to_string(),
+                seg_wit: "NONE".to_string(),
+                chain_id: "".to_string(),
+            };
+            let param = KeystoreCommonDeriveParam {
+                id: import_result.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
+                derivations: vec![derivation],
+            };
+
+            let ret = call_api("keystore_common_derive", param).unwrap();
+            let result: AccountsResponse = AccountsResponse::decode(&ret).unwrap();
+            assert_eq!(result.accounts.first().unwrap().chain_type, "LITECOIN");
+            assert_eq!(
+                result.accounts.first().unwrap().address,
+                "mkeNU5nVnozJiaACDELLCsVUc8Wxoh1rQN"
+            );
+
+            remove_created_wallet(&import_result.id);
+        })
+    }
+
+    #[test]
+    pub fn test_hd_store_export() {
+        run_test(|| {
+            let wallet = import_default_wallet();
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+            };
+            let ret = call_api("hd_store_export", param).unwrap();
+            let result: KeystoreCommonExportResult =
+                KeystoreCommonExportResult::decode(&ret).unwrap();
+
+            assert_eq!(result.r#type, KeyType::Mnemonic as i32);
+            assert_eq!(result.value, TEST_MNEMONIC);
+        })
+    }
+
+    #[test]
+    pub fn test_export_mnemonic() {
+        run_test(|| {
+            let wallet = import_default_wallet();
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+            };
+            let ret = call_api("export_mnemonic", param).unwrap();
+            let result: KeystoreCommonExportResult =
+                KeystoreCommonExportResult::decode(&ret).unwrap();
+
+            assert_eq!(result.r#type, KeyType::Mnemonic as i32);
+            assert_eq!(result.value, TEST_MNEMONIC);
+
+            let wallet = import_default_pk_store();
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+            };
+            unsafe { clear_err() };
+            let ret = call_api("export_mnemonic", param);
+            assert!(ret.is_err());
+            assert_eq!(
+                format!("{}", ret.err().unwrap()),
+                "private_keystore_cannot_export_mnemonic"
+            );
+        })
+    }
+
+    #[test]
+    pub fn test_keystore_common_store_derive() {
+        run_test(|| {
+            let param = HdStoreImportParam {
+                mnemonic: OTHER_MNEMONIC.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                source: "MNEMONIC".to_string(),
+                name: "test-wallet".to_string(),
+                password_hint: "imtoken".to_string(),
+                overwrite: true,
+                kdf: None,
+                passphrase: "".to_string(),
+                language: "".to_string(),
+            };
+            let ret = call_api("hd_store_import", param).unwrap();
+            let import_result: WalletResult = WalletResult::decode(&ret).unwrap();
+
+            let derivations = vec![
+                Derivation {
+                    chain_type: "LITECOIN".to_string(),
+                    path: "m/44'/2'/0'/0/0".to_string(),
+                    network: "MAINNET".to_string(),
+                    seg_wit: "NONE".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "LITECOIN".to_string(),
+                    path: "m/49'/2'/0'/0/0".to_string(),
+                    network: "MAINNET".to_string(),
+                    seg_wit: "P2WPKH".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "LITECOIN".to_string(),
+                    path: "m/49'/1'/0'/0/0".to_string(),
+                    network: "TESTNET".to_string(),
+                    seg_wit: "NONE".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "TRON".to_string(),
+                    path: "m/44'/195'/0'/0/0".to_string(),
+                    network: "".to_string(),
+                    seg_wit: "".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "NERVOS".to_string(),
+                    path: "m/44'/309'/0'/0/0".to_string(),
+                    network: "TESTNET".to_string(),
+                    seg_wit: "".to_string(),
+                    chain_id: "".to_string(),
+                },
+            ];
+            let param = KeystoreCommonDeriveParam {
+                id: import_result.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
+                derivations,
+            };
+            let derived_accounts_bytes = call_api("keystore_common_derive", param).unwrap();
+            let derived_accounts: AccountsResponse =
+                AccountsResponse::decode(derived_accounts_bytes).unwrap();
+            assert_eq!(5, derived_accounts.accounts.len());
+            assert_eq!(
+                "LQ3JqCohgLQ3x1CJXYERnJTy1ySaqr1E32",
+                derived_accounts.accounts[0].address
+            );
+            assert_eq!("/EhDRyPFcj1UGx8i+WiJSIeBSyaN0pX7Oq3wXqwO5M9T1aRhfLpsNPGAPLf07K+p+B0OdQW1ogVbDQCWkIwVXZLPY+njp9LjXaICiWGEeidR1TwBZSwOMRKE68wJWH/7puxYfY/Rq1+d2GFv6NxSCw==", derived_accounts.accounts[0].extended_xpub_key);
+
+            assert_eq!(
+                "MQUu6P7wsLQZfVZMuFWB7UXiheuVTM7RYF",
+                derived_accounts.accounts[1].address
+            );
+            assert_eq!("A5LUzJcPB4r54wqr8EjFh9fe0L87spIN9KJKtzHV6QJXBH6GEAiYT57uftpJITx613HdIXXzi8VJ30TmG8erBF30oD1DnbDmGmDo4sdRTdQSsp9NuprhZ3Y3PR9+xzdc2tKDblRL5dLZswaPxCOQcw==", derived_accounts.accounts[1].extended_xpub_key);
+
+            assert_eq!(
+                "mvdDMnRsqjqzvCyYyRXpvscmnU1FxodhkE",
+                derived_accounts.accounts[2].address
+            );
+            assert_eq!("eZIL4e0a8qw18Pve92iLfehteHDA+kqjwv91aKE+2hNN3arkq20yY2Mx6q4WAowFv0QRfIi6QlrhafJKUpjiC469NNZagCSHLaECYliEwmwTgC97zXmVJDB6MJi79y+mznf8G7Few8+u6UfiXELN5g==", derived_accounts.accounts[2].extended_xpub_key);
+
+            assert_eq!(
+                "TLZnqkrSNLUWNrZMug8u9b6pJ3XcTGbzDV",
+                derived_accounts.accounts[3].address
+            );
+            assert_eq!("Sla41n5BdHqc1QmqA9DXjWNx13Fpq18u19jCaMbYbxClsPr7cr/gzXsbE+08wfNLuGgtVVY4/prpnv3/pdJ8KA/I/iOKvelKxuJgN9n2O5Q54CmObc0qJVZxcAQM0PbrKE9YJyGDkJNMLM+OmjEwjg==", derived_accounts.accounts[3].extended_xpub_key);
+
+            assert_eq!(
+                "ckt1qyqgkffut7e7md39tp5ts9vxssj7wdw8z4cquyflka",
+                derived_accounts.accounts[4].address
+            );
+
+            remove_created_wallet(&import_result.id);
+        })
+    }
+
+    #[test]
+    pub fn test_hd_store_derive_invalid_param() {
+        run_test(|| {
+            let import_result: WalletResult = import_default_wallet();
+
+            let invalid_derivations = vec![
+                Derivation {
+                    chain_type: "WRONG_CHAIN_TYPE".to_string(),
+                    path: "m/44'/2'/0'/0/0".to_string(),
+                    network: "MAINNET".to_string(),
+                    seg_wit: "NONE".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "LITECOIN".to_string(),
+                    path: "WRONG/PATH".to_string(),
+                    network: "MAINNET".to_string(),
+                    seg_wit: "P2WPKH".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "LITECOIN".to_string(),
+                    path: "49'/1'/0'/0/0".to_string(),
+                    network: "TESTNET".to_string(),
+                    seg_wit: "NONE".to_string(),
+                    chain_id: "".to_string(),
+                },
+            ];
+            for derivation in invalid_derivations {
+                let param = KeystoreCommonDeriveParam {
+                    id: import_result.id.to_string(),
+                    password: TEST_PASSWORD.to_string(),
+                    session_token: "".to_string(),
+                    derivations: vec![derivation],
+                };
+                let ret = call_api("keystore_common_derive", param);
+                assert!(ret.is_err());
+            }
+
+            remove_created_wallet(&import_result.id);
+        })
+    }
+
+    #[test]
+    pub fn test_private_key_store_import() {
+        run_test(|| {
+            let import_result: WalletResult = import_default_pk_store();
+
+            assert_eq!(0, import_result.accounts.len());
+
+            let derivations = vec![
+                Derivation {
+                    chain_type: "LITECOIN".to_string(),
+                    path: "m/44'/2'/0'/0/0".to_string(),
+                    network: "MAINNET".to_string(),
+                    seg_wit: "NONE".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "LITECOIN".to_string(),
+                    path: "m/49'/2'/0'/0/0".to_string(),
+                    network: "MAINNET".to_string(),
+                    seg_wit: "P2WPKH".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "LITECOIN".to_string(),
+                    path: "m/49'/1'/0'/0/0".to_string(),
+                    network: "TESTNET".to_string(),
+                    seg_wit: "NONE".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "TRON".to_string(),
+                    path: "m/44'/195'/0'/0/0".to_string(),
+                    network: "".to_string(),
+                    seg_wit: "".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "NERVOS".to_string(),
+                    path: "m/44'/309'/0'/0/0".to_string(),
+                    network: "TESTNET".to_string(),
+                    seg_wit: "".to_string(),
+                    chain_id: "".to_string(),
+                },
+            ];
+            let param = KeystoreCommonDeriveParam {
+                id: import_result.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
+                derivations,
+            };
+            let derived_accounts_bytes = call_api("keystore_common_derive", param).unwrap();
+            let derived_accounts: AccountsResponse =
+                AccountsResponse::decode(derived_accounts_bytes).unwrap();
+            assert_eq!(5, derived_accounts.accounts.len());
+            assert_eq!(
+                "LgGNTHMkgETS7oQcoekvACJQcH355xECog",
+                derived_accounts.accounts[0].address
+            );
+            assert_eq!("", derived_accounts.accounts[0].extended_xpub_key);
+
+            assert_eq!(
+                "MR5Hu9zXPX3o9QuYNJGft1VMpRP418QDfW",
+                derived_accounts.accounts[1].address
+            );
+            assert_eq!("", derived_accounts.accounts[1].extended_xpub_key);
+
+            assert_eq!(
+                "n2ZNV88uQbede7C5M5jzi6SyG4GVuPpng6",
+                derived_accounts.accounts[2].address
+            );
+            assert_eq!("", derived_accounts.accounts[2].extended_xpub_key);
+
+            assert_eq!(
+                "TXo4VDm8Qc5YBSjPhu8pMaxzTApSvLshWG",
+                derived_accounts.accounts[3].address
+            );
+            assert_eq!("", derived_accounts.accounts[3].extended_xpub_key);
+
+            assert_eq!(
+                "ckt1qyqpavderq5jjxh6qhxeks4t706kglffkyassx7h5z",
                 derived_accounts.accounts[4].address
             );
 
-            // pk rederive
-            let derivations = vec![Derivation {
+            // pk rederive
+            let derivations = vec![Derivation {
+                chain_type: "LITECOIN".to_string(),
+                path: "m/44'/2'/0'/0/0".to_string(),
+                network: "MAINNET".to_string(),
+                seg_wit: "NONE".to_string(),
+                chain_id: "".to_string(),
+            }];
+            let param = KeystoreCommonDeriveParam {
+                id: import_result.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
+                derivations,
+            };
+            let derived_accounts_bytes = call_api("keystore_common_derive", param).unwrap();
+            let derived_accounts: AccountsResponse =
+                AccountsResponse::decode(derived_accounts_bytes).unwrap();
+            assert_eq!(
+                "LgGNTHMkgETS7oQcoekvACJQcH355xECog",
+                derived_accounts.accounts[0].address
+            );
+            assert_eq!("", derived_accounts.accounts[0].extended_xpub_key);
+
+            let param = KeystoreCommonAccountsParam {
+                id: import_result.id.to_string(),
+                include_hidden: false,
+            };
+            let accounts_ret = call_api("keystore_common_accounts", param).unwrap();
+            let ret = AccountsResponse::decode(accounts_ret).unwrap();
+            assert_eq!(5, ret.accounts.len());
+
+            remove_created_wallet(&import_result.id);
+        })
+    }
+
+    #[test]
+    pub fn test_private_key_store_export() {
+        run_test(|| {
+            let import_result: WalletResult = import_default_pk_store();
+            let param: PrivateKeyStoreExportParam = PrivateKeyStoreExportParam {
+                id: import_result.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                chain_type: "BITCOINCASH".to_string(),
+                network: "MAINNET".to_string(),
+            };
+            let ret_bytes = call_api("private_key_store_export", param).unwrap();
+            let export_result: KeystoreCommonExportResult =
+                KeystoreCommonExportResult::decode(&ret_bytes).unwrap();
+            assert_eq!(
+                "L2hfzPyVC1jWH7n2QLTe7tVTb6btg9smp5UVzhEBxLYaSFF7sCZB",
+                export_result.value
+            );
+            assert_eq!(KeyType::PrivateKey as i32, export_result.r#type);
+
+            let param: PrivateKeyStoreExportParam = PrivateKeyStoreExportParam {
+                id: import_result.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                chain_type: "BITCOINCASH".to_string(),
+                network: "TESTNET".to_string(),
+            };
+            let ret_bytes = call_api("private_key_store_export", param).unwrap();
+            let export_result: KeystoreCommonExportResult =
+                KeystoreCommonExportResult::decode(&ret_bytes).unwrap();
+            assert_eq!(
+                "cT4fTJyLd5RmSZFHnkGmVCzXDKuJLbyTt7cy77ghTTCagzNdPH1j",
+                export_result.value
+            );
+            assert_eq!(KeyType::PrivateKey as i32, export_result.r#type);
+
+            let param: PrivateKeyStoreExportParam = PrivateKeyStoreExportParam {
+                id: import_result.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                chain_type: "TRON".to_string(),
+                network: "".to_string(),
+            };
+            let ret_bytes = call_api("private_key_store_export", param).unwrap();
+            let export_result: KeystoreCommonExportResult =
+                KeystoreCommonExportResult::decode(&ret_bytes).unwrap();
+            assert_eq!(
+                "a392604efc2fad9c0b3da43b5f698a2e3f270f170d859912be0d54742275c5f6",
+                export_result.value
+            );
+            assert_eq!(KeyType::PrivateKey as i32, export_result.r#type);
+            remove_created_wallet(&import_result.id);
+        })
+    }
+
+    #[test]
+    pub fn test_export_private_key() {
+        run_test(|| {
+            let derivations = vec![
+                Derivation {
+                    chain_type: "BITCOINCASH".to_string(),
+                    path: "".to_string(),
+                    network: "MAINNET".to_string(),
+                    seg_wit: "NONE".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "BITCOINCASH".to_string(),
+                    path: "".to_string(),
+                    network: "TESTNET".to_string(),
+                    seg_wit: "NONE".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "TRON".to_string(),
+                    path: "".to_string(),
+                    network: "".to_string(),
+                    seg_wit: "".to_string(),
+                    chain_id: "".to_string(),
+                },
+            ];
+            let pks = vec![
+                "L2hfzPyVC1jWH7n2QLTe7tVTb6btg9smp5UVzhEBxLYaSFF7sCZB",
+                "cT4fTJyLd5RmSZFHnkGmVCzXDKuJLbyTt7cy77ghTTCagzNdPH1j",
+                "a392604efc2fad9c0b3da43b5f698a2e3f270f170d859912be0d54742275c5f6",
+            ];
+
+            for idx in 0..3 {
+                let import_result: WalletResult = import_pk_and_derive(derivations[idx].clone());
+                let acc = import_result.accounts.first().unwrap().clone();
+                let param: ExportPrivateKeyParam = ExportPrivateKeyParam {
+                    id: import_result.id.to_string(),
+                    password: TEST_PASSWORD.to_string(),
+                    chain_type: acc.chain_type.to_string(),
+                    network: derivations[idx].network.to_string(),
+                    main_address: acc.address.clone(),
+                    path: "".to_string(),
+                };
+                let ret_bytes = call_api("export_private_key", param).unwrap();
+                let export_result: KeystoreCommonExportResult =
+                    KeystoreCommonExportResult::decode(&ret_bytes).unwrap();
+
+                // test export as mainnet
+                assert_eq!(pks[idx], export_result.value);
+                assert_eq!(KeyType::PrivateKey as i32, export_result.r#type);
+                remove_created_wallet(&import_result.id);
+            }
+
+            let wallet = import_default_pk_store();
+            let param: ExportPrivateKeyParam = ExportPrivateKeyParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                chain_type: "LITECOIN".to_string(),
+                network: "MAINNET".to_string(),
+                main_address: "Ldfdegx3hJygDuFDUA7Rkzjjx8gfFhP9DP".to_string(),
+                path: "".to_string(),
+            };
+            let ret = call_api("export_private_key", param);
+            assert!(ret.is_err());
+            assert_eq!(format!("{}", ret.err().unwrap()), "account_not_found");
+        })
+    }
+
+    #[test]
+    pub fn test_export_private_key_from_hd_store() {
+        run_test(|| {
+            let derivations = vec![
+                Derivation {
+                    chain_type: "BITCOINCASH".to_string(),
+                    path: "m/44'/145'/0'/0/0".to_string(),
+                    network: "MAINNET".to_string(),
+                    seg_wit: "NONE".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "BITCOINCASH".to_string(),
+                    path: "m/44'/145'/0'/0/0".to_string(),
+                    network: "MAINNET".to_string(),
+                    seg_wit: "NONE".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "BITCOINCASH".to_string(),
+                    path: "m/44'/1'/0'/0/0".to_string(),
+                    network: "TESTNET".to_string(),
+                    seg_wit: "NONE".to_string(),
+                    chain_id: "".to_string(),
+                },
+                Derivation {
+                    chain_type: "TRON".to_string(),
+                    path: "m/44'/195'/0'/0/0".to_string(),
+                    network: "".to_string(),
+                    seg_wit: "".to_string(),
+                    chain_id: "".to_string(),
+                },
+            ];
+
+            let pks = vec![
+                "L39VXyorp19JfsEJfbD7Tfr4pBEX93RJuVXW7E13C51ZYAhUWbYa",
+                "KyLGdagds7tY1vupT5Kf8C1Cc5wkzzWRK51e4vsh1svCSvYk4Abo",
+                "cN4b1V3cicEexrYXiEhaWEdURyhZiVX6PzAZNFSzZaWfSNZG2cJX",
+                "685634d212eabe016a1cb09d9f1ea1ea757ebe590b9a097d7b1c9379ad280171",
+            ];
+            let export_paths = vec![
+                "m/44'/145'/0'/0/0",
+                "m/44'/145'/0'/0/1",
+                "m/44'/1'/0'/0/1",
+                "m/44'/195'/0'/0/1",
+            ];
+            for idx in 0..derivations.len() {
+                let import_result: WalletResult = import_and_derive(derivations[idx].clone());
+                let acc = import_result.accounts.first().unwrap().clone();
+                let param: ExportPrivateKeyParam = ExportPrivateKeyParam {
+                    id: import_result.id.to_string(),
+                    password: TEST_PASSWORD.to_string(),
+                    chain_type: acc.chain_type.to_string(),
+                    network: derivations[idx].network.to_string(),
+                    main_address: acc.address.to_string(),
+                    path: export_paths[idx].to_string(),
+                };
+                let ret_bytes = call_api("export_private_key", param).unwrap();
+                let export_result: KeystoreCommonExportResult =
+                    KeystoreCommonExportResult::decode(&ret_bytes).unwrap();
+
+                assert_eq!(pks[idx], export_result.value);
+                assert_eq!(KeyType::PrivateKey as i32, export_result.r#type);
+                remove_created_wallet(&import_result.id);
+            }
+
+            let import_result: WalletResult = import_default_wallet();
+
+            let param: ExportPrivateKeyParam = ExportPrivateKeyParam {
+                id: import_result.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                chain_type: "LITECOIN".to_string(),
+                network: "MAINNET".to_string(),
+                main_address: "Ldfdegx3hJygDuFDUA7Rkzjjx8gfFhP9DP".to_string(),
+                path: "m/44'/2'/0'/0/0".to_string(),
+            };
+            let ret = call_api("export_private_key", param);
+            assert!(ret.is_err());
+            assert_eq!(format!("{}", ret.err().unwrap()), "account_not_found");
+            remove_created_wallet(&import_result.id);
+        })
+    }
+
+    #[test]
+    pub fn test_import_to_pk_which_from_hd() {
+        run_test(|| {
+            let param: PrivateKeyStoreImportParam = PrivateKeyStoreImportParam {
+                private_key: "L39VXyorp19JfsEJfbD7Tfr4pBEX93RJuVXW7E13C51ZYAhUWbYa".to_string(),
+                password: TEST_PASSWORD.to_string(),
+                overwrite: true,
+                kdf: None,
+            };
+
+            let ret = TOKEN_CORE
+                .private_key_store_import(&encode_message(param).unwrap())
+                .unwrap();
+            let wallet: WalletResult = WalletResult::decode(ret).unwrap();
+
+            let derivation = Derivation {
+                chain_type: "BITCOINCASH".to_string(),
+                path: "".to_string(),
+                network: "MAINNET".to_string(),
+                seg_wit: "NONE".to_string(),
+                chain_id: "".to_string(),
+            };
+
+            let derive_param = KeystoreCommonDeriveParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
+                derivations: vec![derivation],
+            };
+            let ret_bytes = keystore_common_derive(&encode_message(derive_param).unwrap()).unwrap();
+            let ret: AccountsResponse = AccountsResponse::decode(ret_bytes).unwrap();
+            assert_eq!(
+                "qzld7dav7d2sfjdl6x9snkvf6raj8lfxjcj5fa8y2r",
+                ret.accounts.first().unwrap().address
+            );
+            remove_created_wallet(&wallet.id);
+        })
+    }
+
+    #[test]
+    pub fn test_keystore_common_verify() {
+        run_test(|| {
+            let wallets = vec![import_default_pk_store(), import_default_wallet()];
+            for wallet in wallets {
+                let param: WalletKeyParam = WalletKeyParam {
+                    id: wallet.id.to_string(),
+                    password: TEST_PASSWORD.to_string(),
+                };
+
+                let ret_bytes = call_api("keystore_common_verify", param).unwrap();
+                let result: Response = Response::decode(&ret_bytes).unwrap();
+                assert!(result.is_success);
+
+                let param: WalletKeyParam = WalletKeyParam {
+                    id: wallet.id.to_string(),
+                    password: "WRONG PASSWORD".to_string(),
+                };
+
+                let ret = call_api("keystore_common_verify", param);
+                assert!(ret.is_err());
+                assert_eq!(format!("{}", ret.err().unwrap()), "password_incorrect");
+            }
+        })
+    }
+
+    #[test]
+    pub fn test_unlock_throttling() {
+        run_test(|| {
+            let wallet = import_default_wallet();
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: "WRONG PASSWORD".to_string(),
+            };
+            for _ in 0..3 {
+                let ret = call_api("keystore_common_verify", param.clone());
+                assert_eq!(format!("{}", ret.err().unwrap()), "password_incorrect");
+            }
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+            };
+            let ret = call_api("keystore_common_verify", param.clone());
+            assert_eq!(format!("{}", ret.err().unwrap()), "unlock_throttled");
+            let ret = call_api("hd_store_export", param);
+            assert_eq!(format!("{}", ret.err().unwrap()), "unlock_throttled");
+
+            // the failed unlocks are persisted
+            call_api("scan_keystores", WalletKeyParam::default()).unwrap();
+            let param = KeystoreCommonUnlockWaitParam {
+                id: wallet.id.to_string(),
+            };
+            let ret = call_api("keystore_common_unlock_wait", param).unwrap();
+            let result = UnlockWaitResult::decode(&ret).unwrap();
+            assert_eq!(result.failed_unlocks, 3);
+            assert!(result.remaining_seconds > 0 && result.remaining_seconds <= 2);
+            assert!(!result.locked_out);
+
+            remove_created_wallet(&wallet.id);
+        })
+    }
+
+    #[test]
+    pub fn test_keystore_common_change_password() {
+        run_test(|| {
+            let wallets = vec![import_default_pk_store(), import_default_wallet()];
+            for wallet in wallets {
+                let accounts = TOKEN_CORE.keystore_map().read()[&wallet.id]
+                    .accounts()
+                    .to_vec();
+                let param = KeystoreCommonChangePasswordParam {
+                    id: wallet.id.to_string(),
+                    old_password: "WRONG PASSWORD".to_string(),
+                    new_password: "NEW PASSWORD".to_string(),
+                    password_hint: "new hint".to_string(),
+                };
+                let ret = call_api("keystore_common_change_password", param);
+                assert_eq!(format!("{}", ret.err().unwrap()), "password_incorrect");
+
+                let param = KeystoreCommonChangePasswordParam {
+                    id: wallet.id.to_string(),
+                    old_password: TEST_PASSWORD.to_string(),
+                    new_password: "NEW PASSWORD".to_string(),
+                    password_hint: "new hint".to_string(),
+                };
+                let ret = call_api("keystore_common_change_password", param).unwrap();
+                assert!(Response::decode(&ret).unwrap().is_success);
+
+                // reload from the file to make sure the change is persisted
+                call_api("scan_keystores", WalletKeyParam::default()).unwrap();
+                {
+                    let map = TOKEN_CORE.keystore_map().read();
+                    let keystore = map.get(&wallet.id).unwrap();
+                    assert!(keystore.verify_password("NEW PASSWORD"));
+                    assert!(!keystore.verify_password(TEST_PASSWORD));
+                    assert_eq!(keystore.meta().password_hint, "new hint");
+                    assert_eq!(keystore.accounts(), accounts.as_slice());
+                }
+
+                remove_created_wallet(&wallet.id);
+            }
+        })
+    }
+
+    #[test]
+    pub fn test_change_password_keeps_kdf() {
+        run_test(|| {
+            for kdf in vec![
+                KdfParam {
+                    kdf: "scrypt".to_string(),
+                    n: 1024,
+                    ..KdfParam::default()
+                },
+                KdfParam {
+                    kdf: "argon2id".to_string(),
+                    memory: 64,
+                    time: 1,
+                    parallelism: 1,
+                    ..KdfParam::default()
+                },
+            ] {
+                let param = HdStoreCreateParam {
+                    password: TEST_PASSWORD.to_string(),
+                    password_hint: "".to_string(),
+                    name: kdf.kdf.to_string(),
+                    kdf: Some(kdf.clone()),
+                    passphrase: "".to_string(),
+                    language: "".to_string(),
+                    word_count: 0,
+                    entropy: None,
+                };
+                let ret = call_api("hd_store_create", param).unwrap();
+                let wallet: WalletResult = WalletResult::decode(&ret).unwrap();
+                let path = format!("/tmp/imtoken/wallets/{}.json", wallet.id);
+                let before: Value =
+                    serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+
+                let param = KeystoreCommonChangePasswordParam {
+                    id: wallet.id.to_string(),
+                    old_password: TEST_PASSWORD.to_string(),
+                    new_password: "NEW PASSWORD".to_string(),
+                    password_hint: "".to_string(),
+                };
+                call_api("keystore_common_change_password", param).unwrap();
+
+                let after: Value =
+                    serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+                assert_eq!(after["crypto"]["kdf"], kdf.kdf.as_str());
+                let (mut before_params, mut after_params) = (
+                    before["crypto"]["kdfparams"].clone(),
+                    after["crypto"]["kdfparams"].clone(),
+                );
+                assert_ne!(before_params["salt"], after_params["salt"]);
+                before_params["salt"] = Value::Null;
+                after_params["salt"] = Value::Null;
+                assert_eq!(before_params, after_params);
+                assert!(
+                    TOKEN_CORE.keystore_map().read()[&wallet.id].verify_password("NEW PASSWORD")
+                );
+
+                remove_created_wallet(&wallet.id);
+            }
+        })
+    }
+
+    #[test]
+    pub fn test_keystore_update_meta_and_label() {
+        run_test(|| {
+            let derivation = Derivation {
+                chain_type: "LITECOIN".to_string(),
+                path: "m/44'/2'/0'/0/0".to_string(),
+                network: "MAINNET".to_string(),
+                seg_wit: "NONE".to_string(),
+                chain_id: "".to_string(),
+            };
+            let wallet = import_and_derive(derivation);
+            let address = wallet.accounts[0].address.to_string();
+
+            let param = KeystoreUpdateMetaParam {
+                id: wallet.id.to_string(),
+                name: "renamed".to_string(),
+                password_hint: "".to_string(),
+                update_password_hint: false,
+            };
+            let ret = call_api("keystore_update_meta", param).unwrap();
+            assert!(Response::decode(&ret).unwrap().is_success);
+
+            let param = AccountUpdateLabelParam {
+                id: wallet.id.to_string(),
+                chain_type: "LITECOIN".to_string(),
+                address: address.to_string(),
+                label: "savings".to_string(),
+                tags: vec!["cold".to_string(), "long term".to_string()],
+            };
+            let ret = call_api("account_update_label", param.clone()).unwrap();
+            assert!(Response::decode(&ret).unwrap().is_success);
+
+            let ret = call_api(
+                "account_update_label",
+                AccountUpdateLabelParam {
+                    chain_type: "TRON".to_string(),
+                    ..param
+                },
+            );
+            assert_eq!(format!("{}", ret.err().unwrap()), "account_not_found");
+
+            // reload from the file to make sure the change is persisted
+            call_api("scan_keystores", WalletKeyParam::default()).unwrap();
+            {
+                let map = TOKEN_CORE.keystore_map().read();
+                let keystore = map.get(&wallet.id).unwrap();
+                assert_eq!(keystore.meta().name, "renamed");
+                assert_eq!(keystore.meta().password_hint, "imtoken");
+            }
+
+            let param = KeystoreUpdateMetaParam {
+                id: wallet.id.to_string(),
+                name: "".to_string(),
+                password_hint: "".to_string(),
+                update_password_hint: true,
+            };
+            call_api("keystore_update_meta", param).unwrap();
+            {
+                let map = TOKEN_CORE.keystore_map().read();
+                let keystore = map.get(&wallet.id).unwrap();
+                assert_eq!(keystore.meta().name, "renamed");
+                assert_eq!(keystore.meta().password_hint, "");
+            }
+
+            let param = KeystoreCommonAccountsParam {
+                id: wallet.id.to_string(),
+                include_hidden: false,
+            };
+            let ret = call_api("keystore_common_accounts", param).unwrap();
+            let accounts = AccountsResponse::decode(&ret).unwrap();
+            assert_eq!(accounts.accounts[0].label, "savings");
+            assert_eq!(accounts.accounts[0].tags, vec!["cold", "long term"]);
+
+            remove_created_wallet(&wallet.id);
+        })
+    }
+
+    #[test]
+    pub fn test_keystore_common_remove_and_hide_account() {
+        run_test(|| {
+            let derivation = Derivation {
                 chain_type: "LITECOIN".to_string(),
                 path: "m/44'/2'/0'/0/0".to_string(),
                 network: "MAINNET".to_string(),
                 seg_wit: "NONE".to_string(),
                 chain_id: "".to_string(),
-            }];
-            let param = KeystoreCommonDeriveParam {
-                id: import_result.id.to_string(),
-                password: TEST_PASSWORD.to_string(),
-                derivations,
             };
-            let derived_accounts_bytes = call_api("keystore_common_derive", param).unwrap();
-            let derived_accounts: AccountsResponse =
-                AccountsResponse::decode(derived_accounts_bytes).unwrap();
-            assert_eq!(
-                "LgGNTHMkgETS7oQcoekvACJQcH355xECog",
-                derived_accounts.accounts[0].address
-            );
-            assert_eq!("", derived_accounts.accounts[0].extended_xpub_key);
+            let wallet = import_and_derive(derivation);
+            let list_accounts = |include_hidden: bool| {
+                let param = KeystoreCommonAccountsParam {
+                    id: wallet.id.to_string(),
+                    include_hidden,
+                };
+                let ret = call_api("keystore_common_accounts", param).unwrap();
+                AccountsResponse::decode(&ret).unwrap().accounts
+            };
 
-            let param = KeystoreCommonAccountsParam {
-                id: import_result.id.to_string(),
+            let param = KeystoreCommonAccountParam {
+                id: wallet.id.to_string(),
+                chain_type: "LITECOIN".to_string(),
+                address: "Ldfdegx3hJygDuFDUA7Rkzjjx8gfFhP9DP".to_string(),
+                path: "m/44'/2'/0'/0/0".to_string(),
             };
-            let accounts_ret = call_api("keystore_common_accounts", param).unwrap();
-            let ret = AccountsResponse::decode(accounts_ret).unwrap();
-            assert_eq!(5, ret.accounts.len());
+            let ret = call_api("keystore_common_hide_account", param.clone()).unwrap();
+            assert!(Response::decode(&ret).unwrap().is_success);
+            assert!(list_accounts(false).is_empty());
+
+            // reload from the file to make sure the change is persisted
+            call_api("scan_keystores", WalletKeyParam::default()).unwrap();
+            let accounts = list_accounts(true);
+            assert_eq!(accounts.len(), 1);
+            assert!(accounts[0].hidden);
+
+            call_api("keystore_common_unhide_account", param.clone()).unwrap();
+            let accounts = list_accounts(false);
+            assert_eq!(accounts.len(), 1);
+            assert!(!accounts[0].hidden);
+
+            let ret = call_api(
+                "keystore_common_remove_account",
+                KeystoreCommonAccountParam {
+                    path: "m/44'/2'/0'/0/1".to_string(),
+                    ..param.clone()
+                },
+            );
+            assert_eq!(format!("{}", ret.err().unwrap()), "account_not_found");
 
-            remove_created_wallet(&import_result.id);
+            let ret = call_api("keystore_common_remove_account", param).unwrap();
+            assert!(Response::decode(&ret).unwrap().is_success);
+            assert!(list_accounts(true).is_empty());
+
+            remove_created_wallet(&wallet.id);
         })
     }
 
     #[test]
-    pub fn test_private_key_store_export() {
+    pub fn test_error_code() {
         run_test(|| {
-            let import_result: WalletResult = import_default_pk_store();
-            let param: PrivateKeyStoreExportParam = PrivateKeyStoreExportParam {
-                id: import_result.id.to_string(),
-                password: TEST_PASSWORD.to_string(),
-                chain_type: "BITCOINCASH".to_string(),
-                network: "MAINNET".to_string(),
+            let wallet = import_default_wallet();
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: "WRONG PASSWORD".to_string(),
             };
-            let ret_bytes = call_api("private_key_store_export", param).unwrap();
-            let export_result: KeystoreCommonExportResult =
-                KeystoreCommonExportResult::decode(&ret_bytes).unwrap();
-            assert_eq!(
-                "L2hfzPyVC1jWH7n2QLTe7tVTb6btg9smp5UVzhEBxLYaSFF7sCZB",
-                export_result.value
-            );
-            assert_eq!(KeyType::PrivateKey as i32, export_result.r#type);
+            let ret = call_api("keystore_common_verify", param);
+            assert!(ret.is_err());
+            let err = unsafe { _to_str(get_last_err_message()) };
+            let rsp: Response = Response::decode(hex::decode(err).unwrap()).unwrap();
+            assert!(!rsp.is_success);
+            assert_eq!(rsp.error, "password_incorrect");
+            assert_eq!(rsp.error_code, 1002);
+            assert_eq!(rsp.error_category, ErrorCategory::Crypto as i32);
 
-            let param: PrivateKeyStoreExportParam = PrivateKeyStoreExportParam {
-                id: import_result.id.to_string(),
+            let param = WalletKeyParam {
+                id: "not_exist_id".to_string(),
                 password: TEST_PASSWORD.to_string(),
-                chain_type: "BITCOINCASH".to_string(),
-                network: "TESTNET".to_string(),
             };
-            let ret_bytes = call_api("private_key_store_export", param).unwrap();
-            let export_result: KeystoreCommonExportResult =
-                KeystoreCommonExportResult::decode(&ret_bytes).unwrap();
-            assert_eq!(
-                "cT4fTJyLd5RmSZFHnkGmVCzXDKuJLbyTt7cy77ghTTCagzNdPH1j",
-                export_result.value
-            );
-            assert_eq!(KeyType::PrivateKey as i32, export_result.r#type);
+            let ret = call_api("keystore_common_verify", param);
+            assert!(ret.is_err());
+            let err = unsafe { _to_str(get_last_err_message()) };
+            let rsp: Response = Response::decode(hex::decode(err).unwrap()).unwrap();
+            assert_eq!(rsp.error, "wallet_not_found");
+            assert_eq!(rsp.error_code, 6001);
+            assert_eq!(rsp.error_category, ErrorCategory::Keystore as i32);
+
+            let err: failure::Error = tcx_chain::Error::KeystoreLocked
+                .context("sign_failed")
+                .into();
+            let rsp = error_response(&err);
+            assert_eq!(rsp.error, "sign_failed");
+            assert_eq!(rsp.error_code, 2007);
+            assert_eq!(rsp.context, vec!["keystore_locked".to_string()]);
+
+            let rsp = error_response(&format_err!("untyped_error"));
+            assert_eq!(rsp.error_code, 0);
+            assert_eq!(rsp.error_category, ErrorCategory::Unknown as i32);
 
-            let param: PrivateKeyStoreExportParam = PrivateKeyStoreExportParam {
-                id: import_result.id.to_string(),
+            remove_created_wallet(&wallet.id);
+        })
+    }
+
+    #[test]
+    pub fn test_call_tcx_api_bytes() {
+        run_test(|| {
+            let wallet = import_default_wallet();
+            let call_bytes = |method: &str, msg: WalletKeyParam| -> (i32, Vec<u8>) {
+                let action = TcxAction {
+                    method: method.to_string(),
+                    param: Some(::prost_types::Any {
+                        type_url: "imtoken".to_string(),
+                        value: encode_message(msg).unwrap(),
+                    }),
+                };
+                let data = encode_message(action).unwrap();
+                let mut out_ptr: *mut u8 = std::ptr::null_mut();
+                let mut out_len: usize = 0;
+                unsafe {
+                    let code =
+                        call_tcx_api_bytes(data.as_ptr(), data.len(), &mut out_ptr, &mut out_len);
+                    let reply = slice::from_raw_parts(out_ptr, out_len).to_vec();
+                    free_tcx_buffer(out_ptr, out_len);
+                    (code, reply)
+                }
+            };
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
                 password: TEST_PASSWORD.to_string(),
-                chain_type: "TRON".to_string(),
-                network: "".to_string(),
             };
-            let ret_bytes = call_api("private_key_store_export", param).unwrap();
-            let export_result: KeystoreCommonExportResult =
-                KeystoreCommonExportResult::decode(&ret_bytes).unwrap();
-            assert_eq!(
-                "a392604efc2fad9c0b3da43b5f698a2e3f270f170d859912be0d54742275c5f6",
-                export_result.value
-            );
-            assert_eq!(KeyType::PrivateKey as i32, export_result.r#type);
-            remove_created_wallet(&import_result.id);
+            let (code, reply) = call_bytes("keystore_common_verify", param);
+            assert_eq!(code, TCX_OK);
+            let rsp: Response = Response::decode(reply).unwrap();
+            assert!(rsp.is_success);
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: "WRONG PASSWORD".to_string(),
+            };
+            let (code, reply) = call_bytes("keystore_common_verify", param);
+            assert_eq!(code, TCX_ERROR);
+            let rsp: Response = Response::decode(reply).unwrap();
+            assert!(!rsp.is_success);
+            assert_eq!(rsp.error, "password_incorrect");
+
+            let mut out_ptr: *mut u8 = std::ptr::null_mut();
+            let mut out_len: usize = 0;
+            let code =
+                unsafe { call_tcx_api_bytes(std::ptr::null(), 0, &mut out_ptr, &mut out_len) };
+            assert_eq!(code, TCX_ERROR);
+            unsafe { free_tcx_buffer(out_ptr, out_len) };
+
+            // the out pointers are checked before anything is written
+            let null_out = std::ptr::null_mut();
+            let code = unsafe { call_tcx_api_bytes(std::ptr::null(), 0, null_out, &mut out_len) };
+            assert_eq!(code, TCX_NULL_POINTER);
+            let code = unsafe {
+                call_tcx_api_bytes(std::ptr::null(), 0, &mut out_ptr, std::ptr::null_mut())
+            };
+            assert_eq!(code, TCX_NULL_POINTER);
+            let code =
+                unsafe { call_tcx_api_bytes(std::ptr::null(), 8, &mut out_ptr, &mut out_len) };
+            assert_eq!(code, TCX_NULL_POINTER);
+
+            remove_created_wallet(&wallet.id);
         })
     }
 
     #[test]
-    pub fn test_export_private_key() {
+    pub fn test_call_tcx_api_json() {
         run_test(|| {
-            let derivations = vec![
-                Derivation {
-                    chain_type: "BITCOINCASH".to_string(),
-                    path: "".to_string(),
-                    network: "MAINNET".to_string(),
-                    seg_wit: "NONE".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "BITCOINCASH".to_string(),
-                    path: "".to_string(),
-                    network: "TESTNET".to_string(),
-                    seg_wit: "NONE".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "TRON".to_string(),
-                    path: "".to_string(),
-                    network: "".to_string(),
-                    seg_wit: "".to_string(),
-                    chain_id: "".to_string(),
-                },
-            ];
-            let pks = vec![
-                "L2hfzPyVC1jWH7n2QLTe7tVTb6btg9smp5UVzhEBxLYaSFF7sCZB",
-                "cT4fTJyLd5RmSZFHnkGmVCzXDKuJLbyTt7cy77ghTTCagzNdPH1j",
-                "a392604efc2fad9c0b3da43b5f698a2e3f270f170d859912be0d54742275c5f6",
-            ];
+            let call_json = |json: Value| -> Value {
+                let ret = unsafe { _to_str(call_tcx_api_json(_to_c_char(&json.to_string()))) };
+                serde_json::from_str(ret).unwrap()
+            };
 
-            for idx in 0..3 {
-                let import_result: WalletResult = import_pk_and_derive(derivations[idx].clone());
-                let acc = import_result.accounts.first().unwrap().clone();
-                let param: ExportPrivateKeyParam = ExportPrivateKeyParam {
-                    id: import_result.id.to_string(),
-                    password: TEST_PASSWORD.to_string(),
-                    chain_type: acc.chain_type.to_string(),
-                    network: derivations[idx].network.to_string(),
-                    main_address: acc.address.clone(),
-                    path: "".to_string(),
-                };
-                let ret_bytes = call_api("export_private_key", param).unwrap();
-                let export_result: KeystoreCommonExportResult =
-                    KeystoreCommonExportResult::decode(&ret_bytes).unwrap();
+            let ret = call_json(json!({
+                "method": "hd_store_import",
+                "param": {
+                    "mnemonic": TEST_MNEMONIC,
+                    "password": TEST_PASSWORD,
+                    "source": "MNEMONIC",
+                    "name": "test-wallet",
+                    "passwordHint": "imtoken",
+                    "overwrite": true
+                }
+            }));
+            let wallet = &ret["result"];
+            let wallet_id = wallet["id"].as_str().unwrap().to_string();
+            assert_eq!(wallet["name"], "test-wallet");
+            assert!(wallet["createdAt"].is_string());
+
+            let ret = call_json(json!({
+                "method": "keystore_common_exists",
+                "param": { "type": "MNEMONIC", "value": TEST_MNEMONIC }
+            }));
+            assert_eq!(ret["result"]["isExists"], true);
+            assert_eq!(ret["result"]["id"], wallet_id.as_str());
+
+            let ret = call_json(json!({
+                "method": "keystore_common_derive",
+                "param": {
+                    "id": wallet_id,
+                    "password": TEST_PASSWORD,
+                    "derivations": [{ "chainType": "TRON", "path": "m/44'/195'/0'/0/0" }]
+                }
+            }));
+            let address = ret["result"]["accounts"][0]["address"].as_str().unwrap();
+            assert_eq!(address, "TLZnqkrSNLUWNrZMug8u9b6pJ3XcTGbzDV");
+
+            let raw_data = "0a0202a22208e216e254e43ee10840c8cbe4e3df2d5a67080112630a2d747970652e676f6f676c65617069732e636f6d2f70726f746f636f6c2e5472616e73666572436f6e747261637412320a15415c68cc82c87446f602f019e5fd797437f5b79cc212154156a6076cd1537fa317c2606e4edfa4acd3e8e92e18a08d06709084e1e3df2d";
+            let ret = call_json(json!({
+                "method": "sign_tx",
+                "param": {
+                    "id": wallet_id,
+                    "password": TEST_PASSWORD,
+                    "chainType": "TRON",
+                    "address": address,
+                    "input": {
+                        "@type": "type.googleapis.com/transaction.TronTxInput",
+                        "rawData": raw_data
+                    }
+                }
+            }));
+            assert_eq!(ret["result"]["signatures"][0], "bbf5ce0549490613a26c3ac4fc8574e748eabda05662b2e49cea818216b9da18691e78cd6379000e9c8a35c13dfbf620f269be90a078b58799b56dc20da3bdf200");
+
+            let ret = call_json(json!({
+                "method": "keystore_common_verify",
+                "param": { "id": wallet_id, "password": "WRONG PASSWORD" }
+            }));
+            assert_eq!(ret["error"]["error"], "password_incorrect");
+            assert_eq!(ret["error"]["errorCode"], 1002);
+            assert_eq!(ret["error"]["errorCategory"], "CRYPTO");
+
+            let ret = call_json(json!({
+                "method": "keystore_common_unlock_wait",
+                "param": { "id": wallet_id }
+            }));
+            assert_eq!(ret["result"]["remainingSeconds"], "0");
+            assert_eq!(ret["result"]["failedUnlocks"], 1);
+            let result: UnlockWaitResult = serde_json::from_value(ret["result"].clone()).unwrap();
+            assert_eq!(result.remaining_seconds, 0);
+            assert_eq!(serde_json::to_value(&result).unwrap(), ret["result"]);
+
+            let ret = call_json(json!({ "method": "hd_store_import", "param": [] }));
+            assert_eq!(ret["error"]["error"], "invalid_json");
+            assert_eq!(ret["error"]["errorCode"], 6007);
+
+            remove_created_wallet(&wallet_id);
+        })
+    }
 
-                // test export as mainnet
-                assert_eq!(pks[idx], export_result.value);
-                assert_eq!(KeyType::PrivateKey as i32, export_result.r#type);
-                remove_created_wallet(&import_result.id);
+    #[test]
+    pub fn test_btc_fork_external_address_of_account() {
+        run_test(|| {
+            let legacy = Derivation {
+                chain_type: "LITECOIN".to_string(),
+                path: "m/44'/2'/0'/0/0".to_string(),
+                network: "MAINNET".to_string(),
+                seg_wit: "NONE".to_string(),
+                chain_id: "".to_string(),
+            };
+            let segwit = Derivation {
+                path: "m/49'/2'/0'/0/0".to_string(),
+                seg_wit: "P2WPKH".to_string(),
+                ..legacy.clone()
+            };
+            let wallet = import_default_wallet();
+            let param = KeystoreCommonDeriveParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
+                derivations: vec![legacy, segwit],
+            };
+            let ret = call_api("keystore_common_derive", param).unwrap();
+            let accounts = AccountsResponse::decode(ret).unwrap().accounts;
+            assert_ne!(accounts[0].address, accounts[1].address);
+
+            // the receive address at 0/0 is the address of the account itself
+            for account in &accounts {
+                let param = ExternalAddressParam {
+                    id: wallet.id.to_string(),
+                    chain_type: "LITECOIN".to_string(),
+                    external_idx: 0,
+                    address: account.address.to_string(),
+                };
+                let ret = call_api("btc_fork_external_address", param).unwrap();
+                let external_address = ExternalAddressResult::decode(&ret).unwrap();
+                assert_eq!(external_address.address, account.address);
             }
 
-            let wallet = import_default_pk_store();
-            let param: ExportPrivateKeyParam = ExportPrivateKeyParam {
+            let param = ExternalAddressParam {
                 id: wallet.id.to_string(),
-                password: TEST_PASSWORD.to_string(),
                 chain_type: "LITECOIN".to_string(),
-                network: "MAINNET".to_string(),
-                main_address: "Ldfdegx3hJygDuFDUA7Rkzjjx8gfFhP9DP".to_string(),
+                external_idx: 0,
+                address: "".to_string(),
+            };
+            let ret = call_api("btc_fork_external_address", param);
+            assert_eq!(format!("{}", ret.err().unwrap()), "address_invalid");
+
+            remove_created_wallet(&wallet.id);
+        })
+    }
+
+    #[test]
+    pub fn test_btc_fork_external_address() {
+        run_test(|| {
+            for (chain_type, path) in &[
+                ("LITECOIN", "m/44'/2'/0'/0/0"),
+                ("BITCOINCASH", "m/44'/145'/0'/0/0"),
+            ] {
+                for seg_wit in &["NONE", "P2WPKH"] {
+                    if *chain_type == "BITCOINCASH" && *seg_wit == "P2WPKH" {
+                        continue;
+                    }
+                    let derivation = Derivation {
+                        chain_type: chain_type.to_string(),
+                        path: path.to_string(),
+                        network: "MAINNET".to_string(),
+                        seg_wit: seg_wit.to_string(),
+                        chain_id: "".to_string(),
+                    };
+                    let wallet = import_and_derive(derivation);
+
+                    let param = ExternalAddressParam {
+                        id: wallet.id.to_string(),
+                        chain_type: chain_type.to_string(),
+                        external_idx: 0,
+                        address: wallet.accounts[0].address.to_string(),
+                    };
+                    let ret = call_api("btc_fork_external_address", param).unwrap();
+                    let external_address: ExternalAddressResult =
+                        ExternalAddressResult::decode(&ret).unwrap();
+                    assert_eq!(external_address.address, wallet.accounts[0].address);
+                    assert_eq!(external_address.derived_path, "0/0");
+                    assert_eq!(external_address.r#type, "EXTERNAL");
+
+                    let param = ExternalAddressParam {
+                        id: wallet.id.to_string(),
+                        chain_type: chain_type.to_string(),
+                        external_idx: 1,
+                        address: "".to_string(),
+                    };
+                    let ret = call_api("btc_fork_external_address", param).unwrap();
+                    let external_address: ExternalAddressResult =
+                        ExternalAddressResult::decode(&ret).unwrap();
+                    assert_ne!(external_address.address, wallet.accounts[0].address);
+                    assert_eq!(external_address.derived_path, "0/1");
+
+                    let param = ExternalAddressParam {
+                        id: wallet.id.to_string(),
+                        chain_type: "TRON".to_string(),
+                        external_idx: 1,
+                        address: "".to_string(),
+                    };
+                    let ret = call_api("btc_fork_external_address", param);
+                    assert_eq!(format!("{}", ret.err().unwrap()), "account_not_found");
+
+                    remove_created_wallet(&wallet.id);
+                }
+            }
+
+            let derivation = Derivation {
+                chain_type: "LITECOIN".to_string(),
                 path: "".to_string(),
+                network: "MAINNET".to_string(),
+                seg_wit: "NONE".to_string(),
+                chain_id: "".to_string(),
             };
-            let ret = call_api("export_private_key", param);
-            assert!(ret.is_err());
-            assert_eq!(format!("{}", ret.err().unwrap()), "account_not_found");
+            let wallet = import_pk_and_derive(derivation);
+            let param = ExternalAddressParam {
+                id: wallet.id.to_string(),
+                chain_type: "LITECOIN".to_string(),
+                external_idx: 1,
+                address: "".to_string(),
+            };
+            let ret = call_api("btc_fork_external_address", param);
+            assert_eq!(format!("{}", ret.err().unwrap()), "can_not_derive_key");
+            remove_created_wallet(&wallet.id);
         })
     }
 
     #[test]
-    pub fn test_export_private_key_from_hd_store() {
+    pub fn test_watch_only_keystore() {
         run_test(|| {
-            let derivations = vec![
-                Derivation {
-                    chain_type: "BITCOINCASH".to_string(),
-                    path: "m/44'/145'/0'/0/0".to_string(),
-                    network: "MAINNET".to_string(),
-                    seg_wit: "NONE".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "BITCOINCASH".to_string(),
-                    path: "m/44'/145'/0'/0/0".to_string(),
-                    network: "MAINNET".to_string(),
-                    seg_wit: "NONE".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "BITCOINCASH".to_string(),
-                    path: "m/44'/1'/0'/0/0".to_string(),
-                    network: "TESTNET".to_string(),
-                    seg_wit: "NONE".to_string(),
-                    chain_id: "".to_string(),
-                },
-                Derivation {
-                    chain_type: "TRON".to_string(),
-                    path: "m/44'/195'/0'/0/0".to_string(),
-                    network: "".to_string(),
-                    seg_wit: "".to_string(),
-                    chain_id: "".to_string(),
-                },
-            ];
+            let derivation = Derivation {
+                chain_type: "LITECOIN".to_string(),
+                path: "m/44'/2'/0'/0/0".to_string(),
+                network: "MAINNET".to_string(),
+                seg_wit: "NONE".to_string(),
+                chain_id: "".to_string(),
+            };
+            let hd_wallet = import_and_derive(derivation);
+            let hd_account = hd_wallet.accounts[0].clone();
+            let param = HdStoreExtendedPublicKeyParam {
+                id: hd_wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                chain_type: "LITECOIN".to_string(),
+                address: hd_account.address.to_string(),
+            };
+            let ret = call_api("hd_store_extended_public_key", param).unwrap();
+            let xpub = HdStoreExtendedPublicKeyResponse::decode(&ret)
+                .unwrap()
+                .extended_public_key;
+
+            let param = WatchOnlyStoreImportParam {
+                source: "XPUB".to_string(),
+                value: xpub.to_string(),
+                chain_type: "LITECOIN".to_string(),
+                network: "".to_string(),
+                seg_wit: "".to_string(),
+                path: "m/44'/2'/0'".to_string(),
+                password: TEST_PASSWORD.to_string(),
+                name: "watch-only".to_string(),
+                password_hint: "".to_string(),
+                overwrite: true,
+                kdf: None,
+            };
+            let ret = call_api("watch_only_store_import", param).unwrap();
+            let wallet = WalletResult::decode(&ret).unwrap();
+            assert_eq!(wallet.source, "XPUB");
+            assert_eq!(
+                wallet.accounts[0].address,
+                "Ldfdegx3hJygDuFDUA7Rkzjjx8gfFhP9DP"
+            );
+            assert_eq!(wallet.accounts[0].path, "m/44'/2'/0'/0/0");
+            assert_eq!(
+                wallet.accounts[0].extended_xpub_key,
+                hd_account.extended_xpub_key
+            );
 
-            let pks = vec![
-                "L39VXyorp19JfsEJfbD7Tfr4pBEX93RJuVXW7E13C51ZYAhUWbYa",
-                "KyLGdagds7tY1vupT5Kf8C1Cc5wkzzWRK51e4vsh1svCSvYk4Abo",
-                "cN4b1V3cicEexrYXiEhaWEdURyhZiVX6PzAZNFSzZaWfSNZG2cJX",
-                "685634d212eabe016a1cb09d9f1ea1ea757ebe590b9a097d7b1c9379ad280171",
-            ];
-            let export_paths = vec![
-                "m/44'/145'/0'/0/0",
-                "m/44'/145'/0'/0/1",
-                "m/44'/1'/0'/0/1",
-                "m/44'/195'/0'/0/1",
-            ];
-            for idx in 0..derivations.len() {
-                let import_result: WalletResult = import_and_derive(derivations[idx].clone());
-                let acc = import_result.accounts.first().unwrap().clone();
-                let param: ExportPrivateKeyParam = ExportPrivateKeyParam {
-                    id: import_result.id.to_string(),
-                    password: TEST_PASSWORD.to_string(),
-                    chain_type: acc.chain_type.to_string(),
-                    network: derivations[idx].network.to_string(),
-                    main_address: acc.address.to_string(),
-                    path: export_paths[idx].to_string(),
-                };
-                let ret_bytes = call_api("export_private_key", param).unwrap();
-                let export_result: KeystoreCommonExportResult =
-                    KeystoreCommonExportResult::decode(&ret_bytes).unwrap();
+            let param = KeystoreCommonAccountsParam {
+                id: wallet.id.to_string(),
+                include_hidden: false,
+            };
+            let ret = call_api("keystore_common_accounts", param).unwrap();
+            let accounts = AccountsResponse::decode(&ret).unwrap();
+            assert_eq!(accounts.accounts, wallet.accounts);
 
-                assert_eq!(pks[idx], export_result.value);
-                assert_eq!(KeyType::PrivateKey as i32, export_result.r#type);
-                remove_created_wallet(&import_result.id);
-            }
+            let param = ExternalAddressParam {
+                id: wallet.id.to_string(),
+                chain_type: "LITECOIN".to_string(),
+                external_idx: 1,
+                address: "".to_string(),
+            };
+            let ret = call_api("btc_fork_external_address", param.clone()).unwrap();
+            let external_address = ExternalAddressResult::decode(&ret).unwrap();
+            let param = ExternalAddressParam {
+                id: hd_wallet.id.to_string(),
+                ..param
+            };
+            let ret = call_api("btc_fork_external_address", param).unwrap();
+            assert_eq!(
+                external_address,
+                ExternalAddressResult::decode(&ret).unwrap()
+            );
 
-            let import_result: WalletResult = import_default_wallet();
+            let unspents = vec![Utxo {
+                tx_hash: "a477af6b2667c29670467e4e0728b685ee07b240235771862318e29ddbe58458"
+                    .to_string(),
+                vout: 0,
+                amount: 1000000,
+                address: "Ldfdegx3hJygDuFDUA7Rkzjjx8gfFhP9DP".to_string(),
+                script_pub_key: "".to_string(),
+                derived_path: "0/0".to_string(),
+                sequence: 0,
+            }];
+            let tx_input = BtcForkTxInput {
+                to: external_address.address.to_string(),
+                amount: 500000,
+                unspents,
+                fee: 100000,
+                change_address_index: 1u32,
+                change_address: "".to_string(),
+                network: "MAINNET".to_string(),
+                seg_wit: "NONE".to_string(),
+            };
+            let tx = SignParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
+                chain_type: "LITECOIN".to_string(),
+                address: "Ldfdegx3hJygDuFDUA7Rkzjjx8gfFhP9DP".to_string(),
+                input: Some(::prost_types::Any {
+                    type_url: "imtoken".to_string(),
+                    value: encode_message(tx_input).unwrap(),
+                }),
+            };
+            let ret = call_api("btc_fork_prepare_tx", tx.clone()).unwrap();
+            let unsigned_tx = BtcForkUnsignedTxOutput::decode(&ret).unwrap();
+            assert!(unsigned_tx
+                .raw_tx
+                .contains("5884e5db9de218238671572340b207ee85b628074e7e467096c267266baf77a40000000000ffffffff"));
 
-            let param: ExportPrivateKeyParam = ExportPrivateKeyParam {
-                id: import_result.id.to_string(),
+            let ret = call_api("sign_tx", tx);
+            assert_eq!(format!("{}", ret.err().unwrap()), "watch_only_cannot_sign");
+
+            let param = ExportPrivateKeyParam {
+                id: wallet.id.to_string(),
                 password: TEST_PASSWORD.to_string(),
                 chain_type: "LITECOIN".to_string(),
                 network: "MAINNET".to_string(),
                 main_address: "Ldfdegx3hJygDuFDUA7Rkzjjx8gfFhP9DP".to_string(),
-                path: "m/44'/2'/0'/0/0".to_string(),
+                path: "".to_string(),
             };
             let ret = call_api("export_private_key", param);
-            assert!(ret.is_err());
-            assert_eq!(format!("{}", ret.err().unwrap()), "account_not_found");
-            remove_created_wallet(&import_result.id);
+            assert_eq!(format!("{}", ret.err().unwrap()), "watch_only_cannot_sign");
+
+            remove_created_wallet(&wallet.id);
+            remove_created_wallet(&hd_wallet.id);
         })
     }
 
     #[test]
-    pub fn test_import_to_pk_which_from_hd() {
+    pub fn test_watch_only_address() {
         run_test(|| {
-            let param: PrivateKeyStoreImportParam = PrivateKeyStoreImportParam {
-                private_key: "L39VXyorp19JfsEJfbD7Tfr4pBEX93RJuVXW7E13C51ZYAhUWbYa".to_string(),
+            let param = WatchOnlyStoreImportParam {
+                source: "ADDRESS".to_string(),
+                value: "TXo4VDm8Qc5YBSjPhu8pMaxzTApSvLshWG".to_string(),
+                chain_type: "TRON".to_string(),
+                network: "".to_string(),
+                seg_wit: "".to_string(),
+                path: "".to_string(),
                 password: TEST_PASSWORD.to_string(),
-                overwrite: true,
+                name: "watch-only".to_string(),
+                password_hint: "".to_string(),
+                overwrite: false,
+                kdf: None,
             };
+            let ret = call_api("watch_only_store_import", param.clone()).unwrap();
+            let wallet = WalletResult::decode(&ret).unwrap();
+            assert_eq!(
+                wallet.accounts[0].address,
+                "TXo4VDm8Qc5YBSjPhu8pMaxzTApSvLshWG"
+            );
+            assert_eq!(wallet.accounts[0].extended_xpub_key, "");
 
-            let ret = private_key_store_import(&encode_message(param).unwrap()).unwrap();
-            let wallet: WalletResult = WalletResult::decode(ret).unwrap();
+            let ret = call_api("watch_only_store_import", param.clone());
+            assert_eq!(format!("{}", ret.err().unwrap()), "address_already_exist");
 
-            let derivation = Derivation {
-                chain_type: "BITCOINCASH".to_string(),
-                path: "".to_string(),
+            let input = TronMessageInput {
+                value: "645c0b7b58158babbfa6c6cd5a48aa7340a8749176b120e8516216787a13dc76"
+                    .to_string(),
+                is_hex: true,
+                is_tron_header: true,
+            };
+            let tx = SignParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
+                chain_type: "TRON".to_string(),
+                address: "TXo4VDm8Qc5YBSjPhu8pMaxzTApSvLshWG".to_string(),
+                input: Some(::prost_types::Any {
+                    type_url: "imtoken".to_string(),
+                    value: encode_message(input).unwrap(),
+                }),
+            };
+            let ret = call_api("tron_sign_msg", tx);
+            assert_eq!(format!("{}", ret.err().unwrap()), "watch_only_cannot_sign");
+
+            let param = WatchOnlyStoreImportParam {
+                value: "TXo4VDm8Qc5YBSjPhu8pMaxzTApSvLsh".to_string(),
+                ..param
+            };
+            let ret = call_api("watch_only_store_import", param.clone());
+            assert_eq!(format!("{}", ret.err().unwrap()), "address_invalid");
+
+            let param = WatchOnlyStoreImportParam {
+                value: "Ldfdegx3hJygDuFDUA7Rkzjjx8gfFhP9DP".to_string(),
+                chain_type: "LITECOIN".to_string(),
                 network: "MAINNET".to_string(),
                 seg_wit: "NONE".to_string(),
-                chain_id: "".to_string(),
+                ..param
             };
+            let ret = call_api("watch_only_store_import", param.clone());
+            assert_eq!(
+                format!("{}", ret.err().unwrap()),
+                "unsupported_watch_only_source"
+            );
 
-            let derive_param = KeystoreCommonDeriveParam {
-                id: wallet.id.to_string(),
-                password: TEST_PASSWORD.to_string(),
-                derivations: vec![derivation],
+            let param = WatchOnlyStoreImportParam {
+                source: "PUBLIC_KEY".to_string(),
+                value: "033d710ab45bb54ac99618ad23b3c1da661631aa25f23bfe9d22b41876f1d46e4e"
+                    .to_string(),
+                network: "TESTNET".to_string(),
+                ..param
             };
-            let ret_bytes = keystore_common_derive(&encode_message(derive_param).unwrap()).unwrap();
-            let ret: AccountsResponse = AccountsResponse::decode(ret_bytes).unwrap();
+            let ret = call_api("watch_only_store_import", param).unwrap();
+            let pk_wallet = WalletResult::decode(&ret).unwrap();
+            assert_eq!(pk_wallet.source, "PUBLIC_KEY");
             assert_eq!(
-                "qzld7dav7d2sfjdl6x9snkvf6raj8lfxjcj5fa8y2r",
-                ret.accounts.first().unwrap().address
+                pk_wallet.accounts[0].address,
+                "mkeNU5nVnozJiaACDELLCsVUc8Wxoh1rQN"
             );
+
             remove_created_wallet(&wallet.id);
+            remove_created_wallet(&pk_wallet.id);
         })
     }
 
     #[test]
-    pub fn test_keystore_common_verify() {
+    pub fn test_hd_store_extended_public_key() {
         run_test(|| {
-            let wallets = vec![import_default_pk_store(), import_default_wallet()];
-            for wallet in wallets {
-                let param: WalletKeyParam = WalletKeyParam {
+            for (network, version) in &[("MAINNET", "xpub"), ("TESTNET", "tpub")] {
+                let derivation = Derivation {
+                    chain_type: "LITECOIN".to_string(),
+                    path: "m/44'/2'/0'/0/0".to_string(),
+                    network: network.to_string(),
+                    seg_wit: "NONE".to_string(),
+                    chain_id: "".to_string(),
+                };
+                let wallet = import_and_derive(derivation);
+                let account = &wallet.accounts[0];
+
+                let param = HdStoreExtendedPublicKeyParam {
                     id: wallet.id.to_string(),
                     password: TEST_PASSWORD.to_string(),
+                    chain_type: "LITECOIN".to_string(),
+                    address: account.address.to_string(),
                 };
-
-                let ret_bytes = call_api("keystore_common_verify", param).unwrap();
-                let result: Response = Response::decode(&ret_bytes).unwrap();
-                assert!(result.is_success);
-
-                let param: WalletKeyParam = WalletKeyParam {
+                let ret = call_api("hd_store_extended_public_key", param).unwrap();
+                let rsp: HdStoreExtendedPublicKeyResponse =
+                    HdStoreExtendedPublicKeyResponse::decode(&ret).unwrap();
+                assert!(rsp.extended_public_key.starts_with(version));
+
+                let config = TOKEN_CORE.config();
+                let key = hex::decode(&config.xpub_common_key).unwrap();
+                let iv = hex::decode(&config.xpub_common_iv).unwrap();
+                let encrypted = base64::decode(&account.extended_xpub_key).unwrap();
+                let decrypted = decrypt_pkcs7(&encrypted, &key, &iv).unwrap();
+                assert_eq!(rsp.extended_public_key.as_bytes(), decrypted.as_slice());
+
+                let param = HdStoreExtendedPublicKeyParam {
                     id: wallet.id.to_string(),
                     password: "WRONG PASSWORD".to_string(),
+                    chain_type: "LITECOIN".to_string(),
+                    address: account.address.to_string(),
                 };
-
-                let ret = call_api("keystore_common_verify", param);
-                assert!(ret.is_err());
+                let ret = call_api("hd_store_extended_public_key", param);
                 assert_eq!(format!("{}", ret.err().unwrap()), "password_incorrect");
+
+                let param = HdStoreExtendedPublicKeyParam {
+                    id: wallet.id.to_string(),
+                    password: TEST_PASSWORD.to_string(),
+                    chain_type: "TRON".to_string(),
+                    address: account.address.to_string(),
+                };
+                let ret = call_api("hd_store_extended_public_key", param);
+                assert_eq!(format!("{}", ret.err().unwrap()), "account_not_found");
+
+                remove_created_wallet(&wallet.id);
             }
         })
     }
 
+    #[test]
+    pub fn test_derived_key() {
+        run_test(|| {
+            let wallet = import_default_wallet();
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: "WRONG PASSWORD".to_string(),
+            };
+            let ret = call_api("get_derived_key", param);
+            assert_eq!(format!("{}", ret.err().unwrap()), "password_incorrect");
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+            };
+            let ret = call_api("get_derived_key", param).unwrap();
+            let derived_key_result: DerivedKeyResult = DerivedKeyResult::decode(&ret).unwrap();
+            assert_eq!(derived_key_result.id, wallet.id);
+            let derived_key = derived_key_result.derived_key;
+
+            let param = VerifyDerivedKeyParam {
+                id: wallet.id.to_string(),
+                derived_key: derived_key.to_string(),
+            };
+            let ret = call_api("verify_derived_key", param).unwrap();
+            let verified: DerivedKeyResult = DerivedKeyResult::decode(&ret).unwrap();
+            assert_eq!(verified.derived_key, derived_key);
+
+            let param = VerifyDerivedKeyParam {
+                id: wallet.id.to_string(),
+                derived_key: "00".repeat(64),
+            };
+            let ret = call_api("verify_derived_key", param);
+            assert_eq!(format!("{}", ret.err().unwrap()), "derived_key_incorrect");
+
+            let param = CacheDerivedKeyParam {
+                id: wallet.id.to_string(),
+                derived_key: "00".repeat(64),
+                temp_password: "TEMP_PASSWORD".to_string(),
+            };
+            let ret = call_api("cache_derived_key", param);
+            assert_eq!(format!("{}", ret.err().unwrap()), "derived_key_incorrect");
+
+            let param = CacheDerivedKeyParam {
+                id: wallet.id.to_string(),
+                derived_key: derived_key.to_string(),
+                temp_password: "TEMP_PASSWORD".to_string(),
+            };
+            call_api("cache_derived_key", param).unwrap();
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: "TEMP_PASSWORD".to_string(),
+            };
+            let ret = call_api("export_mnemonic", param).unwrap();
+            let export_result: KeystoreCommonExportResult =
+                KeystoreCommonExportResult::decode(&ret).unwrap();
+            assert_eq!(export_result.value, TEST_MNEMONIC);
+
+            let param = WalletKeyParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+            };
+            let ret = call_api("keystore_common_verify", param).unwrap();
+            let rsp: Response = Response::decode(ret).unwrap();
+            assert!(rsp.is_success);
+
+            remove_created_wallet(&wallet.id);
+        })
+    }
+
     #[test]
     pub fn test_keystore_common_delete() {
         run_test(|| {
@@ -1075,9 +3234,12 @@ mod tests {
                 private_key: "5JZc7wGRUr4J1RHDcM9ySWKLfQ2xjRUEo612qC4RLJ3G7jzJ4qx".to_string(),
                 password: TEST_PASSWORD.to_string(),
                 overwrite: true,
+                kdf: None,
             };
 
-            let ret_bytes = private_key_store_import(&encode_message(param).unwrap()).unwrap();
+            let ret_bytes = TOKEN_CORE
+                .private_key_store_import(&encode_message(param).unwrap())
+                .unwrap();
             let import_result: WalletResult = WalletResult::decode(&ret_bytes).unwrap();
 
             let param: WalletKeyParam = WalletKeyParam {
@@ -1101,6 +3263,7 @@ mod tests {
             let param: KeystoreCommonExistsParam = KeystoreCommonExistsParam {
                 r#type: KeyType::PrivateKey as i32,
                 value: "5JZc7wGRUr4J1RHDcM9ySWKLfQ2xjRUEo612qC4RLJ3G7jzJ4qx".to_string(),
+                passphrase: "".to_string(),
             };
 
             let ret_bytes = call_api("keystore_common_exists", param).unwrap();
@@ -1118,6 +3281,7 @@ mod tests {
             let param: KeystoreCommonExistsParam = KeystoreCommonExistsParam {
                 r#type: KeyType::Mnemonic as i32,
                 value: format!("{}", TEST_MNEMONIC).to_string(),
+                passphrase: "".to_string(),
             };
 
             let ret_bytes = call_api("keystore_common_exists", param).unwrap();
@@ -1130,6 +3294,7 @@ mod tests {
             let param: KeystoreCommonExistsParam = KeystoreCommonExistsParam {
                 r#type: KeyType::PrivateKey as i32,
                 value: "L2hfzPyVC1jWH7n2QLTe7tVTb6btg9smp5UVzhEBxLYaSFF7sCZB".to_string(),
+                passphrase: "".to_string(),
             };
 
             let ret_bytes = call_api("keystore_common_exists", param).unwrap();
@@ -1142,6 +3307,7 @@ mod tests {
                 r#type: KeyType::PrivateKey as i32,
                 value: "a392604efc2fad9c0b3da43b5f698a2e3f270f170d859912be0d54742275c5f6"
                     .to_string(),
+                passphrase: "".to_string(),
             };
 
             let ret_bytes = call_api("keystore_common_exists", param).unwrap();
@@ -1159,6 +3325,7 @@ mod tests {
 
             let param: KeystoreCommonAccountsParam = KeystoreCommonAccountsParam {
                 id: wallet.id.to_string(),
+                include_hidden: false,
             };
 
             let ret_bytes = call_api("keystore_common_accounts", param).unwrap();
@@ -1175,6 +3342,7 @@ mod tests {
             let param = KeystoreCommonDeriveParam {
                 id: wallet.id.to_string(),
                 password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
                 derivations,
             };
             let derived_accounts_bytes = call_api("keystore_common_derive", param).unwrap();
@@ -1257,6 +3425,7 @@ mod tests {
             let tx = SignParam {
                 id: wallet.id.to_string(),
                 password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
                 chain_type: "NERVOS".to_string(),
                 address: wallet.accounts.first().unwrap().address.to_string(),
                 input: Some(::prost_types::Any {
@@ -1293,6 +3462,7 @@ mod tests {
             let tx = SignParam {
                 id: wallet.id.to_string(),
                 password: "WRONG PASSWORD".to_string(),
+                session_token: "".to_string(),
                 chain_type: "TRON".to_string(),
                 address: wallet.accounts.first().unwrap().address.to_string(),
                 input: Some(::prost_types::Any {
@@ -1308,6 +3478,7 @@ mod tests {
             let tx = SignParam {
                 id: wallet.id.to_string(),
                 password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
                 chain_type: "TRON1".to_string(),
                 address: wallet.accounts.first().unwrap().address.to_string(),
                 input: Some(::prost_types::Any {
@@ -1323,6 +3494,7 @@ mod tests {
             let tx = SignParam {
                 id: wallet.id.to_string(),
                 password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
                 chain_type: "TRON".to_string(),
                 address: wallet.accounts.first().unwrap().address.to_string(),
                 input: Some(::prost_types::Any {
@@ -1339,6 +3511,122 @@ mod tests {
         })
     }
 
+    #[test]
+    pub fn test_unlock_session() {
+        run_test(|| {
+            let derivation = Derivation {
+                chain_type: "TRON".to_string(),
+                path: "m/44'/195'/0'/0/0".to_string(),
+                network: "".to_string(),
+                seg_wit: "".to_string(),
+                chain_id: "".to_string(),
+            };
+            let wallet = import_and_derive(derivation);
+            let address = wallet.accounts.first().unwrap().address.to_string();
+
+            let param = UnlockSessionParam {
+                id: wallet.id.to_string(),
+                password: "WRONG PASSWORD".to_string(),
+                ttl: 0,
+                chain_type: "".to_string(),
+                address: "".to_string(),
+                max_uses: 0,
+            };
+            let ret = call_api("unlock_session", param);
+            assert_eq!(format!("{}", ret.err().unwrap()), "password_incorrect");
+
+            let param = UnlockSessionParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                ttl: 60,
+                chain_type: "TRON".to_string(),
+                address: address.to_string(),
+                max_uses: 3,
+            };
+            let ret = call_api("unlock_session", param).unwrap();
+            let session: UnlockSessionResult = UnlockSessionResult::decode(&ret).unwrap();
+            assert_eq!(session.id, wallet.id);
+            assert!(!session.session_token.is_empty());
+
+            let raw_data = "0a0202a22208e216e254e43ee10840c8cbe4e3df2d5a67080112630a2d747970652e676f6f676c65617069732e636f6d2f70726f746f636f6c2e5472616e73666572436f6e747261637412320a15415c68cc82c87446f602f019e5fd797437f5b79cc212154156a6076cd1537fa317c2606e4edfa4acd3e8e92e18a08d06709084e1e3df2d".to_string();
+            let input_value = encode_message(TronTxInput { raw_data }).unwrap();
+            let tx = SignParam {
+                id: wallet.id.to_string(),
+                password: "".to_string(),
+                session_token: session.session_token.to_string(),
+                chain_type: "TRON".to_string(),
+                address: address.to_string(),
+                input: Some(::prost_types::Any {
+                    type_url: "imtoken".to_string(),
+                    value: input_value.clone(),
+                }),
+            };
+            let ret = call_api("sign_tx", tx.clone()).unwrap();
+            let output: TronTxOutput = TronTxOutput::decode(&ret).unwrap();
+            let expected_sign = "bbf5ce0549490613a26c3ac4fc8574e748eabda05662b2e49cea818216b9da18691e78cd6379000e9c8a35c13dfbf620f269be90a078b58799b56dc20da3bdf200";
+            assert_eq!(expected_sign, output.signatures[0]);
+
+            let derivation = Derivation {
+                chain_type: "LITECOIN".to_string(),
+                path: "m/44'/2'/0'/0/0".to_string(),
+                network: "MAINNET".to_string(),
+                seg_wit: "NONE".to_string(),
+                chain_id: "".to_string(),
+            };
+            let param = KeystoreCommonDeriveParam {
+                id: wallet.id.to_string(),
+                password: "".to_string(),
+                session_token: session.session_token.to_string(),
+                derivations: vec![derivation],
+            };
+            let ret = call_api("keystore_common_derive", param);
+            assert_eq!(format!("{}", ret.err().unwrap()), "session_scope_mismatch");
+
+            // the session of an address derives the other accounts of its chain
+            let derivation = Derivation {
+                chain_type: "TRON".to_string(),
+                path: "m/44'/195'/0'/0/1".to_string(),
+                network: "".to_string(),
+                seg_wit: "".to_string(),
+                chain_id: "".to_string(),
+            };
+            let param = KeystoreCommonDeriveParam {
+                id: wallet.id.to_string(),
+                password: "".to_string(),
+                session_token: session.session_token.to_string(),
+                derivations: vec![derivation],
+            };
+            let ret = call_api("keystore_common_derive", param).unwrap();
+            let rsp: AccountsResponse = AccountsResponse::decode(ret).unwrap();
+            assert_ne!(rsp.accounts[0].address, address);
+
+            // the third use is the last one
+            assert!(call_api("sign_tx", tx.clone()).is_ok());
+            let ret = call_api("sign_tx", tx);
+            assert_eq!(format!("{}", ret.err().unwrap()), "session_not_found");
+
+            let param = UnlockSessionParam {
+                id: wallet.id.to_string(),
+                password: TEST_PASSWORD.to_string(),
+                ttl: 60,
+                chain_type: "".to_string(),
+                address: "".to_string(),
+                max_uses: 0,
+            };
+            let ret = call_api("unlock_session", param).unwrap();
+            let session: UnlockSessionResult = UnlockSessionResult::decode(&ret).unwrap();
+            let param = LockSessionParam {
+                session_token: session.session_token.to_string(),
+            };
+            let ret = call_api("lock_session", param.clone()).unwrap();
+            assert!(Response::decode(&ret).unwrap().is_success);
+            let ret = call_api("lock_session", param).unwrap();
+            assert!(!Response::decode(&ret).unwrap().is_success);
+
+            remove_created_wallet(&wallet.id);
+        })
+    }
+
     #[test]
     pub fn test_sign_tron_tx_by_pk() {
         run_test(|| {
@@ -1354,6 +3642,7 @@ mod tests {
             let param = KeystoreCommonDeriveParam {
                 id: import_result.id.to_string(),
                 password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
                 derivations: vec![derivation],
             };
 
@@ -1365,6 +3654,7 @@ mod tests {
             let tx = SignParam {
                 id: import_result.id.to_string(),
                 password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
                 chain_type: "TRON".to_string(),
                 address: rsp.accounts.first().unwrap().address.to_string(),
                 input: Some(::prost_types::Any {
@@ -1423,6 +3713,7 @@ mod tests {
                 let tx = SignParam {
                     id: wallet.id.to_string(),
                     password: TEST_PASSWORD.to_string(),
+                    session_token: "".to_string(),
                     chain_type: "TRON".to_string(),
                     address: wallet.accounts.first().unwrap().address.to_string(),
                     input: Some(::prost_types::Any {
@@ -1462,6 +3753,7 @@ mod tests {
                 let param = KeystoreCommonDeriveParam {
                     id: import_result.id.to_string(),
                     password: TEST_PASSWORD.to_string(),
+                    session_token: "".to_string(),
                     derivations: vec![derivation],
                 };
 
@@ -1493,6 +3785,7 @@ mod tests {
                 let tx = SignParam {
                     id: import_result.id.to_string(),
                     password: TEST_PASSWORD.to_string(),
+                    session_token: "".to_string(),
                     chain_type: chain_type.to_string(),
                     address: rsp.accounts.first().unwrap().address.to_string(),
                     input: Some(::prost_types::Any {
@@ -1530,6 +3823,7 @@ mod tests {
             let tx = SignParam {
                 id: wallet.id.to_string(),
                 password: TEST_PASSWORD.to_string(),
+                session_token: "".to_string(),
                 chain_type: "TRON".to_string(),
                 address: wallet.accounts.first().unwrap().address.to_string(),
                 input: Some(::prost_types::Any {
@@ -1538,7 +3832,7 @@ mod tests {
                 }),
             };
             {
-                let map = KEYSTORE_MAP.read();
+                let map = TOKEN_CORE.keystore_map().read();
                 let keystore: &Keystore = map.get(&wallet.id).unwrap();
                 assert!(keystore.is_locked());
             }
@@ -1549,7 +3843,7 @@ mod tests {
             assert_eq!(expected_sign, output.signatures[0]);
 
             {
-                let map = KEYSTORE_MAP.read();
+                let map = TOKEN_CORE.keystore_map().read();
                 let keystore: &Keystore = map.get(&wallet.id).unwrap();
                 assert!(keystore.is_locked());
             }
@@ -1572,7 +3866,7 @@ mod tests {
             let rsp: Response = Response::decode(err_bytes).unwrap();
             assert!(!rsp.is_success);
             assert_eq!(rsp.error, "test_unlock_then_crash");
-            let map = KEYSTORE_MAP.read();
+            let map = TOKEN_CORE.keystore_map().read();
             let keystore: &Keystore = map.get(&wallet.id).unwrap();
             assert!(keystore.is_locked())
         });
@@ -1582,5 +3876,6 @@ mod tests {
         let full_file_path = format!("{}/{}.json", "/tmp/imtoken/wallets", wid);
         let p = Path::new(&full_file_path);
         remove_file(p).expect("should remove file");
+        let _ = remove_file(format!("{}.bak", full_file_path));
     }
 }
@@ -0,0 +1,139 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tcx_chain::tcx_ensure;
+use tcx_chain::{Keystore, KeystoreGuard};
+use tcx_crypto::numberic_util::random_iv;
+use tcx_crypto::{Secret, Zeroizing};
+
+use crate::error_handling::{Error, Result};
+use crate::token_core::TokenCore;
+
+/// ttl of a session when the caller doesn't set one
+pub const DEFAULT_SESSION_TTL: u32 = 300;
+
+/// An unlocked keystore which can be used without the password until it expires
+///
+/// The session keeps the derived key of the keystore instead of the unlocked secrets, so the
+/// expensive kdf runs only once while `landingpad` still locks every keystore after each call.
+pub(crate) struct Session {
+    keystore_id: String,
+    derived_key: Secret<String>,
+    expires_at: Instant,
+    chain_type: String,
+    address: String,
+    remaining_uses: Option<u32>,
+}
+
+impl Session {
+    fn is_expired(&self) -> bool {
+        Instant::now() >= self.expires_at
+    }
+
+    /// empty chain type or address of the scope means any. Without an address, for deriving
+    /// the accounts of the chain type, only the chain type is checked
+    fn permits(&self, chain_type: &str, address: Option<&str>) -> bool {
+        (self.chain_type.is_empty() || self.chain_type == chain_type)
+            && (self.address.is_empty() || address.map_or(true, |address| self.address == address))
+    }
+}
+
+impl TokenCore {
+    /// returns the session token and the unix timestamp it expires at, `derived_key` is the
+    /// verified output of the kdf
+    pub(crate) fn open_session(
+        &self,
+        keystore: &Keystore,
+        derived_key: Secret<String>,
+        ttl: u32,
+        chain_type: &str,
+        address: &str,
+        max_uses: u32,
+    ) -> Result<(String, i64)> {
+        let ttl = if ttl == 0 { DEFAULT_SESSION_TTL } else { ttl };
+        let session = Session {
+            keystore_id: keystore.id(),
+            derived_key,
+            expires_at: Instant::now() + Duration::from_secs(u64::from(ttl)),
+            chain_type: chain_type.to_owned(),
+            address: address.to_owned(),
+            remaining_uses: if max_uses == 0 { None } else { Some(max_uses) },
+        };
+        let token = hex::encode(random_iv(32));
+        let expires_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() + u64::from(ttl);
+
+        let mut sessions = self.sessions().write();
+        sessions.retain(|_, session| !session.is_expired());
+        sessions.insert(token.to_owned(), session);
+        Ok((token, expires_at as i64))
+    }
+
+    /// returns false if the token doesn't exist or has already expired
+    pub(crate) fn close_session(&self, token: &str) -> bool {
+        match self.sessions().write().remove(token) {
+            Some(session) => !session.is_expired(),
+            None => false,
+        }
+    }
+
+    pub(crate) fn close_keystore_sessions(&self, keystore_id: &str) {
+        self.sessions()
+            .write()
+            .retain(|_, session| session.keystore_id != keystore_id);
+    }
+
+    /// close the sessions of a reloaded keystore which can't unlock it anymore, its password may
+    /// have been changed by another process
+    pub(crate) fn close_stale_sessions(&self, keystore: &Keystore) {
+        let keystore_id = keystore.id();
+        self.sessions().write().retain(|_, session| {
+            session.keystore_id != keystore_id || keystore.verify_derived_key(&session.derived_key)
+        });
+    }
+
+    /// unlock the keystore by the session token if there is one, otherwise by the password
+    ///
+    /// Every chain type in `chain_types` must be in the scope of the session, and the address
+    /// unless it's `None`. Each call consumes one use of the session.
+    pub(crate) fn unlock_keystore<'a>(
+        &self,
+        keystore: &'a mut Keystore,
+        password: &str,
+        session_token: &str,
+        chain_types: &[&str],
+        address: Option<&str>,
+    ) -> Result<KeystoreGuard<'a>> {
+        if session_token.is_empty() {
+            return self.unlock_by_password(keystore, password);
+        }
+
+        let derived_key = {
+            let mut sessions = self.sessions().write();
+            let session = sessions
+                .get_mut(session_token)
+                .ok_or(Error::SessionNotFound)?;
+            if session.is_expired() {
+                sessions.remove(session_token);
+                return Err(Error::SessionExpired.into());
+            }
+            tcx_ensure!(
+                session.keystore_id == keystore.id()
+                    && chain_types
+                        .iter()
+                        .all(|chain_type| session.permits(chain_type, address)),
+                Error::SessionScopeMismatch
+            );
+
+            let derived_key = Zeroizing::new(session.derived_key.to_string());
+            if let Some(remaining_uses) = session.remaining_uses {
+                if remaining_uses <= 1 {
+                    sessions.remove(session_token);
+                } else {
+                    session.remaining_uses = Some(remaining_uses - 1);
+                }
+            }
+            derived_key
+        };
+
+        KeystoreGuard::unlock_by_derived_key(keystore, &derived_key)
+    }
+}
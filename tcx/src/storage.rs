@@ -0,0 +1,488 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use parking_lot::{Mutex, RwLock};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior, NO_PARAMS};
+use tcx_chain::tcx_ensure;
+use tcx_crypto::hash::str_dsha256;
+
+use crate::error_handling::{Error, Result};
+
+/// the prefix of the entries which can't be loaded, they are kept for the user to recover
+pub const QUARANTINE: &str = "quarantine/";
+
+/// the quarantined entry of `name`, the time keeps the entries quarantined before
+pub fn quarantined_name(name: &str) -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default();
+    format!("{}{}.{}", QUARANTINE, name, millis)
+}
+
+fn not_found(name: &str) -> failure::Error {
+    io::Error::new(io::ErrorKind::NotFound, name.to_string()).into()
+}
+
+fn already_exists(name: &str) -> failure::Error {
+    io::Error::new(io::ErrorKind::AlreadyExists, name.to_string()).into()
+}
+
+/// Where the keystore json lives, every entry is named `{id}.json`
+///
+/// `list` returns the entries to scan, without the quarantined ones.
+pub trait KeystoreStorage: Send + Sync {
+    fn list(&self) -> Result<Vec<String>>;
+
+    fn load(&self, name: &str) -> Result<String>;
+
+    /// replace the entry as a whole, a failed save must leave the old content readable
+    fn save(&self, name: &str, content: &str) -> Result<()>;
+
+    /// save only if the entry is still at `revision`, or doesn't exist if `None`. The check and
+    /// the write are done under the lock of the storage, so a change made by another process
+    /// since the entry was loaded isn't overwritten
+    fn save_if_unchanged(&self, name: &str, content: &str, _revision: Option<&str>) -> Result<()> {
+        self.save(name, content)
+    }
+
+    fn delete(&self, name: &str) -> Result<()>;
+
+    /// fails if `to` exists, an entry is never replaced by a rename
+    fn rename(&self, from: &str, to: &str) -> Result<()>;
+
+    /// the content before the last save to restore an entry damaged afterwards, only kept by the
+    /// backends without transactions
+    fn load_backup(&self, _name: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// drop the backup, e.g. it holds the ciphertext of the password the entry was changed from
+    fn delete_backup(&self, _name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// a token changing with the content of the entry, so the changes made by the other
+    /// processes sharing the storage can be found. `None` if the backend is private to the process
+    fn revision(&self, _name: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// The storage backends selectable by `init_token_core_x`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StorageType {
+    /// one json file per keystore in the file dir
+    Directory,
+    /// nothing is written, for tests and ephemeral sessions
+    Memory,
+    /// a single `keystores.sqlite` database in the file dir
+    Sqlite,
+}
+
+impl Default for StorageType {
+    fn default() -> Self {
+        StorageType::Directory
+    }
+}
+
+impl FromStr for StorageType {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "" | "directory" => Ok(StorageType::Directory),
+            "memory" => Ok(StorageType::Memory),
+            "sqlite" => Ok(StorageType::Sqlite),
+            _ => Err(Error::UnsupportedStorage.into()),
+        }
+    }
+}
+
+impl StorageType {
+    pub fn open(self, file_dir: &str) -> Result<Arc<dyn KeystoreStorage>> {
+        let storage: Arc<dyn KeystoreStorage> = match self {
+            StorageType::Directory => Arc::new(DirectoryStorage::new(file_dir)),
+            StorageType::Memory => Arc::new(MemoryStorage::default()),
+            StorageType::Sqlite => Arc::new(SqliteStorage::open(
+                &Path::new(file_dir).join("keystores.sqlite"),
+            )?),
+        };
+        Ok(storage)
+    }
+}
+
+pub struct DirectoryStorage {
+    dir: PathBuf,
+    /// name => the stamp of the file when it was hashed and the hash, a file is only read again
+    /// by `revision` once its stamp changes
+    revisions: Mutex<HashMap<String, (FileStamp, String)>>,
+}
+
+/// The metadata changing when a file is replaced. Every save renames a new temp file into place,
+/// so the inode changes even if the size and the modified time don't
+#[derive(Clone, PartialEq)]
+struct FileStamp {
+    inode: u64,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Result<FileStamp> {
+        let metadata = fs::metadata(path)?;
+        Ok(FileStamp {
+            inode: inode(&metadata),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> u64 {
+    0
+}
+
+impl DirectoryStorage {
+    pub fn new(dir: &str) -> Self {
+        DirectoryStorage {
+            dir: PathBuf::from(dir),
+            revisions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn backup_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.bak", name))
+    }
+
+    /// an advisory lock on the directory shared with the other processes, it's held until the
+    /// returned file is dropped
+    fn lock(&self) -> Result<fs::File> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(self.dir.join(".lock"))?;
+        lock_exclusive(&file)?;
+        Ok(file)
+    }
+
+    /// persist the renamed directory entry
+    #[cfg(unix)]
+    fn sync_dir(&self) -> Result<()> {
+        fs::File::open(&self.dir)?.sync_all()?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn sync_dir(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// the save under the lock of the directory
+    fn save_locked(&self, name: &str, content: &str) -> Result<()> {
+        // write a synced temp file and rename it, so the keystore file is either old or new even
+        // if the process or the device dies in the middle
+        let tmp_path = self.dir.join(format!("{}.tmp", name));
+        write_synced(&tmp_path, content)?;
+
+        // the previous version is kept as the backup, the hard link shares the synced content of
+        // the old file which stays in place until the rename
+        let path = self.dir.join(name);
+        if path.exists() {
+            self.delete_backup_locked(name)?;
+            let backup = self.backup_path(name);
+            if fs::hard_link(&path, &backup).is_err() {
+                write_synced(&backup, &fs::read_to_string(&path)?)?;
+            }
+        }
+        fs::rename(&tmp_path, &path)?;
+        self.sync_dir()
+    }
+
+    fn delete_backup_locked(&self, name: &str) -> Result<()> {
+        let backup = self.backup_path(name);
+        if backup.exists() {
+            fs::remove_file(backup)?;
+        }
+        Ok(())
+    }
+}
+
+impl KeystoreStorage for DirectoryStorage {
+    fn list(&self) -> Result<Vec<String>> {
+        let mut names = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) if name.ends_with(".json") && path.is_file() => name.to_string(),
+                _ => continue,
+            };
+            names.push(name);
+        }
+        Ok(names)
+    }
+
+    fn load(&self, name: &str) -> Result<String> {
+        Ok(fs::read_to_string(self.dir.join(name))?)
+    }
+
+    fn save(&self, name: &str, content: &str) -> Result<()> {
+        let _lock = self.lock()?;
+        self.save_locked(name, content)
+    }
+
+    fn save_if_unchanged(&self, name: &str, content: &str, revision: Option<&str>) -> Result<()> {
+        let _lock = self.lock()?;
+        let stored = if self.dir.join(name).exists() {
+            self.revision(name)?
+        } else {
+            None
+        };
+        tcx_ensure!(
+            stored.as_ref().map(String::as_str) == revision,
+            Error::KeystoreChanged
+        );
+        self.save_locked(name, content)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let _lock = self.lock()?;
+        fs::remove_file(self.dir.join(name))?;
+        self.revisions.lock().remove(name);
+
+        // the backup must go too, or the deleted wallet could come back from it
+        self.delete_backup_locked(name)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let _lock = self.lock()?;
+        let to_path = self.dir.join(to);
+        tcx_ensure!(!to_path.exists(), already_exists(to));
+        if let Some(parent) = to_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(self.dir.join(from), to_path)?;
+        self.revisions.lock().remove(from);
+        Ok(())
+    }
+
+    fn load_backup(&self, name: &str) -> Result<Option<String>> {
+        let backup = self.backup_path(name);
+        if !backup.exists() {
+            return Ok(None);
+        }
+        Ok(Some(fs::read_to_string(backup)?))
+    }
+
+    fn delete_backup(&self, name: &str) -> Result<()> {
+        let _lock = self.lock()?;
+        self.delete_backup_locked(name)?;
+        self.sync_dir()
+    }
+
+    /// the hash of the content, the modified time may not change between two quick saves. The
+    /// file is only read and hashed when its stamp differs from the last hash of it
+    fn revision(&self, name: &str) -> Result<Option<String>> {
+        let path = self.dir.join(name);
+        // stamped before the read, a change in between only costs another hash next time
+        let stamp = FileStamp::of(&path)?;
+        if let Some((hashed, revision)) = self.revisions.lock().get(name) {
+            if *hashed == stamp {
+                return Ok(Some(revision.clone()));
+            }
+        }
+        let revision = str_dsha256(&fs::read_to_string(&path)?);
+        self.revisions
+            .lock()
+            .insert(name.to_string(), (stamp, revision.clone()));
+        Ok(Some(revision))
+    }
+}
+
+fn write_synced(path: &Path, content: &str) -> Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn lock_exclusive(file: &fs::File) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn lock_exclusive(_file: &fs::File) -> io::Result<()> {
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct MemoryStorage {
+    entries: RwLock<BTreeMap<String, String>>,
+}
+
+impl KeystoreStorage for MemoryStorage {
+    fn list(&self) -> Result<Vec<String>> {
+        Ok(self
+            .entries
+            .read()
+            .keys()
+            .filter(|name| !name.starts_with(QUARANTINE))
+            .cloned()
+            .collect())
+    }
+
+    fn load(&self, name: &str) -> Result<String> {
+        self.entries
+            .read()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| not_found(name))
+    }
+
+    fn save(&self, name: &str, content: &str) -> Result<()> {
+        self.entries
+            .write()
+            .insert(name.to_string(), content.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        self.entries
+            .write()
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| not_found(name))
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let mut entries = self.entries.write();
+        tcx_ensure!(!entries.contains_key(to), already_exists(to));
+        let content = entries.remove(from).ok_or_else(|| not_found(from))?;
+        entries.insert(to.to_string(), content);
+        Ok(())
+    }
+}
+
+/// All keystores in one database, every statement is a transaction so the writes are atomic, and
+/// sqlite locks the database against the other processes
+pub struct SqliteStorage {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        // wait for the writes of the other processes instead of failing at once
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS keystore (name TEXT PRIMARY KEY, content TEXT NOT NULL)",
+            NO_PARAMS,
+        )?;
+        Ok(SqliteStorage {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl KeystoreStorage for SqliteStorage {
+    fn list(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare("SELECT name FROM keystore WHERE name NOT LIKE ?1")?;
+        let names = stmt
+            .query_map(params![format!("{}%", QUARANTINE)], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(names)
+    }
+
+    fn load(&self, name: &str) -> Result<String> {
+        let conn = self.conn.lock();
+        match conn.query_row(
+            "SELECT content FROM keystore WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        ) {
+            Ok(content) => Ok(content),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Err(not_found(name)),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn save(&self, name: &str, content: &str) -> Result<()> {
+        self.conn.lock().execute(
+            "INSERT OR REPLACE INTO keystore (name, content) VALUES (?1, ?2)",
+            params![name, content],
+        )?;
+        Ok(())
+    }
+
+    fn save_if_unchanged(&self, name: &str, content: &str, revision: Option<&str>) -> Result<()> {
+        let mut conn = self.conn.lock();
+        // an immediate transaction takes the write lock of the database before the check
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let stored: Option<String> = tx
+            .query_row(
+                "SELECT content FROM keystore WHERE name = ?1",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?;
+        tcx_ensure!(
+            stored
+                .map(|stored| str_dsha256(&stored))
+                .as_ref()
+                .map(String::as_str)
+                == revision,
+            Error::KeystoreChanged
+        );
+        tx.execute(
+            "INSERT OR REPLACE INTO keystore (name, content) VALUES (?1, ?2)",
+            params![name, content],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let deleted = self
+            .conn
+            .lock()
+            .execute("DELETE FROM keystore WHERE name = ?1", params![name])?;
+        tcx_ensure!(deleted > 0, not_found(name));
+        Ok(())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        // the primary key fails the update if `to` exists
+        let renamed = self.conn.lock().execute(
+            "UPDATE keystore SET name = ?2 WHERE name = ?1",
+            params![from, to],
+        )?;
+        tcx_ensure!(renamed > 0, not_found(from));
+        Ok(())
+    }
+
+    /// the hash of the content like the directory
+    fn revision(&self, name: &str) -> Result<Option<String>> {
+        Ok(Some(str_dsha256(&self.load(name)?)))
+    }
+}
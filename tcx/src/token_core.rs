@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+use prost::Message;
+use tcx_chain::tcx_ensure;
+use tcx_chain::{Keystore, KeystoreGuard, DEFAULT_MAX_UNLOCK_FAILURES};
+use tcx_crypto::{
+    default_kdf_rounds, Argon2Params, Cipher, Kdf, Pbkdf2Params, SCryptParams, Secret, Zeroizing,
+    XPUB_COMMON_IV, XPUB_COMMON_KEY_128,
+};
+
+use crate::api::KdfParam;
+use crate::error_handling::Result;
+use crate::handler::encode_message;
+use crate::session::Session;
+use crate::storage::{KeystoreStorage, StorageType};
+
+/// Config of a TokenCore instance
+///
+/// `kdf_rounds` and `cipher` are only used when creating new keystores, existing keystores keep
+/// their own params unless `upgrade_cipher` is on, then they are re-encrypted by `cipher` on the
+/// next successful unlock by password. `storage` picks the backend keeping the keystores in
+/// `file_dir`. A keystore is locked out after `max_unlock_failures` failed unlocks by password in
+/// a row
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub file_dir: String,
+    pub xpub_common_key: String,
+    pub xpub_common_iv: String,
+    pub kdf_rounds: u32,
+    pub cipher: Cipher,
+    pub upgrade_cipher: bool,
+    pub storage: StorageType,
+    pub max_unlock_failures: u32,
+    pub is_debug: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            file_dir: "../test-data".to_string(),
+            xpub_common_key: XPUB_COMMON_KEY_128.read().to_string(),
+            xpub_common_iv: XPUB_COMMON_IV.read().to_string(),
+            kdf_rounds: default_kdf_rounds(),
+            cipher: Cipher::default(),
+            upgrade_cipher: false,
+            storage: StorageType::default(),
+            max_unlock_failures: DEFAULT_MAX_UNLOCK_FAILURES,
+            is_debug: false,
+        }
+    }
+}
+
+/// A handle that owns a keystore folder and all keystores loaded from it
+///
+/// Every api of `call_tcx_api` is exposed as a method, so one process can host several wallet
+/// directories by creating several instances.
+pub struct TokenCore {
+    keystore_map: RwLock<HashMap<String, Keystore>>,
+    sessions: RwLock<HashMap<String, Session>>,
+    config: RwLock<Config>,
+    storage: RwLock<Option<Arc<dyn KeystoreStorage>>>,
+    /// storage entry name => (keystore id, revision when it was read or written by this instance)
+    stored_revisions: RwLock<HashMap<String, (String, Option<String>)>>,
+}
+
+impl Default for TokenCore {
+    fn default() -> Self {
+        TokenCore::new(Config::default())
+    }
+}
+
+impl TokenCore {
+    pub fn new(config: Config) -> TokenCore {
+        TokenCore {
+            keystore_map: RwLock::new(HashMap::new()),
+            sessions: RwLock::new(HashMap::new()),
+            config: RwLock::new(config),
+            storage: RwLock::new(None),
+            stored_revisions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> Config {
+        self.config.read().clone()
+    }
+
+    pub fn set_config(&self, config: Config) {
+        let storage_changed = {
+            let current = self.config.read();
+            current.file_dir != config.file_dir || current.storage != config.storage
+        };
+        *self.config.write() = config;
+        if storage_changed {
+            *self.storage.write() = None;
+        }
+    }
+
+    /// replace the configured storage backend, until the file dir or the storage type changes
+    pub fn set_storage(&self, storage: Arc<dyn KeystoreStorage>) {
+        *self.storage.write() = Some(storage);
+    }
+
+    /// the storage backend, opened by the config on the first use
+    pub(crate) fn storage(&self) -> Result<Arc<dyn KeystoreStorage>> {
+        let config = self.config();
+        let mut storage = self.storage.write();
+        let opened = match storage.as_ref() {
+            Some(opened) => Arc::clone(opened),
+            None => config.storage.open(&config.file_dir)?,
+        };
+        *storage = Some(Arc::clone(&opened));
+        Ok(opened)
+    }
+
+    pub(crate) fn keystore_map(&self) -> &RwLock<HashMap<String, Keystore>> {
+        &self.keystore_map
+    }
+
+    pub(crate) fn stored_revisions(&self) -> &RwLock<HashMap<String, (String, Option<String>)>> {
+        &self.stored_revisions
+    }
+
+    pub(crate) fn sessions(&self) -> &RwLock<HashMap<String, Session>> {
+        &self.sessions
+    }
+
+    /// kdf params of a new keystore, zero params of `param` fall back to the defaults
+    pub(crate) fn kdf_params(&self, param: Option<&KdfParam>) -> Result<Kdf> {
+        let param = param.cloned().unwrap_or_default();
+        let kdf: Kdf = match param.kdf.as_str() {
+            "" | "pbkdf2" => {
+                let rounds = if param.c == 0 {
+                    self.config.read().kdf_rounds
+                } else {
+                    param.c
+                };
+                Pbkdf2Params::with_rounds(rounds).into()
+            }
+            "scrypt" => SCryptParams::with_params(param.n, param.r, param.p).into(),
+            "argon2id" => {
+                Argon2Params::with_params(param.memory, param.time, param.parallelism).into()
+            }
+            _ => return Err(tcx_crypto::Error::KdfParamsInvalid.into()),
+        };
+        kdf.validate_cost()?;
+        Ok(kdf)
+    }
+
+    pub(crate) fn cipher(&self) -> Cipher {
+        self.config.read().cipher
+    }
+
+    /// unlock by the password, the keystore is upgraded to the configured cipher if
+    /// `upgrade_cipher` is on. The keystore is flushed when it's upgraded or the failed unlocks
+    /// change, so the throttling survives a restart
+    pub(crate) fn unlock_by_password<'a>(
+        &self,
+        keystore: &'a mut Keystore,
+        password: &str,
+    ) -> Result<KeystoreGuard<'a>> {
+        let (cipher, upgrade_cipher, max_failures) = {
+            let config = self.config.read();
+            (
+                config.cipher,
+                config.upgrade_cipher,
+                config.max_unlock_failures,
+            )
+        };
+        let failed_unlocks = keystore.failed_unlocks();
+        let unlocked = if upgrade_cipher {
+            keystore
+                .get_derived_key_throttled(password, max_failures)
+                .and_then(|derived_key| {
+                    let derived_key = Zeroizing::new(derived_key);
+                    let upgraded = keystore.upgrade_cipher(&derived_key, cipher)?;
+                    keystore.unlock_by_derived_key(&derived_key)?;
+                    Ok(upgraded)
+                })
+        } else {
+            keystore
+                .unlock_by_password(password, max_failures)
+                .map(|_| false)
+        };
+
+        match unlocked {
+            Ok(upgraded) => {
+                let changed = upgraded || keystore.failed_unlocks() != failed_unlocks;
+                let guard = KeystoreGuard::from_unlocked(keystore);
+                if changed {
+                    self.flush_keystore(guard.keystore())?;
+                }
+                Ok(guard)
+            }
+            Err(err) => {
+                if keystore.failed_unlocks() != failed_unlocks {
+                    self.flush_keystore(keystore)?;
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// verify the password under the unlock throttling, the failed unlocks are flushed like
+    /// `unlock_by_password`
+    pub(crate) fn verify_password(&self, keystore: &mut Keystore, password: &str) -> Result<()> {
+        let failed_unlocks = keystore.failed_unlocks();
+        let max_failures = self.config.read().max_unlock_failures;
+        let verified = keystore.try_password(password, max_failures);
+        if keystore.failed_unlocks() != failed_unlocks {
+            self.flush_keystore(keystore)?;
+        }
+        verified
+    }
+
+    /// the derived key of the password, checked under the unlock throttling like
+    /// `verify_password`. The kdf runs once, its output is the check of the password
+    pub(crate) fn derive_key(
+        &self,
+        keystore: &mut Keystore,
+        password: &str,
+    ) -> Result<Secret<String>> {
+        let failed_unlocks = keystore.failed_unlocks();
+        let max_failures = self.config.read().max_unlock_failures;
+        let derived_key = keystore.get_derived_key_throttled(password, max_failures);
+        if keystore.failed_unlocks() != failed_unlocks {
+            self.flush_keystore(keystore)?;
+        }
+        derived_key.map(Secret::new)
+    }
+
+    pub(crate) fn encode_message(&self, msg: impl Message) -> Result<Vec<u8>> {
+        if self.config.read().is_debug {
+            println!("{:#?}", msg);
+        }
+        encode_message(msg)
+    }
+
+    pub fn lock_all_keystore(&self) {
+        let mut map = self.keystore_map.write();
+        for ks in map.values_mut() {
+            ks.lock();
+        }
+    }
+}
@@ -23,7 +23,7 @@ pushd ./tcx
 if [ ! -d cheader ]; then
   mkdir -p cheader
 fi
-RUST_BACKTRACE=1 cbindgen src/lib.rs -l c > cheader/tcx.h
+RUST_BACKTRACE=1 cbindgen --config cbindgen.toml src/lib.rs > cheader/tcx.h
 cargo lipo --release  --targets x86_64-apple-ios
 # cargo lipo --release  --targets aarch64-apple-ios,armv7-apple-ios,armv7s-apple-ios,x86_64-apple-ios,i386-apple-ios
 cp cheader/tcx.h $LIBS/Include